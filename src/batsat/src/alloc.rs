@@ -20,10 +20,11 @@ OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWA
 
 use std::cmp;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::marker::PhantomData;
 use std::ops;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct RegionAllocator<T: Copy> {
     vec: Vec<T>,
     wasted: usize,
@@ -100,6 +101,11 @@ impl<T: Copy> Ord for Ref<T> {
         Ord::cmp(&self.0, &rhs.0)
     }
 }
+impl<T: Copy> Hash for Ref<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
 impl<T: Copy> Default for Ref<T> {
     fn default() -> Self {
         Ref(0, PhantomData)
@@ -21,7 +21,9 @@ OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWA
 
 use std::fmt;
 use std::iter::DoubleEndedIterator;
+use std::mem;
 use std::ops;
+use std::slice;
 use std::u32;
 use smallvec::SmallVec;
 
@@ -41,10 +43,19 @@ impl fmt::Debug for Var {
     }
 }
 
+/// DIMACS representation of the variable (its index plus one)
+impl fmt::Display for Var {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
 impl Var {
     pub const UNDEF: Var = Var(!0);
+    /// The variable of index `idx`: solvers number their variables densely from `0`
+    /// (see `SolverInterface::num_vars`).
     #[inline(always)]
-    pub(crate) fn from_idx(idx: u32) -> Self {
+    pub fn from_idx(idx: u32) -> Self {
         debug_assert!(idx < u32::MAX / 2, "Var::from_idx: index too large");
         Var(idx)
     }
@@ -66,16 +77,48 @@ impl AsIndex for Var {
 pub type VMap<V> = IntMap<Var, V>;
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
 pub struct Lit(u32);
 
 impl Lit {
     pub const UNDEF: Lit = Lit(!1);
     pub const ERROR: Lit = Lit(!0);
 
+    /// The literal of `var` with the given sign: `true` for the positive
+    /// literal `var`, `false` for its negation `!var`.
+    ///
+    /// ```
+    /// use batsat::{Lit, Var};
+    /// let v = Var::from_idx(0);
+    /// assert!(Lit::new(v, true).sign());
+    /// assert_eq!(Lit::new(v, true), Lit::positive(v));
+    /// assert_eq!(Lit::new(v, false), !Lit::positive(v));
+    /// assert_eq!(format!("{}", Lit::new(v, false)), "-1");
+    /// ```
     #[inline(always)]
     pub fn new(var: Var, sign: bool) -> Self {
         Lit(var.0 * 2 + (!sign) as u32)
     }
+    /// The positive literal of `var`, same as `Lit::new(var, true)`.
+    #[inline(always)]
+    pub fn positive(var: Var) -> Self {
+        Lit::new(var, true)
+    }
+    /// The negative literal of `var`, same as `Lit::new(var, false)`.
+    ///
+    /// ```
+    /// use batsat::{Lit, Var};
+    /// let v = Var::from_idx(2);
+    /// let neg = Lit::negative(v);
+    /// assert!(!neg.sign());
+    /// assert_eq!(neg.var(), v);
+    /// assert_eq!(!neg, Lit::positive(v));
+    /// assert_eq!(neg ^ true, Lit::positive(v));
+    /// ```
+    #[inline(always)]
+    pub fn negative(var: Var) -> Self {
+        Lit::new(var, false)
+    }
     #[inline(always)]
     pub(crate) fn from_idx(idx: u32) -> Self {
         Lit(idx)
@@ -84,10 +127,12 @@ impl Lit {
     pub fn idx(&self) -> u32 {
         self.0
     }
+    /// `true` if the literal is positive.
     #[inline(always)]
     pub fn sign(&self) -> bool {
         (self.0 & 1) == 0
     }
+    /// The variable of the literal, regardless of its sign.
     #[inline(always)]
     pub fn var(&self) -> Var {
         Var(self.0 >> 1)
@@ -106,6 +151,13 @@ impl fmt::Debug for Lit {
     }
 }
 
+/// DIMACS representation of the literal (`3` or `-3` for the variable `3`)
+impl fmt::Display for Lit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
 impl ops::Not for Lit {
     type Output = Self;
     #[inline(always)]
@@ -264,6 +316,7 @@ pub(crate) struct ClauseRef<'a> {
     header: ClauseHeader,
     data: &'a [ClauseData],
     extra: Option<ClauseData>,
+    touched: Option<ClauseData>,
 }
 #[derive(Debug)]
 /// A mutable reference to some clause, with a temporary lifetime
@@ -271,6 +324,7 @@ pub(crate) struct ClauseMut<'a> {
     header: &'a mut ClauseHeader,
     data: &'a mut [ClauseData],
     extra: Option<&'a mut ClauseData>,
+    touched: Option<&'a mut ClauseData>,
 }
 
 impl<'a, 'b> PartialEq<ClauseRef<'b>> for ClauseRef<'a> {
@@ -312,6 +366,12 @@ impl<'a> ClauseRef<'a> {
         debug_assert!(self.has_extra());
         unsafe { self.extra.expect("no extra field").u32 }
     }
+    /// Stamp of the last propagation of a learnt clause (0 if none).
+    #[inline(always)]
+    pub fn touched(&self) -> u32 {
+        debug_assert!(self.learnt());
+        unsafe { self.touched.expect("not a learnt clause").u32 }
+    }
     #[inline(always)]
     pub fn relocation(&self) -> CRef {
         debug_assert!(self.reloced());
@@ -321,6 +381,12 @@ impl<'a> ClauseRef<'a> {
     pub fn iter(& self) -> impl DoubleEndedIterator<Item=&'a Lit> {
         self.data.iter().map(|lit| unsafe { &lit.lit })
     }
+    /// The literals of the clause, as a slice
+    pub fn lits(&self) -> &'a [Lit] {
+        // `ClauseData` is a `repr(C)` union of 32-bit fields, so a slice of it
+        // can be read as a slice of `Lit` (a `repr(transparent)` `u32`)
+        unsafe { slice::from_raw_parts(self.data.as_ptr() as *const Lit, self.data.len()) }
+    }
 }
 
 /// Anything that can be considered as a list of literals.
@@ -338,7 +404,7 @@ impl<T: ClauseIterable> display::Print for T {
     fn fmt_dimacs(&self, out: &mut fmt::Formatter) -> fmt::Result {
         for &x in self.items().iter() {
             let lit: Lit = x.into();
-            write!(out, "{}{} ", (if lit.sign() {""} else {"-"}), lit.var().idx()+1)?;
+            write!(out, "{} ", lit)?;
         }
         write!(out, "0")?;
         Ok(())
@@ -392,6 +458,11 @@ impl<'a> ClauseMut<'a> {
     pub fn size(&self) -> u32 {
         self.data.len() as u32
     }
+    /// The literals of the clause, as a slice
+    pub fn lits(&self) -> &[Lit] {
+        // see `ClauseRef::lits`
+        unsafe { slice::from_raw_parts(self.data.as_ptr() as *const Lit, self.data.len()) }
+    }
     #[inline(always)]
     pub fn set_mark(&mut self, mark: u32) {
         debug_assert!(mark < 4);
@@ -429,6 +500,11 @@ impl<'a> ClauseMut<'a> {
         debug_assert!(self.has_extra());
         self.extra.as_mut().expect("no extra field").u32 = abstraction;
     }
+    #[inline(always)]
+    pub fn set_touched(&mut self, stamp: u32) {
+        debug_assert!(self.learnt());
+        self.touched.as_mut().expect("not a learnt clause").u32 = stamp;
+    }
     pub fn relocation(&self) -> CRef {
         debug_assert!(self.reloced());
         unsafe { self.data[0].cref }
@@ -448,9 +524,21 @@ impl<'a> ClauseMut<'a> {
         debug_assert!(2 <= new_size);
         debug_assert!(new_size <= self.size());
         if new_size < self.size() {
+            let extra = self.extra.as_ref().map(|x| **x);
+            let touched = self.touched.as_ref().map(|x| **x);
             self.header.set_size(new_size);
-            if let Some(extra) = self.extra {
-                self.data[new_size as usize] = *extra;
+            let mut k = new_size as usize;
+            if let Some(extra) = extra {
+                self.data[k] = extra;
+                k += 1;
+            }
+            if let Some(touched) = touched {
+                if k < self.data.len() {
+                    self.data[k] = touched;
+                } else if let Some(old_extra) = self.extra {
+                    // shrunk by a single literal: the stamp takes the old extra slot
+                    *old_extra = touched;
+                }
             }
         }
     }
@@ -459,6 +547,7 @@ impl<'a> ClauseMut<'a> {
             header: *self.header,
             data: self.data,
             extra: self.extra.as_mut().map(|extra| **extra),
+            touched: self.touched.as_mut().map(|touched| **touched),
         }
     }
 }
@@ -482,7 +571,7 @@ impl<'a> ops::IndexMut<u32> for ClauseMut<'a> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 /// Main clause allocator. It stores a set of clauses efficiently.
 pub struct ClauseAllocator {
     ra: RegionAllocator<ClauseData>,
@@ -492,6 +581,7 @@ pub struct ClauseAllocator {
 #[derive(Clone, Copy)]
 /// Items used in the clause allocator. It should be compact enough that
 /// we do no waste space.
+#[repr(C)]
 pub(crate) union ClauseData {
     u32: u32,
     f32: f32,
@@ -567,6 +657,13 @@ impl ClauseHeader {
     pub fn size(&self) -> u32 {
         self.0 & ((1 << 27) - 1)
     }
+    /// Number of words after the literals: the extra field (activity or
+    /// abstraction), then the last propagation stamp of learnt clauses
+    #[inline(always)]
+    pub fn extra_len(&self) -> u32 {
+        debug_assert!(self.has_extra() || !self.learnt());
+        self.has_extra() as u32 + self.learnt() as u32
+    }
     pub fn set_mark(&mut self, mark: u32) {
         debug_assert!(mark < 4);
         self.0 = (self.0 & !(3 << 30)) | (mark << 30);
@@ -587,7 +684,8 @@ impl ClauseHeader {
 }
 
 impl ClauseAllocator {
-    pub const UNIT_SIZE: u32 = 32;
+    /// Size in bytes of a unit of the arena (`len` and `wasted` count units).
+    pub const UNIT_SIZE: u32 = mem::size_of::<ClauseData>() as u32;
     pub fn with_start_cap(start_cap: u32) -> Self {
         Self {
             ra: RegionAllocator::new(start_cap),
@@ -604,9 +702,17 @@ impl ClauseAllocator {
     pub fn wasted(&self) -> u32 {
         self.ra.wasted()
     }
+    /// Size in bytes of the arena (`len() * UNIT_SIZE`), including dead clauses.
+    pub fn bytes_used(&self) -> usize {
+        self.len() as usize * Self::UNIT_SIZE as usize
+    }
+    /// Size in bytes of the dead clauses, reclaimed by the next garbage collection.
+    pub fn bytes_wasted(&self) -> usize {
+        self.wasted() as usize * Self::UNIT_SIZE as usize
+    }
     pub(crate) fn alloc_with_learnt(&mut self, clause: &[Lit], learnt: bool) -> CRef {
         let use_extra = learnt | self.extra_clause_field;
-        let cid = self.ra.alloc(1 + clause.len() as u32 + use_extra as u32);
+        let cid = self.ra.alloc(1 + clause.len() as u32 + use_extra as u32 + learnt as u32);
         self.ra[cid].header = ClauseHeader::new(0, learnt, use_extra, false, clause.len() as u32);
         let clause_ptr = cid + 1;
         for (i, &lit) in clause.iter().enumerate() {
@@ -615,6 +721,7 @@ impl ClauseAllocator {
         if use_extra {
             if learnt {
                 self.ra[clause_ptr + clause.len() as u32].f32 = 0.0;
+                self.ra[clause_ptr + clause.len() as u32 + 1].u32 = 0;
             } else {
                 // NOTE: not used right now, but can be used to accelerate `lit_redundant`
                 let mut abstraction: u32 = 0;
@@ -630,7 +737,7 @@ impl ClauseAllocator {
 
     pub(crate) fn alloc_copy(&mut self, from: ClauseRef) -> CRef {
         let use_extra = from.learnt() | self.extra_clause_field;
-        let cid = self.ra.alloc(1 + from.size() + use_extra as u32);
+        let cid = self.ra.alloc(1 + from.size() + use_extra as u32 + from.learnt() as u32);
         self.ra[cid].header = from.header;
         // NOTE: the copied clause may lose the extra field.
         unsafe { &mut self.ra[cid].header }.set_has_extra(use_extra);
@@ -640,13 +747,16 @@ impl ClauseAllocator {
         if use_extra {
             self.ra[cid + 1 + from.size()] = from.extra.unwrap();
         }
+        if let Some(touched) = from.touched {
+            self.ra[cid + 2 + from.size()] = touched;
+        }
         cid
     }
 
     pub(crate) fn free(&mut self, cr: CRef) {
         let size = {
             let c = self.get_ref(cr);
-            1 + c.size() + c.has_extra() as u32
+            1 + c.size() + c.header.extra_len()
         };
         self.ra.free(size);
     }
@@ -682,27 +792,35 @@ impl ClauseAllocator {
         } else {
             None
         };
+        let touched = if header.learnt() {
+            Some(self.ra[cr + 2 + size])
+        } else {
+            None
+        };
         ClauseRef {
             header,
             data,
             extra,
+            touched,
         }
     }
 
     /// Get a mutable reference on the clause `cr` points to
     pub(crate) fn get_mut(&mut self, cr: CRef) -> ClauseMut {
         let header = unsafe { self.ra[cr].header };
-        let has_extra = header.has_extra();
         let size = header.size();
-        let len = 1 + size + has_extra as u32;
+        let len = 1 + size + header.extra_len();
 
         let subslice = self.ra.subslice_mut(cr, len);
         let (subslice0, subslice) = subslice.split_at_mut(1);
         let (subslice1, subslice2) = subslice.split_at_mut(size as usize);
+        // the extra field comes first, then the stamp of learnt clauses
+        let mut extras = subslice2.iter_mut();
         ClauseMut {
             header: unsafe { &mut subslice0[0].header },
             data: subslice1,
-            extra: subslice2.first_mut(),
+            extra: extras.next(),
+            touched: extras.next(),
         }
     }
 }
@@ -944,5 +1062,16 @@ mod test {
         assert_eq!(lbool::UNDEF | lbool::TRUE, lbool::TRUE);
         assert_eq!(lbool::TRUE | lbool::UNDEF, lbool::TRUE);
     }
+    #[test]
+    fn test_display_dimacs() {
+        use super::{Lit, Var};
+        use super::display::Print;
+        let v = Var::from_idx(2);
+        assert_eq!(format!("{}", v), "3");
+        assert_eq!(format!("{}", Lit::new(v, true)), "3");
+        assert_eq!(format!("{}", Lit::new(v, false)), "-3");
+        let c = vec![Lit::new(v, false), Lit::new(Var::from_idx(0), true)];
+        assert_eq!(format!("{}", c.pp_dimacs()), "-3 1 0");
+    }
 }
 
@@ -26,11 +26,29 @@ use std::mem;
 use std::sync::atomic::{Ordering,AtomicBool};
 use std::fmt;
 use std::fmt::Write;
+use std::error;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+use std::collections::hash_map::DefaultHasher;
+use std::io::{self, BufRead};
 use {lbool, Lit, Var};
 use intmap::{Comparator, Heap, HeapData, PartialComparator};
 use clause::{CRef, ClauseAllocator, ClauseRef, DeletePred, LSet, OccLists, OccListsData,
     VMap, ClauseIterable};
 use interface::*;
+use dimacs;
+#[cfg(feature="parallel")]
+use rayon::prelude::*;
+
+pub use self::preprocess::{Preprocessor, PreprocessReport};
+pub use self::structure::FormulaClass;
+use self::lrat::Lrat;
+
+mod preprocess;
+mod structure;
+mod maxsat;
+mod lrat;
 
 #[derive(Debug)]
 pub struct Solver {
@@ -43,6 +61,8 @@ pub struct Solver {
 
     // Mode of operation:
     verbosity: i32,
+    /// Level of the search trace (see `set_trace`).
+    trace: u8,
     var_decay: f64,
     clause_decay: f64,
     random_var_freq: f64,
@@ -54,17 +74,69 @@ pub struct Solver {
     phase_saving: i32,
     /// Use random polarities for branching heuristics.
     rnd_pol: bool,
+    /// Saved phase of new variables (`false`: their first decision makes them false).
+    default_polarity: bool,
+    /// User callback choosing the polarity of decisions.
+    polarity_cb: Callback<dyn FnMut(Var) -> lbool>,
+    /// User callback choosing the next decision variable.
+    decision_cb: Callback<DecisionFn>,
+    /// Variable of the next decision, if any (see `set_first_decision`).
+    first_decision: Var,
+    /// Decision variables made non-decision by `set_decision_scope`.
+    out_of_scope: Vec<Var>,
+    /// User callback notified of assignments and unassignments.
+    assign_cb: Callback<dyn FnMut(Lit, bool)>,
+    /// User callback notified of reductions of the clause database.
+    reduce_cb: Callback<dyn FnMut(usize, usize)>,
+    /// User callback that can prevent the deletion of learnt clauses.
+    keep_cb: Callback<KeepClauseFn>,
+    /// External propagator, called when unit propagation reaches a fixpoint.
+    ext_propagator: Callback<dyn Propagator>,
     /// Initialize variable activities with a small random value.
     rnd_init_act: bool,
     /// The fraction of wasted memory allowed before a garbage collection is triggered.
     garbage_frac: f64,
+    /// Amount of wasted memory (in bytes) that triggers a garbage collection,
+    /// regardless of `garbage_frac`.
+    garbage_min_bytes: usize,
     /// Minimum number to set the learnts limit to.
     min_learnts_lim: i32,
+    /// Keep the learnt clauses that were a reason during the last `reduce_protect`
+    /// conflicts when reducing the clause database (0 to disable).
+    reduce_protect: u64,
+    /// Skip original clauses that are identical to an already present one.
+    detect_duplicates: bool,
+    /// Order of the literals of original clauses, and choice of their watches.
+    watch_select: WatchSelect,
+    /// Which UIP clauses are considered by conflict analysis.
+    uip_mode: UipMode,
+    /// Order of the literals of learnt clauses, after the asserting literal.
+    learnt_sort: LearntSort,
+    /// Learnt clauses longer than this are only kept until the next restart.
+    max_learnt_len: usize,
+    /// Record the size and LBD of each learnt clause.
+    learnt_histograms: bool,
+    /// Record the distance of each backjump.
+    backjump_histogram: bool,
+    /// Propagate unit clauses as soon as they are added.
+    eager_unit_propagation: bool,
+    /// Maintain occurrence lists of original clauses (see `set_simp_mode`).
+    use_simp: bool,
+    /// Bump the variables of each learnt clause once more, after minimization.
+    bump_learnt_lits: bool,
 
     /// The initial restart limit. (default 100)
     restart_first: i32,
     /// The factor with which the restart limit is multiplied in each restart. (default 1.5)
     restart_inc: f64,
+    /// Maximum relative random perturbation of each restart limit. (default 0)
+    restart_jitter: f64,
+    /// Lay out clauses in the order of `learnts` and `clauses` when collecting garbage.
+    gc_locality: bool,
+    /// Search interrupted by `solve_steps`, to be resumed by the next call.
+    steps: Option<Steps>,
+    /// Result of the last call to `solve` (`UNDEF` before the first one).
+    last_result: lbool,
     /// The intitial limit for learnt clauses is a factor of the original clauses. (default 1 / 3)
     learntsize_factor: f64,
     /// The limit for learnt clauses is multiplied with this factor each restart. (default 1.1)
@@ -72,6 +144,8 @@ pub struct Solver {
 
     produce_proof: bool,
     proof: Proof, // DRAT proof
+    /// LRAT proof, if enabled with `set_lrat_proof`.
+    lrat: Option<Lrat>,
 
     learntsize_adjust_start_confl: i32,
     learntsize_adjust_inc: f64,
@@ -90,12 +164,27 @@ pub struct Solver {
     // v.learnts_literals: u64,
     max_literals: u64,
     tot_literals: u64,
+    /// Number of learnt clauses by size (if `learnt_histograms`).
+    learnt_size_hist: Vec<u64>,
+    /// Number of learnt clauses by LBD (if `learnt_histograms`).
+    learnt_lbd_hist: Vec<u64>,
+    /// Number of backjumps by distance in decision levels (if `backjump_histogram`).
+    backjump_hist: Vec<u64>,
 
     // Solver state:
     /// List of problem clauses.
     clauses: Vec<CRef>,
     /// List of learnt clauses.
     learnts: Vec<CRef>,
+    /// Learnt clauses longer than `max_learnt_len`, removed when backtracking to level 0.
+    transient: Vec<CRef>,
+    /// Original clauses indexed by the hash of their sorted literals (only if `detect_duplicates`).
+    clause_hashes: HashMap<u64, Vec<CRef>>,
+    /// Clauses referred to by a `ClauseHandle` (`CRef::UNDEF` once removed).
+    clause_handles: Vec<CRef>,
+    /// Clauses removed by `preprocess`, used to extend the model to eliminated
+    /// variables. The first literal of each clause is the one to flip.
+    elim_clauses: Vec<Vec<Lit>>,
     // /// Assignment stack; stores all assigments made in the order they were made.
     // v.trail: Vec<Lit>,
     // /// Separator indices for different decision levels in 'trail'.
@@ -113,10 +202,14 @@ pub struct Solver {
     user_pol: VMap<lbool>,
     /// Declares if a variable is eligible for selection in the decision heuristic.
     decision: VMap<bool>,
+    /// Variables removed from the formula by `preprocess`.
+    eliminated: VMap<bool>,
     // /// Stores reason and level for each variable.
     // v.vardata: VMap<VarData>,
     /// 'watches[lit]' is a list of constraints watching 'lit' (will go there if literal becomes true).
     watches_data: OccListsData<Lit, Watcher>,
+    /// 'occurs[var]' is a list of original clauses containing 'var' (only if `use_simp`).
+    occurs: OccListsData<Var, CRef>,
     /// A priority queue of variables ordered with respect to the variable activity.
     order_heap_data: HeapData<Var>,
     /// If FALSE, the constraints are already unsatisfiable. No part of the solver state may be used!
@@ -127,12 +220,27 @@ pub struct Solver {
     // v.var_inc: f64,
     /// Head of queue (as index into the trail -- no more explicit propagation queue in MiniSat).
     qhead: i32,
+    /// Number of literals of the trail already reported to `assign_cb`.
+    assign_cb_head: usize,
     /// Number of top-level assignments since last execution of 'simplify()'.
     simp_db_assigns: i32,
     /// Remaining number of propagations that must be made before next execution of 'simplify()'.
     simp_db_props: i64,
+    /// If non-zero, number of conflicts between executions of 'simplify()', instead of 'simp_db_props'.
+    simp_interval: u64,
+    /// If non-negative, value of 'simp_db_props' after 'simplify()', instead of the
+    /// number of literals in the clause database.
+    simp_props_threshold: i64,
+    /// Number of conflicts at the last execution of 'simplify()'.
+    simp_db_conflicts: u64,
     /// Set by 'search()'.
     progress_estimate: f64,
+    /// LBD of the last learnt clause.
+    last_learnt_lbd: u32,
+    /// LBD of the last learnt clause before the last restart.
+    last_restart_lbd: u32,
+    /// Length of the trail when the last restart was decided.
+    last_restart_trail: usize,
     /// Indicates whether possibly inefficient linear scan for satisfied clauses should be performed in 'simplify'.
     remove_satisfied: bool,
     /// Next variable to be created.
@@ -147,6 +255,9 @@ pub struct Solver {
     analyze_stack: Vec<ShrinkStackElem>,
     analyze_toclear: Vec<Lit>,
     add_tmp: Vec<Lit>,
+    /// `lbd_seen[level] == lbd_stamp` iff `level` was already counted by `compute_lbd`.
+    lbd_seen: Vec<u64>,
+    lbd_stamp: u64,
 
     max_learnts: f64,
     learntsize_adjust_confl: f64,
@@ -160,7 +271,7 @@ pub struct Solver {
 
     v: SolverV,
 }
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct SolverV {
     /// A heuristic measurement of the activity of a variable.
     activity: VMap<f64>,
@@ -174,11 +285,23 @@ struct SolverV {
     vardata: VMap<VarData>,
     /// Amount to bump next variable with.
     var_inc: f64,
+    /// Record how often, and at which level, each variable is assigned.
+    var_stats: bool,
+    /// Number of times each variable was assigned (if `var_stats`).
+    var_assigns: VMap<u64>,
+    /// Sum of the levels at which each variable was assigned (if `var_stats`).
+    var_levels: VMap<u64>,
+    /// Variables assigned at level 0, to be removed from the order heap.
+    fixed_pending: Vec<Var>,
 
     num_clauses: u64,
     num_learnts: u64,
     clauses_literals: u64,
     learnts_literals: u64,
+    /// Number of learnt clauses whose LBD was at most 2 when they were learnt.
+    glue_clauses: u64,
+    /// Comparator of the order heap, if not the default one (see `set_order_comparator`).
+    order_cmp: OrderCmp,
 }
 
 impl Default for Solver {
@@ -187,6 +310,133 @@ impl Default for Solver {
     }
 }
 
+/// Fork the solver, with its clauses, learnt clauses, heuristic state and options.
+///
+/// Callbacks are not carried over: the clone has no stop predicate, no polarity,
+/// decision, assignment, reduce or keep clause callback, and no external
+/// propagator (see `set_stop_pred`, `set_polarity_callback`,
+/// `set_decision_callback`, `set_assignment_callback`, `set_reduce_callback`,
+/// `set_keep_clause_callback` and `set_external_propagator`). The custom order
+/// of `set_order_comparator`, if any, is shared with the clone. Temporary
+/// buffers start empty.
+impl Clone for Solver {
+    fn clone(&self) -> Self {
+        let mut seen = VMap::new();
+        for v in self.vars() {
+            seen.insert_default(v, Seen::UNDEF);
+        }
+        Self {
+            model: self.model.clone(),
+            conflict: self.conflict.clone(),
+            verbosity: self.verbosity,
+            trace: self.trace,
+            var_decay: self.var_decay,
+            clause_decay: self.clause_decay,
+            random_var_freq: self.random_var_freq,
+            random_seed: self.random_seed,
+            luby_restart: self.luby_restart,
+            ccmin_mode: self.ccmin_mode,
+            phase_saving: self.phase_saving,
+            rnd_pol: self.rnd_pol,
+            default_polarity: self.default_polarity,
+            polarity_cb: Callback::none(),
+            decision_cb: Callback::none(),
+            first_decision: self.first_decision,
+            out_of_scope: self.out_of_scope.clone(),
+            assign_cb: Callback::none(),
+            reduce_cb: Callback::none(),
+            keep_cb: Callback::none(),
+            ext_propagator: Callback::none(),
+            rnd_init_act: self.rnd_init_act,
+            garbage_frac: self.garbage_frac,
+            garbage_min_bytes: self.garbage_min_bytes,
+            min_learnts_lim: self.min_learnts_lim,
+            reduce_protect: self.reduce_protect,
+            detect_duplicates: self.detect_duplicates,
+            watch_select: self.watch_select,
+            uip_mode: self.uip_mode,
+            learnt_sort: self.learnt_sort,
+            max_learnt_len: self.max_learnt_len,
+            learnt_histograms: self.learnt_histograms,
+            backjump_histogram: self.backjump_histogram,
+            eager_unit_propagation: self.eager_unit_propagation,
+            use_simp: self.use_simp,
+            bump_learnt_lits: self.bump_learnt_lits,
+            restart_first: self.restart_first,
+            restart_inc: self.restart_inc,
+            restart_jitter: self.restart_jitter,
+            gc_locality: self.gc_locality,
+            steps: self.steps.clone(),
+            last_result: self.last_result,
+            learntsize_factor: self.learntsize_factor,
+            learntsize_inc: self.learntsize_inc,
+            produce_proof: self.produce_proof,
+            proof: self.proof.clone(),
+            lrat: self.lrat.clone(),
+            learntsize_adjust_start_confl: self.learntsize_adjust_start_confl,
+            learntsize_adjust_inc: self.learntsize_adjust_inc,
+            solves: self.solves,
+            starts: self.starts,
+            decisions: self.decisions,
+            rnd_decisions: self.rnd_decisions,
+            propagations: self.propagations,
+            conflicts: self.conflicts,
+            dec_vars: self.dec_vars,
+            max_literals: self.max_literals,
+            tot_literals: self.tot_literals,
+            learnt_size_hist: self.learnt_size_hist.clone(),
+            learnt_lbd_hist: self.learnt_lbd_hist.clone(),
+            backjump_hist: self.backjump_hist.clone(),
+            clauses: self.clauses.clone(),
+            learnts: self.learnts.clone(),
+            transient: self.transient.clone(),
+            clause_hashes: self.clause_hashes.clone(),
+            clause_handles: self.clause_handles.clone(),
+            elim_clauses: self.elim_clauses.clone(),
+            assumptions: self.assumptions.clone(),
+            polarity: self.polarity.clone(),
+            user_pol: self.user_pol.clone(),
+            decision: self.decision.clone(),
+            eliminated: self.eliminated.clone(),
+            watches_data: self.watches_data.clone(),
+            occurs: self.occurs.clone(),
+            order_heap_data: self.order_heap_data.clone(),
+            ok: self.ok,
+            cla_inc: self.cla_inc,
+            qhead: self.qhead,
+            assign_cb_head: self.assign_cb_head,
+            simp_db_assigns: self.simp_db_assigns,
+            simp_db_props: self.simp_db_props,
+            simp_interval: self.simp_interval,
+            simp_props_threshold: self.simp_props_threshold,
+            simp_db_conflicts: self.simp_db_conflicts,
+            progress_estimate: self.progress_estimate,
+            last_learnt_lbd: self.last_learnt_lbd,
+            last_restart_lbd: self.last_restart_lbd,
+            last_restart_trail: self.last_restart_trail,
+            remove_satisfied: self.remove_satisfied,
+            next_var: self.next_var,
+            ca: self.ca.clone(),
+            free_vars: self.free_vars.clone(),
+            // scratch buffers: never share stale state with the original
+            seen,
+            analyze_stack: vec![],
+            analyze_toclear: vec![],
+            add_tmp: vec![],
+            lbd_seen: vec![],
+            lbd_stamp: 0,
+            max_learnts: self.max_learnts,
+            learntsize_adjust_confl: self.learntsize_adjust_confl,
+            learntsize_adjust_cnt: self.learntsize_adjust_cnt,
+            conflict_budget: self.conflict_budget,
+            propagation_budget: self.propagation_budget,
+            asynch_interrupt: AtomicBool::new(self.has_been_interrupted()),
+            stop_pred: StopPredicate::none(),
+            v: self.v.clone(),
+        }
+    }
+}
+
 /// Print the model/proof as DIMACS
 pub struct SolverPrintDimacs<'a> {
     s: &'a Solver,
@@ -209,7 +459,7 @@ impl<'a> fmt::Display for SolverPrintDimacs<'a> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Proof(Vec<i32>);
 
 impl fmt::Display for Proof {
@@ -273,6 +523,44 @@ impl fmt::Debug for StopPredicate {
     }
 }
 
+/// Ordering of two variables given their activities, see `set_order_comparator`
+type OrderCmpFn = dyn Fn(&VMap<f64>, Var, Var) -> cmp::Ordering;
+
+/// Optional user-provided comparator, shared by the clones of a solver
+#[derive(Clone)]
+struct OrderCmp(Option<Rc<OrderCmpFn>>);
+
+impl fmt::Debug for OrderCmp {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            None => Ok(()),
+            Some(_) => out.write_str("<comparator>")
+        }
+    }
+}
+
+/// Optional user-provided callback of type `F`
+struct Callback<F: ?Sized>(Option<Box<F>>);
+
+impl<F: ?Sized> Callback<F> {
+    fn none() -> Self { Callback(None) }
+}
+
+/// See `Solver::set_decision_callback`
+type DecisionFn = dyn FnMut(&Solver) -> Option<Var>;
+
+/// See `Solver::set_keep_clause_callback`
+type KeepClauseFn = dyn FnMut(&[Lit]) -> bool;
+
+impl<F: ?Sized> fmt::Debug for Callback<F> {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            None => Ok(()),
+            Some(_) => out.write_str("<callback>")
+        }
+    }
+}
+
 impl SolverInterface for Solver {
     fn set_verbosity(&mut self, verbosity: i32) {
         debug_assert!(0 <= verbosity && verbosity <= 2);
@@ -288,6 +576,9 @@ impl SolverInterface for Solver {
         });
         self.watches().init(Lit::new(v, false));
         self.watches().init(Lit::new(v, true));
+        if self.use_simp {
+            self.occurs.init(v);
+        }
         self.v.assigns.insert_default(v, lbool::UNDEF);
         self.v
             .vardata
@@ -300,9 +591,14 @@ impl SolverInterface for Solver {
             self.v.activity.insert_default(v, 0.0);
         }
         self.seen.insert_default(v, Seen::UNDEF);
-        self.polarity.insert_default(v, false);
+        self.polarity.insert_default(v, self.default_polarity);
         self.user_pol.insert_default(v, upol);
         self.decision.reserve_default(v);
+        self.eliminated.insert_default(v, false);
+        if self.v.var_stats {
+            self.v.var_assigns.insert_default(v, 0);
+            self.v.var_levels.insert_default(v, 0);
+        }
         let len = self.v.trail.len();
         if v.idx() as usize > len {
             self.v.trail.reserve(v.idx() as usize + 1 - len);
@@ -316,38 +612,7 @@ impl SolverInterface for Solver {
     }
 
     fn add_clause_reuse(&mut self, clause: &mut Vec<Lit>) -> bool {
-        // eprintln!("add_clause({:?})", clause);
-        debug_assert_eq!(self.v.decision_level(), 0);
-        debug!("add clause {:?}", clause);
-        if !self.ok {
-            return false;
-        }
-        clause.sort();
-        let mut last_lit = Lit::UNDEF;
-        let mut j = 0;
-        for i in 0..clause.len() {
-            let value = self.v.value_lit(clause[i]);
-            if value == lbool::TRUE || clause[i] == !last_lit {
-                return true;
-            } else if value != lbool::FALSE && clause[i] != last_lit {
-                last_lit = clause[i];
-                clause[j] = clause[i];
-                j += 1;
-            }
-        }
-        clause.resize(j, Lit::UNDEF);
-        if clause.len() == 0 {
-            self.ok = false;
-            return false;
-        } else if clause.len() == 1 {
-            self.v.unchecked_enqueue(clause[0], CRef::UNDEF);
-        } else {
-            let cr = self.ca.alloc_with_learnt(&clause, false);
-            self.clauses.push(cr);
-            self.attach_clause(cr);
-        }
-
-        true
+        self.add_clause_detailed(clause) != AddResult::Unsat
     }
 
     fn solve_limited(&mut self, assumps: &[Lit]) -> lbool {
@@ -386,7 +651,7 @@ impl SolverInterface for Solver {
         println!(
             "c decisions             : {:<12}   ({:4.2} % random)",
             self.decisions,
-            self.rnd_decisions as f32 * 100.0 / self.decisions as f32
+            percent(self.rnd_decisions, self.decisions)
         );
         println!(
             "c propagations          : {:<12}",
@@ -395,8 +660,9 @@ impl SolverInterface for Solver {
         println!(
             "c conflict literals     : {:<12}   ({:4.2} % deleted)",
             self.tot_literals,
-            (self.max_literals - self.tot_literals) as f64 * 100.0 / self.max_literals as f64
+            self.minimization_ratio() * 100.0
         );
+        println!("c glue clauses          : {}", self.v.glue_clauses);
     }
 
     fn unsat_core(&self) -> &[Lit] {
@@ -422,6 +688,53 @@ impl SolverInterface for Solver {
 }
 
 impl Solver {
+    /// Create a new solver with default options, with space reserved for
+    /// `num_vars` variables and `num_clauses` clauses (see `reserve`).
+    pub fn with_capacity(num_vars: u32, num_clauses: usize) -> Self {
+        let mut s = Self::default();
+        s.reserve(num_vars, num_clauses);
+        s
+    }
+
+    /// Create a solver with default options, `num_vars` variables, and the given clauses.
+    ///
+    /// The literals of the clauses must be on these variables. If a clause is
+    /// empty (or the clauses are found unsatisfiable at level 0), the remaining
+    /// clauses are skipped and the solver is unsatisfiable (`is_ok()` is false).
+    ///
+    /// ```
+    /// use batsat::{lbool, Lit, Solver, SolverInterface, Var};
+    /// let x = |i: u32| Lit::new(Var::from_idx(i), true);
+    /// let mut solver = Solver::from_clauses(2, &[&[x(0), x(1)], &[!x(0)]]);
+    /// assert_eq!(solver.solve_limited(&[]), lbool::TRUE);
+    /// assert_eq!(solver.value_lit(x(1)), lbool::TRUE);
+    /// ```
+    pub fn from_clauses(num_vars: u32, clauses: &[&[Lit]]) -> Self {
+        let mut s = Self::with_capacity(num_vars, clauses.len());
+        for _ in 0..num_vars {
+            s.new_var_default();
+        }
+        let mut clause = vec![];
+        for c in clauses {
+            assert!(c.iter().all(|lit| lit.var().idx() < num_vars),
+                "clause {:?} uses a variable outside of the {} variables", c, num_vars);
+            clause.clear();
+            clause.extend_from_slice(c);
+            if !s.add_clause_reuse(&mut clause) {
+                break;
+            }
+        }
+        s
+    }
+
+    /// Create a solver with default options for the problem in DIMACS format in `input`
+    /// (see `read_dimacs_into`).
+    pub fn from_dimacs_str(input: &str) -> io::Result<Self> {
+        let mut s = Self::default();
+        s.read_dimacs_into(input.as_bytes())?;
+        Ok(s)
+    }
+
     /// Create a new solver with the given options
     pub fn new(opts: SolverOpts) -> Self {
         assert!(opts.check());
@@ -430,6 +743,7 @@ impl Solver {
             model: vec![],
             conflict: LSet::new(),
             verbosity: 0,
+            trace: 0,
             var_decay: opts.var_decay,
             clause_decay: opts.clause_decay,
             random_var_freq: opts.random_var_freq,
@@ -438,11 +752,36 @@ impl Solver {
             ccmin_mode: opts.ccmin_mode,
             phase_saving: opts.phase_saving,
             rnd_pol: false,
+            default_polarity: false,
+            polarity_cb: Callback::none(),
+            decision_cb: Callback::none(),
+            first_decision: Var::UNDEF,
+            out_of_scope: vec![],
+            assign_cb: Callback::none(),
+            reduce_cb: Callback::none(),
+            keep_cb: Callback::none(),
+            ext_propagator: Callback::none(),
             rnd_init_act: opts.rnd_init_act,
             garbage_frac: opts.garbage_frac,
+            garbage_min_bytes: usize::MAX,
             min_learnts_lim: opts.min_learnts_lim,
+            reduce_protect: 0,
+            detect_duplicates: false,
+            watch_select: WatchSelect::Sorted,
+            uip_mode: UipMode::First,
+            learnt_sort: LearntSort::None,
+            max_learnt_len: usize::MAX,
+            learnt_histograms: false,
+            backjump_histogram: false,
+            eager_unit_propagation: false,
+            use_simp: false,
+            bump_learnt_lits: false,
             restart_first: opts.restart_first,
             restart_inc: opts.restart_inc,
+            restart_jitter: 0.0,
+            gc_locality: false,
+            steps: None,
+            last_result: lbool::UNDEF,
 
             // Parameters (the rest):
             learntsize_factor: 1.0 / 3.0,
@@ -450,6 +789,7 @@ impl Solver {
 
             produce_proof: opts.produce_proof,
             proof: Proof::new(), // DRAT proof
+            lrat: None,
 
             // Parameters (experimental):
             learntsize_adjust_start_confl: 100,
@@ -469,9 +809,16 @@ impl Solver {
             // v.learnts_literals: 0,
             max_literals: 0,
             tot_literals: 0,
+            learnt_size_hist: vec![],
+            learnt_lbd_hist: vec![],
+            backjump_hist: vec![],
 
             clauses: vec![],
             learnts: vec![],
+            transient: vec![],
+            clause_hashes: HashMap::new(),
+            clause_handles: vec![],
+            elim_clauses: vec![],
             // v.trail: vec![],
             // v.trail_lim: vec![],
             assumptions: vec![],
@@ -480,16 +827,25 @@ impl Solver {
             polarity: VMap::new(),
             user_pol: VMap::new(),
             decision: VMap::new(),
+            eliminated: VMap::new(),
             // v.vardata: VMap::new(),
             watches_data: OccListsData::new(),
+            occurs: OccListsData::new(),
             order_heap_data: HeapData::new(),
             ok: true,
             cla_inc: 1.0,
             // v.var_inc: 1.0,
             qhead: 0,
+            assign_cb_head: 0,
             simp_db_assigns: -1,
             simp_db_props: 0,
+            simp_interval: 0,
+            simp_props_threshold: -1,
+            simp_db_conflicts: 0,
             progress_estimate: 0.0,
+            last_learnt_lbd: 0,
+            last_restart_lbd: 0,
+            last_restart_trail: 0,
             remove_satisfied: false, // FIXME: before enabling, check ICNF regression test
             next_var: Var::from_idx(0),
 
@@ -499,6 +855,8 @@ impl Solver {
             analyze_stack: vec![],
             analyze_toclear: vec![],
             add_tmp: vec![],
+            lbd_seen: vec![],
+            lbd_stamp: 0,
             max_learnts: 0.0,
             learntsize_adjust_confl: 0.0,
             learntsize_adjust_cnt: 0,
@@ -516,10 +874,16 @@ impl Solver {
                 trail_lim: vec![],
                 vardata: VMap::new(),
                 var_inc: 1.0,
+                var_stats: false,
+                var_assigns: VMap::new(),
+                var_levels: VMap::new(),
+                fixed_pending: vec![],
                 num_clauses: 0,
                 num_learnts: 0,
                 clauses_literals: 0,
                 learnts_literals: 0,
+                glue_clauses: 0,
+                order_cmp: OrderCmp(None),
             },
         }
     }
@@ -528,1294 +892,5632 @@ impl Solver {
         self.v.num_learnts as u32
     }
 
-    fn var_decay_activity(&mut self) {
-        self.v.var_inc *= 1.0 / self.var_decay;
+    /// LBD of the last clause learnt before the most recent restart (0 if there was
+    /// no restart yet).
+    pub fn last_restart_lbd(&self) -> u32 {
+        self.last_restart_lbd
     }
 
-    fn cla_decay_activity(&mut self) {
-        self.cla_inc *= 1.0 / self.clause_decay;
+    /// Length of the trail (number of assigned literals) when the most recent
+    /// restart was decided, before backtracking (0 if there was no restart yet).
+    pub fn last_restart_trail(&self) -> usize {
+        self.last_restart_trail
     }
 
-    fn cla_bump_activity(&mut self, cr: CRef) {
-        let new_activity = {
-            let mut c = self.ca.get_mut(cr);
-            let r = c.activity() + self.cla_inc as f32;
-            c.set_activity(r);
-            r
-        };
-        if new_activity > 1e20 {
-            // Rescale:
-            for &learnt in &self.learnts {
-                let mut c = self.ca.get_mut(learnt);
-                let r = c.activity() * 1e-20;
-                c.set_activity(r);
-            }
-            self.cla_inc *= 1e-20;
-        }
+    /// Number of learnt clauses currently stored whose LBD (number of distinct
+    /// decision levels) was at most 2 when they were learnt ("glue" clauses).
+    pub fn glue_clauses(&self) -> u64 {
+        self.v.glue_clauses
     }
 
-    fn set_decision_var(&mut self, v: Var, b: bool) {
-        if b && !self.decision[v] {
-            self.dec_vars += 1;
-        } else if !b && self.decision[v] {
-            self.dec_vars -= 1;
+    /// Current values of the statistics counters.
+    pub fn stats(&self) -> Statistics {
+        Statistics {
+            solves: self.solves,
+            starts: self.starts,
+            decisions: self.decisions,
+            rnd_decisions: self.rnd_decisions,
+            propagations: self.propagations,
+            conflicts: self.conflicts,
+            dec_vars: self.dec_vars,
+            num_clauses: self.v.num_clauses,
+            num_learnts: self.v.num_learnts,
+            clauses_literals: self.v.clauses_literals,
+            learnts_literals: self.v.learnts_literals,
+            glue_clauses: self.v.glue_clauses,
+            max_literals: self.max_literals,
+            tot_literals: self.tot_literals,
         }
-        self.decision[v] = b;
-        self.insert_var_order(v);
     }
 
-    fn insert_var_order(&mut self, x: Var) {
-        if !self.order_heap().in_heap(x) && self.decision[x] {
-            self.order_heap().insert(x);
+    /// Fraction of the literals of learnt clauses removed by minimization so far
+    /// (see `Statistics::minimization_ratio`).
+    pub fn minimization_ratio(&self) -> f64 {
+        self.stats().minimization_ratio()
+    }
+
+    /// Print, like `print_stats`, the work done since the snapshot `since` was
+    /// taken with `stats` (e.g. just before a call to `solve_limited`).
+    ///
+    /// Only the counters are printed, not the size of the clause database.
+    pub fn print_stats_delta(&self, since: &Statistics) {
+        let stdout = io::stdout();
+        self.write_stats_delta(since, &mut stdout.lock()).expect("cannot write to stdout");
+    }
+
+    /// Write the output of `print_stats_delta` to `w`.
+    fn write_stats_delta<W: io::Write>(&self, since: &Statistics, w: &mut W) -> io::Result<()> {
+        let now = self.stats();
+        let delta = |f: fn(&Statistics) -> u64| f(&now).saturating_sub(f(since));
+        let max_literals = delta(|s| s.max_literals);
+        let tot_literals = delta(|s| s.tot_literals);
+        writeln!(w, "c solves                : {}", delta(|s| s.solves))?;
+        writeln!(w, "c restarts              : {}", delta(|s| s.starts))?;
+        writeln!(w, "c conflicts             : {:<12}", delta(|s| s.conflicts))?;
+        writeln!(
+            w,
+            "c decisions             : {:<12}   ({:4.2} % random)",
+            delta(|s| s.decisions),
+            percent(delta(|s| s.rnd_decisions), delta(|s| s.decisions))
+        )?;
+        writeln!(w, "c propagations          : {:<12}", delta(|s| s.propagations))?;
+        writeln!(
+            w,
+            "c conflict literals     : {:<12}   ({:4.2} % deleted)",
+            tot_literals,
+            percent(max_literals.saturating_sub(tot_literals), max_literals)
+        )
+    }
+
+    /// Reset the counters of `stats` (solves, restarts, decisions, propagations,
+    /// conflicts, and literals of learnt clauses) and the learnt clause and backjump
+    /// histograms, to measure a phase of an incremental session on its own.
+    ///
+    /// The clauses, learnt clauses, and heuristics are kept, as are the statistics
+    /// describing the current formula (`dec_vars`, `num_clauses`, etc.).
+    pub fn reset_stats(&mut self) {
+        // budgets and the simplification throttle are relative to the counters
+        if self.conflict_budget >= 0 {
+            self.conflict_budget = cmp::max(0, self.conflict_budget - self.conflicts as i64);
+        }
+        if self.propagation_budget >= 0 {
+            self.propagation_budget = cmp::max(0, self.propagation_budget - self.propagations as i64);
         }
+        self.simp_db_conflicts = self.simp_db_conflicts.saturating_sub(self.conflicts);
+
+        self.solves = 0;
+        self.starts = 0;
+        self.decisions = 0;
+        self.rnd_decisions = 0;
+        self.propagations = 0;
+        self.conflicts = 0;
+        self.max_literals = 0;
+        self.tot_literals = 0;
+        self.learnt_size_hist.clear();
+        self.learnt_lbd_hist.clear();
+        self.backjump_hist.clear();
     }
 
-    /// Pick a literal to make a decision with
-    fn pick_branch_lit(&mut self) -> Lit {
-        let mut next = Var::UNDEF;
+    /// The assumptions used by the last call to `solve_limited`.
+    pub fn assumptions(&self) -> &[Lit] {
+        &self.assumptions
+    }
 
-        // Random decision:
-        if drand(&mut self.random_seed) < self.random_var_freq && !self.order_heap().is_empty() {
-            let idx_tmp = irand(&mut self.random_seed, self.order_heap_data.len() as i32) as usize;
-            next = self.order_heap_data[idx_tmp];
-            if self.v.value(next) == lbool::UNDEF && self.decision[next] {
-                self.rnd_decisions += 1;
-            }
+    /// Forget the assumptions of the last call to `solve_limited`.
+    ///
+    /// This is never needed to recover from UNSAT under assumptions: each call
+    /// to `solve_limited` replaces the assumptions, and an unsatisfiable set of
+    /// assumptions is only recorded in `unsat_core`, not in `is_ok`.
+    pub fn clear_assumptions(&mut self) {
+        self.assumptions.clear();
+    }
+
+    /// Explain why `lit` was propagated: returns its reason clause, with `lit`
+    /// first and all the other literals false in the current assignment.
+    ///
+    /// Returns an empty vector if `lit` is not true, or was not propagated by a
+    /// clause (decisions, assumptions, and unit clauses).
+    pub fn explain(&mut self, lit: Lit) -> Vec<Lit> {
+        if self.v.value_lit(lit) != lbool::TRUE {
+            return vec![];
         }
+        let reason = self.v.reason(lit.var());
+        if reason == CRef::UNDEF {
+            return vec![];
+        }
+        let c = self.ca.get_ref(reason);
+        debug_assert_eq!(c[0], lit);
+        debug_assert!(c.iter().skip(1).all(|&p| self.v.value_lit(p) == lbool::FALSE));
+        c.lits().to_vec()
+    }
 
-        // Activity based decision:
-        while next == Var::UNDEF || self.v.value(next) != lbool::UNDEF || !self.decision[next] {
-            let mut order_heap = self.order_heap();
-            if order_heap.is_empty() {
-                next = Var::UNDEF;
+    /// Dry run of adding the clause `lits`: temporarily assume the negation of
+    /// each of its literals, propagate, and return the literals implied by
+    /// propagation (in trail order). Must be called at level 0.
+    ///
+    /// The trail, decision level and saved phases are restored afterwards.
+    /// If propagation reaches a conflict (the clause is then implied by the
+    /// current clauses), the literals implied before the conflict are returned.
+    /// Returns an empty vector if the solver is unsatisfiable, or out of budget.
+    pub fn would_propagate(&mut self, lits: &[Lit]) -> Vec<Lit> {
+        assert_eq!(self.v.decision_level(), 0);
+        if !self.ok || !self.within_budget() {
+            return vec![];
+        }
+        if self.propagate() != CRef::UNDEF {
+            self.ok = false;
+            return vec![];
+        }
+
+        let start = self.v.trail.len();
+        self.new_decision_level();
+        let mut consistent = true;
+        for &lit in lits {
+            let value = self.v.value_lit(lit);
+            if value == lbool::TRUE {
+                consistent = false;
                 break;
-            } else {
-                next = order_heap.remove_min();
+            } else if value == lbool::UNDEF {
+                self.v.unchecked_enqueue(!lit, CRef::UNDEF);
             }
         }
-
-        // Choose polarity based on different polarity modes (global or per-variable):
-        if next == Var::UNDEF {
-            Lit::UNDEF
-        } else if self.user_pol[next] != lbool::UNDEF {
-            Lit::new(next, self.user_pol[next] == lbool::TRUE)
-        } else if self.rnd_pol {
-            Lit::new(next, drand(&mut self.random_seed) < 0.5)
-        } else {
-            Lit::new(next, self.polarity[next])
+        if consistent {
+            self.propagate();
         }
-    }
 
-    /// Begins a new decision level.
-    fn new_decision_level(&mut self) {
-        // eprintln!(
-        //     "decision_level {} -> {}",
-        //     self.v.trail_lim.len(),
-        //     self.v.trail_lim.len() + 1
-        // );
-        self.v.trail_lim.push(self.v.trail.len() as i32);
+        let implied: Vec<Lit> = self.v.trail[start..].iter().cloned()
+            .filter(|p| self.v.reason(p.var()) != CRef::UNDEF)
+            .collect();
+        let phases: Vec<(Var, bool)> = self.v.trail[start..].iter()
+            .map(|p| (p.var(), self.polarity[p.var()]))
+            .collect();
+        self.cancel_until(0);
+        for (x, phase) in phases {
+            self.polarity[x] = phase;
+        }
+        implied
     }
 
-    fn simplify_internal(&mut self) -> bool {
-        debug_assert_eq!(self.v.decision_level(), 0);
-
-        if !self.ok || self.propagate() != CRef::UNDEF {
+    /// Try to find a model by setting every decision variable to `all_true`
+    /// in turn, with propagation (which may assign some variables the other way).
+    /// Must be called at level 0; this is a cheap check before `solve`.
+    ///
+    /// Returns the model (also available through `get_model`) if no conflict
+    /// occurs, and `None` otherwise (or if an external propagator is set). The
+    /// solver is back at level 0 in both cases, with its saved phases unchanged.
+    pub fn try_lucky_assignment(&mut self, all_true: bool) -> Option<Vec<lbool>> {
+        assert_eq!(self.v.decision_level(), 0);
+        self.model.clear();
+        if !self.ok || self.ext_propagator.0.is_some() {
+            return None;
+        }
+        if self.propagate() != CRef::UNDEF {
             self.ok = false;
-            return false;
+            return None;
         }
 
-        if self.v.num_assigns() as i32 == self.simp_db_assigns || self.simp_db_props > 0 {
-            return true;
+        let start = self.v.trail.len();
+        let mut lucky = true;
+        for v in self.vars() {
+            if self.decision[v] && self.v.value(v) == lbool::UNDEF {
+                self.new_decision_level();
+                self.v.unchecked_enqueue(Lit::new(v, all_true), CRef::UNDEF);
+                if self.propagate() != CRef::UNDEF {
+                    lucky = false;
+                    break;
+                }
+            }
+        }
+        if lucky {
+            self.copy_model();
         }
 
-        self.remove_satisfied(ClauseSet::Learnt); // Remove satisfied learnt clauses
-        if self.remove_satisfied {
-            // FIXME: seems very wrong in incremental context (see incremental regression test1)
-            self.remove_satisfied(ClauseSet::Original); // remove satisfied normal clauses
+        let phases: Vec<(Var, bool)> = self.v.trail[start..].iter()
+            .map(|p| (p.var(), self.polarity[p.var()]))
+            .collect();
+        self.cancel_until(0);
+        for (x, phase) in phases {
+            self.polarity[x] = phase;
         }
-        self.check_garbage();
-        self.rebuild_order_heap();
+        if lucky { Some(self.model.clone()) } else { None }
+    }
 
-        self.simp_db_assigns = self.v.num_assigns() as i32;
-        // (shouldn't depend on stats really, but it will do for now)
-        self.simp_db_props = (self.v.clauses_literals + self.v.learnts_literals) as i64;
+    /// Iterate over the assignment trail, pairing each literal with the
+    /// decision level at which it was assigned.
+    ///
+    /// This reads the live trail, so it reflects the current state of the
+    /// search even if called in the middle of solving.
+    pub fn trail_with_levels<'a>(&'a self) -> impl Iterator<Item=(Lit, i32)> + 'a {
+        let v = &self.v;
+        v.trail.iter().map(move |&lit| (lit, v.level(lit.var())))
+    }
 
-        true
+    /// The literals fixed at level 0 since `marker`, the length of
+    /// `proved_at_lvl_0()` when it was last read. The new marker is `marker`
+    /// plus the length of the result.
+    ///
+    /// Literals fixed at level 0 are never unassigned, so a client can poll
+    /// this to learn the new facts without scanning the older ones again.
+    pub fn new_fixed_literals_since(&self, marker: usize) -> &[Lit] {
+        let fixed = self.proved_at_lvl_0();
+        assert!(marker <= fixed.len(),
+            "marker {} beyond the {} literals fixed at level 0", marker, fixed.len());
+        &fixed[marker..]
     }
 
-    /// Search for a model the specified number of conflicts.
-    /// NOTE! Use negative value for 'nof_conflicts' indicate infinity.
+    /// The current decision level (0 outside of a search).
+    pub fn decision_level(&self) -> u32 {
+        self.v.decision_level()
+    }
+
+    /// Open a new decision level, assign `lit` in it, and propagate.
     ///
-    /// # Output:
+    /// Returns the literals of the conflicting clause (all false) if propagation
+    /// leads to a conflict, and `None` otherwise; in both cases the new level is
+    /// kept until `backtrack_to` is called. If the solver is already unsatisfiable,
+    /// nothing is assigned and the empty clause is returned.
     ///
-    /// 'l_True' if a partial assigment that is consistent with respect to the clauseset is found. If
-    /// all variables are decision variables, this means that the clause set is satisfiable. 'l_False'
-    /// if the clause set is unsatisfiable. 'l_Undef' if the bound on number of conflicts is reached.
-    fn search(&mut self, nof_conflicts: i32) -> lbool {
-        debug_assert!(self.ok);
-        let mut conflict_c = 0;
-        let mut learnt_clause: Vec<Lit> = vec![];
-        self.starts += 1;
+    /// This lets a theory solver drive the search literal by literal. `lit` must
+    /// not be false; if it is already true, the new level is empty.
+    pub fn assume_and_propagate(&mut self, lit: Lit) -> Option<Vec<Lit>> {
+        assert!(self.steps.is_none(), "a `solve_steps` run is in progress");
+        if !self.ok {
+            return Some(vec![]);
+        }
+        let value = self.v.value_lit(lit);
+        assert_ne!(value, lbool::FALSE, "assume_and_propagate: {:?} is false", lit);
+        self.new_decision_level();
+        if value == lbool::UNDEF {
+            self.v.unchecked_enqueue(lit, CRef::UNDEF);
+        }
+        let confl = self.propagate();
+        if confl == CRef::UNDEF {
+            None
+        } else {
+            Some(self.ca.get_ref(confl).lits().to_vec())
+        }
+    }
 
-        loop {
-            let confl = self.propagate();
-            if confl != CRef::UNDEF {
-                // CONFLICT
-                self.conflicts += 1;
-                conflict_c += 1;
-                if self.v.decision_level() == 0 {
-                    return lbool::FALSE;
-                }
+    /// Undo the decision levels above `level` (see `assume_and_propagate`),
+    /// unassigning their variables. Their phases are saved as during the search.
+    pub fn backtrack_to(&mut self, level: u32) {
+        assert!(self.steps.is_none(), "a `solve_steps` run is in progress");
+        assert!(level <= self.v.decision_level(),
+            "backtrack_to({}) above the current decision level {}", level, self.v.decision_level());
+        self.cancel_until(level);
+    }
 
-                learnt_clause.clear();
-                let backtrack_level = self.analyze(confl, &mut learnt_clause);
-                if self.produce_proof { self.proof.create_clause(&learnt_clause); } // emit proof
-                self.cancel_until(backtrack_level as u32);
+    /// Propagate at level 0, without searching.
+    ///
+    /// Returns `lbool::FALSE` if propagation leads to a conflict (the solver is then
+    /// unsatisfiable), `lbool::TRUE` if every variable is assigned (or eliminated),
+    /// in which case the model is available as after `solve`, and `lbool::UNDEF` otherwise.
+    pub fn propagate_only(&mut self) -> lbool {
+        assert_eq!(self.v.decision_level(), 0);
+        self.model.clear();
+        if !self.ok || self.propagate() != CRef::UNDEF {
+            self.ok = false;
+            return lbool::FALSE;
+        }
+        let complete = self.vars()
+            .all(|v| self.v.value(v) != lbool::UNDEF || self.eliminated[v]);
+        if complete {
+            self.copy_model();
+            lbool::TRUE
+        } else {
+            lbool::UNDEF
+        }
+    }
 
-                // propagate the only lit of `learnt_clause` that isn't false
-                if learnt_clause.len() == 1 {
-                    // directly propagate the unit clause at level 0
-                    self.v.unchecked_enqueue(learnt_clause[0], CRef::UNDEF);
-                } else {
-                    // propagate the lit, justified by `cr`
-                    let cr = self.ca.alloc_with_learnt(&learnt_clause, true);
-                    self.learnts.push(cr);
-                    self.attach_clause(cr);
-                    self.cla_bump_activity(cr);
-                    self.v.unchecked_enqueue(learnt_clause[0], cr);
-                }
+    /// Whether the last model gives a value to every variable.
+    ///
+    /// Returns `false` if the last result was not `lbool::TRUE`. See `undef_vars`.
+    pub fn model_is_complete(&self) -> bool {
+        !self.model.is_empty()
+            && self.model.len() == self.num_vars() as usize
+            && self.model.iter().all(|&val| val != lbool::UNDEF)
+    }
 
-                self.var_decay_activity();
-                self.cla_decay_activity();
+    /// The variables that have no value in the last model (see `value_var`).
+    ///
+    /// After a `lbool::TRUE` result, these are non-decision variables that were
+    /// left unassigned, and variables created since.
+    pub fn undef_vars(&self) -> Vec<Var> {
+        self.vars().filter(|&v| self.value_var(v) == lbool::UNDEF).collect()
+    }
 
-                self.learntsize_adjust_cnt -= 1;
-                if self.learntsize_adjust_cnt == 0 {
-                    self.learntsize_adjust_confl *= self.learntsize_adjust_inc;
-                    self.learntsize_adjust_cnt = self.learntsize_adjust_confl as i32;
-                    self.max_learnts *= self.learntsize_inc;
+    /// The last model, indexed by variable (same as `get_model`).
+    ///
+    /// It is empty if the last call to solve did not return `lbool::TRUE`.
+    pub fn model_vec(&self) -> &[lbool] {
+        &self.model
+    }
 
-                    if self.verbosity >= 1 {
-                        let trail_lim_head = self.v
-                            .trail_lim
-                            .first()
-                            .cloned()
-                            .unwrap_or(self.v.trail.len() as i32);
-                        println!(
-                            "c | {:9} | {:7} {:8} {:8} | {:8} {:8} {:6.0} | {:6.3} % |",
-                            self.conflicts as i32,
-                            self.dec_vars as i32 - trail_lim_head,
-                            self.num_clauses(),
-                            self.v.clauses_literals as i32,
-                            self.max_learnts as i32,
-                            self.num_learnts(),
-                            self.v.learnts_literals as f64 / self.num_learnts() as f64,
-                            self.progress_estimate() * 100.0
-                        );
-                    }
-                }
-            } else {
-                // NO CONFLICT
-                if (nof_conflicts >= 0 && conflict_c >= nof_conflicts) || !self.within_budget() {
-                    // Reached bound on number of conflicts:
-                    self.progress_estimate = self.progress_estimate();
-                    self.cancel_until(0);
-                    return lbool::UNDEF;
-                }
+    /// The last model as the list of its true literals, in variable order.
+    ///
+    /// Variables that are `UNDEF` in the model are skipped; the list is empty
+    /// if the last call to solve did not return `lbool::TRUE`.
+    pub fn model_lits(&self) -> Vec<Lit> {
+        self.model.iter().enumerate()
+            .filter(|&(_, &val)| val != lbool::UNDEF)
+            .map(|(i, &val)| Lit::new(Var::from_idx(i as u32), val == lbool::TRUE))
+            .collect()
+    }
 
-                // Simplify the set of problem clauses:
-                if self.v.decision_level() == 0 && !self.simplify() {
-                    return lbool::FALSE;
-                }
+    /// Check that the last model satisfies every original clause, as well as the
+    /// clauses kept to reconstruct eliminated variables (see `preprocess`).
+    ///
+    /// Returns `false` if there is no model, or if a clause is not satisfied
+    /// (which is a bug, unless some non-decision variables are `UNDEF` in the model).
+    pub fn verify_model(&self) -> bool {
+        if self.model.is_empty() {
+            return false;
+        }
+        let sat = |lit: &Lit| self.value_lit(*lit) == lbool::TRUE;
+        // unit clauses of the reconstruction stack are default values, not constraints
+        self.original_clauses().all(|c| c.iter().any(&sat))
+            && self.elim_clauses.iter().filter(|c| c.len() > 1).all(|c| c.iter().any(&sat))
+    }
 
-                if self.learnts.len() as f64 - self.v.num_assigns() as f64 >= self.max_learnts {
-                    // Reduce the set of learnt clauses:
-                    self.reduce_db();
-                }
+    /// Check that the last unsat core is unsatisfiable, by solving a copy of the
+    /// solver under exactly the assumptions of the core.
+    ///
+    /// Returns `false` if the copy finds a model, or runs out of budget.
+    pub fn verify_core(&self) -> bool {
+        let assumps: Vec<Lit> = self.conflict.as_slice().iter().map(|&lit| !lit).collect();
+        let mut s = self.clone();
+        s.conflict_budget = -1;
+        s.propagation_budget = -1;
+        s.solve_limited(&assumps) == lbool::FALSE
+    }
 
-                let mut next = Lit::UNDEF;
-                while (self.v.decision_level() as usize) < self.assumptions.len() {
-                    // Perform user provided assumption:
-                    let p = self.assumptions[self.v.decision_level() as usize];
-                    if self.v.value_lit(p) == lbool::TRUE {
-                        // Dummy decision level, since `p` is true already:
-                        self.new_decision_level();
-                    } else if self.v.value_lit(p) == lbool::FALSE {
-                        // conflict at level 0 because of `p`, unsat
-                        let mut conflict = mem::replace(&mut self.conflict, LSet::new());
-                        self.analyze_final(!p, &mut conflict);
-                        self.conflict = conflict;
-                        return lbool::FALSE;
-                    } else {
-                        next = p;
-                        break;
-                    }
-                }
+    /// Same as `solve_limited`, with the assumptions read from `assumps`
+    /// without building an intermediate vector.
+    pub fn solve_assumptions_iter<I: IntoIterator<Item=Lit>>(&mut self, assumps: I) -> lbool {
+        self.asynch_interrupt.store(false, Ordering::SeqCst);
+        self.assumptions.clear();
+        self.assumptions.extend(assumps);
+        self.solve_internal()
+    }
 
-                if next == Lit::UNDEF {
-                    // New variable decision:
-                    self.decisions += 1;
-                    next = self.pick_branch_lit();
+    /// Same as `solve_limited`, but return a copy of the model (as `get_model` would)
+    /// if one is found, and `None` if the clauses are unsatisfiable under `assumps`
+    /// or the search was interrupted.
+    pub fn solve_assumptions_get_model(&mut self, assumps: &[Lit]) -> Option<Vec<lbool>> {
+        if self.solve_limited(assumps) == lbool::TRUE {
+            Some(self.model.clone())
+        } else {
+            None
+        }
+    }
 
-                    if next == Lit::UNDEF {
-                        // Model found:
-                        return lbool::TRUE;
-                    }
-                }
+    /// Did the last call to `solve` find a model?
+    ///
+    /// Returns `false` if it was interrupted or ran out of budget, and if
+    /// the solver became inconsistent since (e.g. by adding a clause).
+    pub fn is_sat(&self) -> bool {
+        self.ok && self.last_result == lbool::TRUE
+    }
 
-                // Increase decision level and enqueue `next`
-                // with no justification since it's a decision
-                self.new_decision_level();
-                debug!("pick-next {:?}", next);
-                self.v.unchecked_enqueue(next, CRef::UNDEF);
+    /// Are the clauses unsatisfiable, either under the assumptions of the last
+    /// call to `solve` or unconditionally (see `is_ok`)?
+    ///
+    /// Returns `false` if the last call to `solve` was interrupted or ran out
+    /// of budget, unless the solver is inconsistent anyway.
+    pub fn is_unsat(&self) -> bool {
+        !self.ok || self.last_result == lbool::FALSE
+    }
+
+    /// Solve without assumptions for at most `max_conflicts` conflicts, and return
+    /// `SolveStatus::InProgress` if no answer was found yet.
+    ///
+    /// The next call resumes the search exactly where it stopped: the trail, the
+    /// learnt clauses and the restart schedule are preserved in between. Until
+    /// an answer is returned, the solver stays above level 0, so no clause can be
+    /// added and `solve` cannot be called; use `cancel_steps` to give up.
+    pub fn solve_steps(&mut self, max_conflicts: u64) -> SolveStatus {
+        assert!(self.out_of_scope.is_empty(), "`solve_steps` does not support a decision scope");
+        let mut st = match self.steps.take() {
+            Some(st) => st,
+            None => {
+                assert_eq!(self.v.decision_level(), 0);
+                self.assumptions.clear();
+                self.model.clear();
+                self.conflict.clear();
+                self.last_result = lbool::UNDEF;
+                if !self.ok {
+                    self.last_result = lbool::FALSE;
+                    return SolveStatus::Unsat;
+                }
+                self.solves += 1;
+                self.init_learnt_limits();
+                Steps { curr_restarts: 0, nof_conflicts: self.restart_limit(0), conflict_c: 0 }
+            }
+        };
+        let pause_at = self.conflicts.saturating_add(max_conflicts);
+        while self.conflicts < pause_at {
+            if st.conflict_c == 0 && self.v.decision_level() == 0 {
+                self.starts += 1;
+            }
+            let status = self.search_resumable(st.nof_conflicts, &mut st.conflict_c, pause_at);
+            if status == lbool::TRUE {
+                self.copy_model();
+                self.cancel_until(0);
+                self.last_result = lbool::TRUE;
+                return SolveStatus::Sat;
+            } else if status == lbool::FALSE {
+                self.ok = false;
+                self.last_result = lbool::FALSE;
+                self.cancel_until(0);
+                return SolveStatus::Unsat;
+            } else if self.v.decision_level() > 0 || !self.within_budget() {
+                // paused or interrupted, not restarting
+                break;
+            } else if st.conflict_c >= st.nof_conflicts {
+                st.curr_restarts += 1;
+                st.nof_conflicts = self.restart_limit(st.curr_restarts);
+                st.conflict_c = 0;
             }
         }
+        self.steps = Some(st);
+        SolveStatus::InProgress
     }
 
-    /// Main solve method (assumptions given in `self.assumptions`).
-    fn solve_internal(&mut self) -> lbool {
-        assert!(self.v.decision_level()==0);
-        self.model.clear();
-        self.conflict.clear();
-        if !self.ok {
-            return lbool::FALSE;
+    /// Abandon the search started by `solve_steps`, if any, and backtrack to level 0.
+    pub fn cancel_steps(&mut self) {
+        if self.steps.take().is_some() {
+            self.cancel_until(0);
         }
+    }
 
-        self.solves += 1;
-
-        self.max_learnts = self.num_clauses() as f64 * self.learntsize_factor;
-        if self.max_learnts < self.min_learnts_lim as f64 {
-            self.max_learnts = self.min_learnts_lim as f64;
+    /// Add the unit clause `lit`, even above level 0: the solver first backtracks
+    /// to level 0 (see `backtrack_to`). A search started by `solve_steps` is kept,
+    /// and the next call resumes it from level 0, as after a restart.
+    ///
+    /// Returns `false` if the solver is unsatisfiable, as `add_clause_reuse`.
+    pub fn add_unit_anytime(&mut self, lit: Lit) -> bool {
+        self.cancel_until(0);
+        let ok = self.add_clause_reuse(&mut vec![lit]);
+        if !ok {
+            // nothing left to resume, `solve_steps` will report UNSAT
+            self.steps = None;
         }
+        ok
+    }
 
-        self.learntsize_adjust_confl = self.learntsize_adjust_start_confl as f64;
-        self.learntsize_adjust_cnt = self.learntsize_adjust_confl as i32;
-        let mut status = lbool::UNDEF;
-
-        if self.verbosity >= 1 {
-            println!( "c ============================[ Search Statistics ]==============================");
-            println!( "c | Conflicts |          ORIGINAL         |          LEARNT          | Progress |");
-            println!( "c |           |    Vars  Clauses Literals |    Limit  Clauses Lit/Cl |          |");
-            println!( "c ===============================================================================");
+    /// Find a minimal subset of `soft` that is unsatisfiable when assumed, using
+    /// the deletion-based algorithm: each literal is removed in turn, and kept only
+    /// if the problem becomes satisfiable without it.
+    ///
+    /// Returns:
+    ///
+    /// - `None` if assuming `soft` is satisfiable, or if the budget runs out
+    ///   before `soft` is shown to be unsatisfiable;
+    /// - `Some(vec![])` if the problem is unsatisfiable without any assumption;
+    /// - otherwise, `Some` of a minimal subset of `soft`. If the budget runs out
+    ///   during the minimization, the subset found so far is returned instead; it
+    ///   is unsatisfiable, but not necessarily minimal.
+    pub fn extract_mus(&mut self, soft: &[Lit]) -> Option<Vec<Lit>> {
+        if self.solve_limited(soft) != lbool::FALSE {
+            return None;
         }
-
-        // Search:
-        let mut curr_restarts: i32 = 0;
-        while status == lbool::UNDEF {
-            let rest_base = if self.luby_restart {
-                luby(self.restart_inc, curr_restarts)
-            } else {
-                f64::powi(self.restart_inc, curr_restarts)
-            };
-            let nof_clauses = (rest_base * self.restart_first as f64) as i32;
-            status = self.search(nof_clauses);
-            if !self.within_budget() {
-                break;
+        let mut mus = self.core_of(soft);
+        let mut i = 0;
+        let mut assumps = Vec::with_capacity(mus.len());
+        while i < mus.len() {
+            assumps.clear();
+            assumps.extend(mus.iter().enumerate().filter(|&(j, _)| j != i).map(|(_, &lit)| lit));
+            match self.solve_limited(&assumps) {
+                x if x == lbool::FALSE => {
+                    // `mus[i]` is not needed, and neither are the literals out of the new core
+                    mus = self.core_of(&assumps);
+                }
+                x if x == lbool::TRUE => i += 1,
+                _ => break,
             }
-            curr_restarts += 1;
         }
+        Some(mus)
+    }
 
-        if self.verbosity >= 1 {
-            println!(
-                "c ==============================================================================="
-            );
-        }
+    /// The literals of `assumps` that are in the last unsat core, in order.
+    fn core_of(&self, assumps: &[Lit]) -> Vec<Lit> {
+        assumps.iter().cloned().filter(|&lit| self.conflict.has(!lit)).collect()
+    }
 
-        if status == lbool::TRUE {
-            // Extend & copy model:
-            let num_vars = self.num_vars();
-            self.model.resize(num_vars as usize, lbool::UNDEF);
-            for i in 0..num_vars {
-                self.model[i as usize] = self.v.value(Var::from_idx(i));
+    /// Enumerate the models of the problem under `assumps`, projected on `proj`.
+    ///
+    /// `f` is called once per distinct assignment of the variables of `proj`,
+    /// with their values in the same order, and returns `false` to stop the
+    /// enumeration. Each model found is blocked by a clause guarded by a fresh
+    /// selector variable; the selector is disabled at the end, so the blocking
+    /// clauses have no effect on later calls.
+    ///
+    /// Returns `lbool::FALSE` once all the models have been enumerated, `lbool::TRUE`
+    /// if `f` stopped the enumeration, and `lbool::UNDEF` if the solver was interrupted.
+    pub fn solve_all_under_assumptions<F>(&mut self, assumps: &[Lit], proj: &[Var], mut f: F) -> lbool
+        where F: FnMut(&[lbool]) -> bool
+    {
+        assert!(proj.iter().all(|&v| !self.eliminated[v]), "cannot project on eliminated variables");
+        let sel = Lit::new(self.new_var(lbool::UNDEF, false), true);
+        let mut assumps = assumps.to_vec();
+        assumps.push(sel);
+        let mut values = Vec::with_capacity(proj.len());
+        let mut block = Vec::with_capacity(proj.len() + 1);
+        let res = loop {
+            let res = self.solve_limited(&assumps);
+            if res != lbool::TRUE {
+                break res;
             }
-        } else if status == lbool::FALSE && self.conflict.len() == 0 {
-            // NOTE: we may return `false` without an empty conflict in case we had assumptions. In
-            // this case `self.conflict` contains the unsat-core but adding new clauses might
-            // succeed in the absence of these assumptions.
-            self.ok = false;
+            values.clear();
+            values.extend(proj.iter().map(|&v| self.value_var(v)));
+            if !f(&values) {
+                break lbool::TRUE;
+            }
+            block.clear();
+            block.push(!sel);
+            block.extend(proj.iter().zip(values.iter())
+                .filter(|&(_, &val)| val != lbool::UNDEF)
+                .map(|(&v, &val)| Lit::new(v, val != lbool::TRUE)));
+            self.add_clause_reuse(&mut block);
+        };
+        // retire the blocking clauses
+        self.add_clause_reuse(&mut vec![!sel]);
+        res
+    }
+
+    /// Reset the temporary buffers used by conflict analysis and clause addition,
+    /// and release their memory.
+    #[doc(hidden)]
+    pub fn reset_scratch(&mut self) {
+        for v in self.vars() {
+            self.seen[v] = Seen::UNDEF;
         }
+        self.analyze_stack = vec![];
+        self.analyze_toclear = vec![];
+        self.add_tmp = vec![];
+        self.lbd_seen = vec![];
+        self.lbd_stamp = 0;
+    }
 
-        self.cancel_until(0);
-        debug!("res: {:?}; proved at lvl 0: {:?}", status, self.v.trail.iter().collect::<Vec<_>>());
-        status
+    /// Iterate over the original clauses that are not deleted.
+    pub(crate) fn original_clauses<'a>(&'a self) -> impl Iterator<Item=ClauseRef<'a>> + 'a {
+        let ca = &self.ca;
+        self.clauses.iter().map(move |&cr| ca.get_ref(cr)).filter(|c| c.mark() != 1)
     }
 
-    /// Remove half of the learnt clauses, minus the clauses locked by the current assignment. Locked
-    /// clauses are clauses that are reason to some assignment. Binary clauses are never removed.
-    fn reduce_db(&mut self) {
-        let extra_lim = self.cla_inc / self.learnts.len() as f64; // Remove any clause below this activity
+    /// Iterate over all the variables of the solver.
+    ///
+    /// The iterator does not borrow the solver, so it is fine to modify
+    /// the solver while iterating.
+    pub fn vars(&self) -> impl Iterator<Item=Var> {
+        (0..self.num_vars()).map(Var::from_idx)
+    }
 
-        info!("reduce_db.start");
+    /// Iterate over all the literals of the solver (both polarities of each variable).
+    pub fn lits(&self) -> impl Iterator<Item=Lit> {
+        (0..2 * self.num_vars()).map(Lit::from_idx)
+    }
 
-        {
-            let ca = &self.ca;
-            self.learnts.sort_unstable_by(|&x, &y| {
-                let x = ca.get_ref(x);
-                let y = ca.get_ref(y);
-                debug_assert!(x.learnt());
-                debug_assert!(y.learnt());
-                Ord::cmp(&(x.size() <= 2), &(y.size() <= 2)).then(
-                    PartialOrd::partial_cmp(&x.activity(), &y.activity()).expect("NaN activity"),
-                )
-            });
+    /// Add a clause to the solver, reporting what happened to it.
+    ///
+    /// Like `add_clause_reuse`, `clause` is sorted and simplified in place
+    /// (it is only simplified with `WatchSelect::KeepOrder`).
+    ///
+    /// Learnt clauses are kept, since they are still implied by the clauses; they
+    /// are checked against the new level 0 assignments by the next propagation.
+    pub fn add_clause_detailed(&mut self, clause: &mut Vec<Lit>) -> AddResult {
+        // eprintln!("add_clause({:?})", clause);
+        debug_assert_eq!(self.v.decision_level(), 0);
+        debug!("add clause {:?}", clause);
+        if !self.ok {
+            // the clause still has its ID in the proof
+            if let Some(ref mut lrat) = self.lrat { lrat.new_input(); }
+            return AddResult::Unsat;
         }
-        // Don't delete binary or locked clauses. From the rest, delete clauses from the first half
-        // and clauses with activity smaller than `extra_lim`:
+        let user_order = if self.watch_select == WatchSelect::KeepOrder {
+            Some(clause.clone())
+        } else {
+            None
+        };
+        clause.sort();
+        assert!(clause.iter().all(|lit| !self.eliminated[lit.var()]),
+            "clause contains an eliminated variable");
+        let lrat_input = self.lrat_input(clause);
+        let mut last_lit = Lit::UNDEF;
         let mut j = 0;
-        for i in 0..self.learnts.len() {
-            let cr = self.learnts[i];
-            let cond = {
-                let c = self.ca.get_ref(cr);
-                c.size() > 2 && !self.v.locked(&self.ca, c)
-                    && (i < self.learnts.len() / 2 || (c.activity() as f64) < extra_lim)
-            };
-            if cond {
-                self.v.remove_clause(&mut self.ca, &mut self.watches_data, cr);
-                if self.produce_proof { self.proof.delete_clause(&self.ca.get_ref(cr)); }
-            } else {
-                self.learnts[j] = cr;
+        for i in 0..clause.len() {
+            let value = self.v.value_lit(clause[i]);
+            if value == lbool::TRUE || clause[i] == !last_lit {
+                return AddResult::Trivial;
+            } else if value != lbool::FALSE && clause[i] != last_lit {
+                last_lit = clause[i];
+                clause[j] = clause[i];
                 j += 1;
             }
         }
+        clause.resize(j, Lit::UNDEF);
+        let hash = if self.detect_duplicates && clause.len() > 1 {
+            let h = hash_lits(clause);
+            if self.find_clause_hashed(h, clause).is_some() {
+                debug!("skip duplicate clause {:?}", clause);
+                return AddResult::AlreadyPresent;
+            }
+            Some(h)
+        } else {
+            None
+        };
+        if let Some(user_order) = user_order {
+            restore_order(clause, &user_order);
+            self.select_watches(clause);
+        }
+        let lrat_id = self.lrat_simplified_input(lrat_input, clause);
+        if clause.is_empty() {
+            self.ok = false;
+            return AddResult::Unsat;
+        } else if clause.len() == 1 {
+            self.v.unchecked_enqueue(clause[0], CRef::UNDEF);
+            self.lrat_bind(clause, CRef::UNDEF, lrat_id);
+            if self.eager_unit_propagation && self.propagate() != CRef::UNDEF {
+                self.ok = false;
+                return AddResult::Unsat;
+            }
+        } else {
+            let cr = self.ca.alloc_with_learnt(clause, false);
+            self.lrat_bind(clause, cr, lrat_id);
+            self.clauses.push(cr);
+            self.attach_clause(cr);
+            self.add_occurrences(cr);
+            if let Some(h) = hash {
+                self.clause_hashes.entry(h).or_default().push(cr);
+            }
+        }
 
-        // self.learnts.resize_default(j);
-        let _deleted = self.learnts.len()-j;
-        self.learnts.resize(j, CRef::UNDEF);
+        AddResult::Added
+    }
 
-        debug!("reduce_db.done (deleted {})", _deleted);
+    /// Like `add_clause_detailed`, but reports misuse as an error instead of
+    /// returning `AddResult::Unsat` or panicking.
+    ///
+    /// Fails with `SolverError::Inconsistent` if the solver is already unsatisfiable,
+    /// or in the middle of a `solve_steps` search. `Ok(AddResult::Unsat)` means that
+    /// this clause made the solver unsatisfiable.
+    pub fn try_add_clause(&mut self, lits: &[Lit]) -> Result<AddResult, SolverError> {
+        if !self.ok || self.steps.is_some() {
+            return Err(SolverError::Inconsistent);
+        }
+        Ok(self.add_clause_detailed(&mut lits.to_vec()))
+    }
 
-        self.check_garbage();
+    /// Like `solve_limited` without assumptions, but an unknown result is an error.
+    ///
+    /// Fails with `SolverError::Interrupted` if the search was interrupted or ran
+    /// out of budget, and `SolverError::Inconsistent` if a `solve_steps` search
+    /// is in progress.
+    pub fn try_solve(&mut self) -> Result<SatResult, SolverError> {
+        if self.steps.is_some() {
+            return Err(SolverError::Inconsistent);
+        }
+        match self.solve_limited(&[]) {
+            x if x == lbool::TRUE => Ok(SatResult::Sat),
+            x if x == lbool::FALSE => Ok(SatResult::Unsat),
+            _ => Err(SolverError::Interrupted),
+        }
     }
 
-    /// Shrink the given set to contain only non-satisfied clauses.
-    fn remove_satisfied(&mut self, which: ClauseSet) {
+    /// Reserve space for `num_vars` variables and `num_clauses` clauses in total.
+    ///
+    /// This is only a hint, to avoid repeated reallocations when the size of
+    /// the problem is known in advance.
+    pub fn reserve(&mut self, num_vars: u32, num_clauses: usize) {
+        let n_vars = (num_vars as usize).saturating_sub(self.v.trail.len());
+        let n_clauses = num_clauses.saturating_sub(self.clauses.len());
+        self.v.trail.reserve(n_vars);
+        self.clauses.reserve(n_clauses);
+    }
+
+    /// Read a problem in DIMACS format from `r`, adding each clause as soon as
+    /// it is parsed (the input is never buffered as a whole).
+    ///
+    /// Variables are created as needed. The `p cnf` header is optional, and is
+    /// only used to `reserve` space: wrong counts are not an error.
+    pub fn read_dimacs_into<R: BufRead>(&mut self, mut r: R) -> io::Result<()> {
+        dimacs::parse_with_header(&mut r, self, false, false, |s, n_vars, n_clauses| {
+            s.reserve(n_vars, n_clauses as usize)
+        })
+    }
+
+    /// Same as `read_dimacs_into`, but `input` is held in memory and parsed
+    /// by several threads (see `dimacs::parse_parallel`).
+    #[cfg(feature="parallel")]
+    pub fn read_dimacs_parallel(&mut self, input: &[u8]) -> io::Result<()> {
+        dimacs::parse_parallel_with_header(input, self, |s, n_vars, n_clauses| {
+            s.reserve(n_vars, n_clauses as usize)
+        })
+    }
+
+    /// Add a clause to the solver, and return a handle to it if it was stored.
+    ///
+    /// No handle is returned if the clause is unit (it is then a level-0 fact),
+    /// trivial, or not added. See `remove_clause_by_handle`.
+    pub fn add_clause_with_handle(&mut self, clause: &mut Vec<Lit>) -> Option<ClauseHandle> {
+        if self.add_clause_detailed(clause) == AddResult::Added && clause.len() > 1 {
+            let cr = *self.clauses.last().expect("clause was not stored");
+            self.clause_handles.push(cr);
+            Some(ClauseHandle(self.clause_handles.len() as u32 - 1))
+        } else {
+            None
+        }
+    }
+
+    /// Forbid the partial assignment `cube`, by adding the clause `!l1 ∨ !l2 ∨ …`
+    /// made of the negations of its literals. Must be called at level 0.
+    ///
+    /// Panics if `cube` contains both a literal and its negation.
+    /// Returns `false` if the solver is now in an unsatisfiable state.
+    pub fn add_clause_negated_cube(&mut self, cube: &[Lit]) -> bool {
+        let mut clause: Vec<Lit> = cube.iter().map(|&lit| !lit).collect();
+        clause.sort();
+        assert!(clause.windows(2).all(|w| w[0] != !w[1]),
+            "cube contains complementary literals");
+        self.add_clause_reuse(&mut clause)
+    }
+
+    /// Add a redundant clause, known to be implied by the problem (or at least
+    /// to preserve its satisfiability, like a symmetry-breaking lemma).
+    ///
+    /// Unlike `add_clause`, which stores problem clauses permanently, the clause is
+    /// stored as a learnt clause: it may be deleted during search, when the learnt
+    /// clause database is reduced. Units are still fixed at level 0.
+    /// When proofs are enabled, the clause is added to the proof, so it should
+    /// be RUP (or RAT) for the proof to check.
+    ///
+    /// Returns `false` if the solver is now in an unsatisfiable state.
+    pub fn add_learnt_clause(&mut self, lits: &[Lit]) -> bool {
         assert_eq!(self.v.decision_level(), 0);
-        let cs: &mut Vec<CRef> = match which {
-            ClauseSet::Learnt => &mut self.learnts,
-            ClauseSet::Original => &mut self.clauses,
-        };
-        let ca = &mut self.ca;
-        let watches_data = &mut self.watches_data;
-        let self_v = &mut self.v;
-        cs.retain(|&cr| {
-            let satisfied = self_v.satisfied(ca.get_ref(cr));
-            if satisfied {
-                self_v.remove_clause(ca, watches_data, cr);
-                debug!("remove satisfied clause {}", ca.get_ref(cr).pp_dimacs());
-                // we should not need to tell the proof checker to remove the clause
-            } else {
-                let amount_shaved = {
-                    let mut c = ca.get_mut(cr);
-                    // Trim clause (but keep the 2 first lits as they are watching):
-                    debug_assert_eq!(self_v.value_lit(c[0]), lbool::UNDEF);
-                    debug_assert_eq!(self_v.value_lit(c[1]), lbool::UNDEF);
-                    let mut k = 2;
-                    let orig_size = c.size();
-                    let mut end = c.size();
-                    while k < end {
-                        if self_v.value_lit(c[k]) == lbool::FALSE {
-                            // this lit is false at level 0, remove it from `c`
-                            debug_assert!(self_v.level(c[k].var()) == 0);
-                            end -= 1;
-                            c[k] = c[end];
-                        } else {
-                            k += 1;
-                        }
-                    }
-                    c.shrink(end);
-                    orig_size - end
-                };
-                // It was not in MiniSAT, but it is needed for correct wasted calculation.
-                ca.free_amount(amount_shaved);
+        assert!(self.lrat.is_none(), "add_learnt_clause does not support LRAT proofs");
+        debug!("add learnt clause {:?}", lits);
+        if !self.ok {
+            return false;
+        }
+        let mut clause = lits.to_vec();
+        clause.sort();
+        clause.dedup();
+        assert!(clause.iter().all(|lit| !self.eliminated[lit.var()]),
+            "clause contains an eliminated variable");
+        if clause.windows(2).any(|w| w[0] == !w[1])
+            || clause.iter().any(|&lit| self.v.value_lit(lit) == lbool::TRUE)
+        {
+            return true;
+        }
+        clause.retain(|&lit| self.v.value_lit(lit) != lbool::FALSE);
+        if self.produce_proof { self.proof.create_clause(&clause); }
+        if clause.is_empty() {
+            self.ok = false;
+        } else if clause.len() == 1 {
+            self.v.unchecked_enqueue(clause[0], CRef::UNDEF);
+            if self.eager_unit_propagation && self.propagate() != CRef::UNDEF {
+                self.ok = false;
             }
-            !satisfied
-        });
+        } else {
+            let cr = self.ca.alloc_with_learnt(&clause, true);
+            self.learnts.push(cr);
+            self.attach_clause(cr);
+            self.cla_bump_activity(cr);
+        }
+        self.ok
     }
 
-    fn rebuild_order_heap(&mut self) {
-        let mut vs = vec![];
-        for v in (0..self.num_vars()).map(Var::from_idx) {
-            if self.decision[v] && self.v.value(v) == lbool::UNDEF {
-                vs.push(v);
+    /// The literals of the clause designated by `h`, or `None` if it was removed.
+    ///
+    /// Literals false at level 0 may have been removed from the clause, and the
+    /// literals may have been reordered.
+    pub fn clause_lits(&self, h: ClauseHandle) -> Option<&[Lit]> {
+        let cr = self.clause_handles.get(h.0 as usize).cloned().unwrap_or(CRef::UNDEF);
+        if cr == CRef::UNDEF {
+            return None;
+        }
+        let c = self.ca.get_ref(cr);
+        if c.mark() == 1 { None } else { Some(c.lits()) }
+    }
+
+    /// The literals of the clause designated by `h` in DIMACS style, separated
+    /// by spaces (without the final `0`), or `None` if it was removed.
+    ///
+    /// ```
+    /// use batsat::{Solver, SolverInterface, Lit};
+    /// let mut s = Solver::default();
+    /// let a = Lit::new(s.new_var_default(), true);
+    /// let b = Lit::new(s.new_var_default(), true);
+    /// let h = s.add_clause_with_handle(&mut vec![a, !b]).unwrap();
+    /// assert_eq!(s.fmt_clause(h).unwrap(), "1 -2");
+    /// ```
+    pub fn fmt_clause(&self, h: ClauseHandle) -> Option<String> {
+        let lits = self.clause_lits(h)?;
+        let mut s = String::new();
+        for (i, lit) in lits.iter().enumerate() {
+            if i > 0 {
+                s.push(' ');
             }
+            write!(s, "{}", lit).unwrap();
         }
-        self.order_heap().build(&vs);
+        Some(s)
     }
 
-    fn attach_clause(&mut self, cr: CRef) {
-        let (c0, c1, learnt, size) = {
-            let c = self.ca.get_ref(cr);
-            debug_assert!(c.size() > 1);
-            (c[0], c[1], c.learnt(), c.size())
+    /// Remove the clause designated by `h`. Returns `false` if it was
+    /// already removed (e.g. because it was satisfied at level 0), or if `h`
+    /// does not come from this solver.
+    ///
+    /// Learnt clauses may have been derived from the removed clause, so **all the
+    /// learnt clauses are deleted** as well, and the search starts over from the
+    /// original clauses. Literals already proved at level 0 are kept, so this is
+    /// only sound for clauses that did not contribute to `proved_at_lvl_0`;
+    /// otherwise, guard the clause with a selector literal and use assumptions.
+    ///
+    /// Handles remain valid across garbage collections.
+    pub fn remove_clause_by_handle(&mut self, h: ClauseHandle) -> bool {
+        assert_eq!(self.v.decision_level(), 0);
+        let cr = match self.clause_handles.get(h.0 as usize) {
+            Some(&cr) => cr,
+            None => return false,
         };
-        self.watches()[!c0].push(Watcher::new(cr, c1));
-        self.watches()[!c1].push(Watcher::new(cr, c0));
-        if learnt {
-            self.v.num_learnts += 1;
-            self.v.learnts_literals += size as u64;
-        } else {
-            self.v.num_clauses += 1;
-            self.v.clauses_literals += size as u64;
+        if cr == CRef::UNDEF || self.ca.get_ref(cr).mark() == 1 {
+            return false;
+        }
+        self.clause_handles[h.0 as usize] = CRef::UNDEF;
+        self.clauses.retain(|&x| x != cr);
+        if self.produce_proof { self.proof.delete_clause(&self.ca.get_ref(cr)); }
+        if let Some(ref mut lrat) = self.lrat { lrat.delete(cr); }
+        self.smudge_occurrences(cr);
+        self.v.remove_clause(&mut self.ca, &mut self.watches_data, cr);
+
+        // learnt clauses might depend on `cr`
+        for i in 0..self.learnts.len() {
+            let cr = self.learnts[i];
+            if self.produce_proof { self.proof.delete_clause(&self.ca.get_ref(cr)); }
+            if let Some(ref mut lrat) = self.lrat { lrat.delete(cr); }
+            self.v.remove_clause(&mut self.ca, &mut self.watches_data, cr);
         }
+        self.learnts.clear();
+
+        self.check_garbage();
+        true
     }
 
-    /// Revert to the state at given level (keeping all assignment at 'level' but not beyond).
-    fn cancel_until(&mut self, level: u32) {
-        if self.v.decision_level() > level {
-            let trail_lim_last = *self.v.trail_lim.last().expect("trail_lim is empty") as usize;
-            let trail_lim_level = self.v.trail_lim[level as usize] as usize;
-            for c in (trail_lim_level..self.v.trail.len()).rev() {
-                let x = self.v.trail[c].var();
-                self.v.assigns[x] = lbool::UNDEF;
-                if self.phase_saving > 1 || (self.phase_saving == 1 && c > trail_lim_last) {
-                    self.polarity[x] = self.v.trail[c].sign();
-                }
-                self.insert_var_order(x);
+    /// Delete learnt clauses until at most `target` remain.
+    ///
+    /// Like the periodic reduction of the learnt clause database, clauses with
+    /// the lowest activity are deleted first, glue clauses (LBD at most 2) after
+    /// the others, binary clauses last. Clauses that are the reason of a current
+    /// assignment, recently used ones (see `set_reduce_protect`) and those the
+    /// keep callback vetoes (see `set_keep_clause_callback`) are kept, so more
+    /// than `target` clauses may remain.
+    pub fn shrink_clause_db_to(&mut self, target: usize) {
+        if self.learnts.len() <= target {
+            return;
+        }
+        {
+            let ca = &self.ca;
+            self.learnts.sort_unstable_by(|&x, &y| {
+                let x = ca.get_ref(x);
+                let y = ca.get_ref(y);
+                Ord::cmp(&(x.size() <= 2), &(y.size() <= 2))
+                    .then(Ord::cmp(&(x.mark() == GLUE_MARK), &(y.mark() == GLUE_MARK)))
+                    .then(
+                        PartialOrd::partial_cmp(&x.activity(), &y.activity()).expect("NaN activity"),
+                    )
+            });
+        }
+        let mut to_delete = self.learnts.len() - target;
+        let mut j = 0;
+        for i in 0..self.learnts.len() {
+            let cr = self.learnts[i];
+            let cond = to_delete > 0
+                && !self.v.locked(&self.ca, self.ca.get_ref(cr))
+                && !self.recently_used(cr)
+                && !self.keep_clause(cr);
+            if cond {
+                if self.produce_proof { self.proof.delete_clause(&self.ca.get_ref(cr)); }
+                if let Some(ref mut lrat) = self.lrat { lrat.delete(cr); }
+                self.v.remove_clause(&mut self.ca, &mut self.watches_data, cr);
+                to_delete -= 1;
+            } else {
+                self.learnts[j] = cr;
+                j += 1;
             }
-            self.qhead = trail_lim_level as i32;
-            self.v.trail.resize(trail_lim_level, Lit::UNDEF);
-            // eprintln!("decision_level {} -> {}", self.v.trail_lim.len(), level);
-            self.v.trail_lim.resize(level as usize, 0);
         }
-    }
+        let before = self.learnts.len();
+        self.learnts.truncate(j);
+        debug!("shrink_clause_db_to {} (now {})", target, j);
+        let n_clauses = self.clauses.len();
+        self.notify_reduce(n_clauses + before, n_clauses + j);
 
-    pub fn dimacs_model(& self) -> SolverPrintDimacs {
-        SolverPrintDimacs {s: self, model: true}
+        self.check_garbage();
     }
 
-    pub fn dimacs_proof(& self) -> SolverPrintDimacs {
-        SolverPrintDimacs {s: self, model: false}
+    /// Choose how the literals of original clauses are ordered (see `WatchSelect`).
+    ///
+    /// This only affects the clauses added afterwards.
+    pub fn set_watch_selection(&mut self, mode: WatchSelect) {
+        self.watch_select = mode;
     }
 
-    /// Analyze conflict and produce a reason clause.
+    /// Choose which UIP clauses conflict analysis learns (see `UipMode`).
     ///
-    /// # Pre-conditions:
-    ///
-    /// - `out_learnt` is assumed to be cleared.
-    /// - Current decision level must be greater than root level.
-    ///
-    /// # Post-conditions:
-    ///
-    /// - `btlevel` is returned.
-    /// - `out_learnt[0]` is the asserting literal at level `btlevel`.
-    /// - If out_learnt.size() > 1 then `out_learnt[1]` has the greatest decision level of the
-    ///   rest of literals. There may be others from the same level though.
-    fn analyze(&mut self, mut confl: CRef, out_learnt: &mut Vec<Lit>) -> i32 {
-        let mut path_c = 0;
-        let mut p = Lit::UNDEF;
+    /// This is experimental: the default is to learn the first-UIP clause only.
+    pub fn set_uip_mode(&mut self, mode: UipMode) {
+        self.uip_mode = mode;
+    }
 
-        debug!("analyze.start [{}]", self.ca.get_ref(confl).pp_dimacs());
+    /// Choose the order of the literals of learnt clauses (see `LearntSort`).
+    pub fn set_learnt_sort(&mut self, mode: LearntSort) {
+        self.learnt_sort = mode;
+    }
 
-        // Generate conflict clause:
-        //
-        out_learnt.push(Lit::from_idx(0)); // (leave room for the asserting literal)
-        let mut index = self.v.trail.len();
+    /// Only keep the learnt clauses of at most `n` literals.
+    ///
+    /// Longer clauses are still used to backjump and to propagate their asserting
+    /// literal, but they are removed at the next restart (or whenever the search
+    /// backtracks to level 0), instead of being added to the learnt clause database.
+    /// This may make the search repeat work to keep propagation fast, but does not
+    /// affect soundness. There is no limit by default.
+    pub fn set_max_learnt_len(&mut self, n: usize) {
+        self.max_learnt_len = n;
+    }
 
-        loop {
-            debug_assert_ne!(confl, CRef::UNDEF); // (otherwise should be UIP)
-            if self.ca.get_ref(confl).learnt() {
-                self.cla_bump_activity(confl);
+    /// Sort the literals of the learnt clause `c` (as produced by `analyze`) after
+    /// the asserting literal, according to `learnt_sort`.
+    fn sort_learnt(&self, c: &mut [Lit]) {
+        let v = &self.v;
+        match self.learnt_sort {
+            LearntSort::None => (),
+            LearntSort::ByLevel => {
+                // `c[1]` has the highest level among `c[1..]`, and stays first
+                c[1..].sort_by_key(|lit| cmp::Reverse(v.level(lit.var())));
             }
-
-            let c = self.ca.get_mut(confl);
-
-            debug!("analyze.resolve-with [{}]", c.pp_dimacs());
-
-            let mut iter = c.iter();
-            if p != Lit::UNDEF {
-                // skip first literal of `c`, because it should be the one propagated (`p`), on
-                // which we do resolution, so it can't appear in the learnt clause
-                let first = iter.next();
-                debug_assert_eq!(Some(&p), first);
+            LearntSort::ByActivity => {
+                c[2..].sort_by(|x, y| {
+                    PartialOrd::partial_cmp(&v.activity[y.var()], &v.activity[x.var()])
+                        .expect("NaN activity")
+                });
             }
-            for &q in iter {
-                if !self.seen[q.var()].is_seen() && self.v.level(q.var()) > 0 {
-                    self.v.var_bump_activity(&mut self.order_heap_data, q.var());
-                    self.seen[q.var()] = Seen::SOURCE;
-                    if self.v.level(q.var()) >= self.v.decision_level() as i32 {
-                        // at decision level: need to eliminate this lit by resolution
-                        path_c += 1;
-                    } else {
-                        out_learnt.push(q); // part of the learnt clause
-                    }
+        }
+    }
+
+    /// Move the two best literals of `c` to the front, where they are watched:
+    /// unassigned or true literals first, then false literals of the highest level.
+    fn select_watches(&self, c: &mut [Lit]) {
+        let v = &self.v;
+        let rank = |lit: Lit| {
+            if v.value_lit(lit) == lbool::FALSE { v.level(lit.var()) } else { i32::MAX }
+        };
+        for i in 0..cmp::min(2, c.len()) {
+            let mut best = i;
+            for k in i + 1..c.len() {
+                if rank(c[k]) > rank(c[best]) {
+                    best = k;
                 }
             }
+            c.swap(i, best);
+        }
+    }
 
-            // Select next literal in the trail to look at:
-            while !self.seen[self.v.trail[index - 1].var()].is_seen() {
-                index -= 1;
-            }
-            p = self.v.trail[index - 1];
-            index -= 1;
-            confl = self.v.reason(p.var());
-            self.seen[p.var()] = Seen::UNDEF;
-            path_c -= 1;
+    /// Enable or disable detection of duplicate original clauses.
+    ///
+    /// When enabled, adding a clause identical (up to literal order) to an
+    /// original clause already present does not store it again, and
+    /// `add_clause_detailed` returns `AddResult::AlreadyPresent`.
+    pub fn set_detect_duplicates(&mut self, b: bool) {
+        if b && !self.detect_duplicates {
+            // index the clauses that are already there
+            self.detect_duplicates = true;
+            self.rehash_clauses();
+        } else if !b {
+            self.clause_hashes.clear();
+        }
+        self.detect_duplicates = b;
+    }
 
-            if path_c <= 0 {
-                break;
+    /// Rebuild the duplicate detection index (if enabled), after original
+    /// clauses were modified in place.
+    fn rehash_clauses(&mut self) {
+        if !self.detect_duplicates {
+            return;
+        }
+        self.clause_hashes.clear();
+        let mut lits = vec![];
+        for &cr in &self.clauses {
+            let c = self.ca.get_ref(cr);
+            if c.mark() == 1 {
+                continue;
             }
+            lits.clear();
+            lits.extend(c.iter().cloned());
+            lits.sort();
+            self.clause_hashes.entry(hash_lits(&lits)).or_default().push(cr);
         }
-        out_learnt[0] = !p; // literal that will be propagated when the clause is learnt
-        debug_assert!(self.v.value_lit(!p) != lbool::TRUE);
+    }
 
-        // Simplify conflict clause:
-        self.analyze_toclear.clear();
-        self.analyze_toclear.extend_from_slice(&out_learnt);
-        let new_size = if self.ccmin_mode == 2 {
-            let mut j = 1;
-            for i in 1..out_learnt.len() {
-                let lit = out_learnt[i];
-                // can eliminate `lit` only if it's redundant *and* not a decision
-                if self.v.reason(lit.var()) == CRef::UNDEF || !self.lit_redundant(lit) {
-                    out_learnt[j] = lit;
-                    j += 1;
-                }
+    /// Register the original clause `cr` in the occurrence lists (if `use_simp`).
+    fn add_occurrences(&mut self, cr: CRef) {
+        if self.use_simp {
+            for &lit in self.ca.get_ref(cr).iter() {
+                self.occurs[lit.var()].push(cr);
             }
-            j
-        } else if self.ccmin_mode == 1 {
-            let mut j = 1;
-            for i in 1..out_learnt.len() {
-                let lit = out_learnt[i];
-                let x = lit.var();
-                let reason = self.v.reason(x);
+        }
+    }
 
-                let mut retain = true;
-                if reason == CRef::UNDEF {
-                    retain = true;
-                } else {
-                    let c = self.ca.get_ref(reason);
-                    for k in 1..c.size() {
-                        let v = c[k].var();
-                        if !self.seen[v].is_seen() && self.v.level(v) > 0 {
-                            retain = true;
-                            break;
-                        }
-                    }
-                }
-                if retain {
-                    out_learnt[j] = lit;
-                    j += 1;
-                }
+    /// Mark the occurrence lists of the variables of `cr` as dirty, before
+    /// `cr` is removed (if `use_simp`).
+    fn smudge_occurrences(&mut self, cr: CRef) {
+        if self.use_simp {
+            for &lit in self.ca.get_ref(cr).iter() {
+                self.occurs.smudge(lit.var());
             }
-            j
-        } else {
-            out_learnt.len()
-        };
+        }
+    }
 
-        self.max_literals += out_learnt.len() as u64;
-        self.tot_literals += new_size as u64;
-        out_learnt.resize(new_size, Lit::UNDEF);
+    /// Original clauses containing `v`. Requires simp mode.
+    fn occurrences(&mut self, v: Var) -> &[CRef] {
+        debug_assert!(self.use_simp);
+        self.occurs.lookup_mut_pred(v, &ClauseDeleted { ca: &self.ca })
+    }
 
-        // Find correct backtrack level:
-        //
-        let btlevel = if new_size == 1 {
-            0
-        } else {
-            let mut max_i = 1;
-            let mut max_level = self.v.level(out_learnt[max_i].var());
-            // Find the first literal assigned at the next-highest level:
-            for i in 2..out_learnt.len() {
-                let level = self.v.level(out_learnt[i].var());
-                if level > max_level {
-                    max_i = i;
-                    max_level = level;
-                }
-            }
-            // Swap-in this literal at index 1:
-            let p = out_learnt[max_i];
-            out_learnt[max_i] = out_learnt[1];
-            out_learnt[1] = p;
-            self.v.level(p.var())
-        };
+    /// Find an original clause equal to the sorted, duplicate-free `lits`,
+    /// among those with hash `h`.
+    fn find_clause_hashed(&self, h: u64, lits: &[Lit]) -> Option<CRef> {
+        let crs = self.clause_hashes.get(&h)?;
+        crs.iter().cloned().find(|&cr| self.is_original_clause(cr, lits))
+    }
 
-        for &lit in &self.analyze_toclear {
-            self.seen[lit.var()] = Seen::UNDEF; // (`seen[]` is now cleared)
+    /// Is `cr` a live original clause equal to the sorted, duplicate-free `lits`?
+    fn is_original_clause(&self, cr: CRef, lits: &[Lit]) -> bool {
+        let c = self.ca.get_ref(cr);
+        c.mark() != 1 && !c.learnt() && c.size() as usize == lits.len()
+            && c.iter().all(|lit| lits.binary_search(lit).is_ok())
+    }
+
+    /// Is the clause `lits` (in any order) already one of the original clauses?
+    ///
+    /// Like in `add_clause`, literals false at level 0 are ignored, and a clause
+    /// that is a tautology or is satisfied at level 0 counts as present (adding it
+    /// would be useless). Must be called at level 0.
+    ///
+    /// With `set_detect_duplicates`, this is a hash table lookup. Otherwise, the
+    /// occurrence lists (with `set_simp_mode`) or the watch lists of the literals of
+    /// the clause are scanned, in O(occurrences) time.
+    pub fn contains_clause(&self, lits: &[Lit]) -> bool {
+        debug_assert_eq!(self.v.decision_level(), 0);
+        let mut clause = lits.to_vec();
+        clause.sort();
+        clause.dedup();
+        if clause.windows(2).any(|w| w[0] == !w[1])
+            || clause.iter().any(|&lit| self.v.value_lit(lit) == lbool::TRUE)
+        {
+            return true;
+        }
+        clause.retain(|&lit| self.v.value_lit(lit) != lbool::FALSE);
+        if clause.len() < 2 {
+            return false; // (units are not stored, they would be true)
+        }
+
+        if self.detect_duplicates {
+            self.find_clause_hashed(hash_lits(&clause), &clause).is_some()
+        } else if self.use_simp {
+            // the variable with the fewest occurrences
+            let x = clause.iter().map(|lit| lit.var())
+                .min_by_key(|&x| self.occurs[x].len())
+                .expect("clause is not empty");
+            self.occurs[x].iter().any(|&cr| self.is_original_clause(cr, &clause))
+        } else {
+            // each stored clause is watched by two of its literals
+            clause.iter().any(|&lit| {
+                self.watches_data[!lit].iter().any(|w| self.is_original_clause(w.cref, &clause))
+            })
         }
-        debug_assert!(out_learnt.iter().all(|&l| self.v.value_lit(l) == lbool::FALSE));
-        btlevel
     }
 
-    // COULD THIS BE IMPLEMENTED BY THE ORDINARY "analyze" BY SOME REASONABLE GENERALIZATION?
-    /// Specialized analysis procedure to express the final conflict in terms of assumptions.
-    /// Calculates the (possibly empty) set of assumptions that led to the assignment of `p`, and
-    /// stores the result in `out_conflict`.
-    fn analyze_final(&mut self, p: Lit, out_conflict: &mut LSet) {
-        out_conflict.clear();
-        out_conflict.insert(p);
-        debug!("analyze_final lit={:?}", p);
+    /// Length of the watch list of `lit`, not counting deleted clauses.
+    ///
+    /// These are the clauses visited by propagation when `lit` becomes true,
+    /// i.e. those where `!lit` is one of the two watched literals.
+    pub fn watch_count(&self, lit: Lit) -> usize {
+        let pred = WatcherDeleted { ca: &self.ca };
+        self.watches_data[lit].iter().filter(|w| !pred.deleted(w)).count()
+    }
 
-        if self.v.decision_level() == 0 {
-            if self.produce_proof { self.proof.create_clause(out_conflict); }
-            return;
+    /// Current activity of `v` in the decision heuristic.
+    pub fn activity(&self, v: Var) -> f64 {
+        self.v.activity[v]
+    }
+
+    /// Set the activity of `v` in the decision heuristic, which must be finite.
+    ///
+    /// Variables with a higher activity are picked first for decisions.
+    pub fn set_activity(&mut self, v: Var, a: f64) {
+        assert!(a.is_finite(), "activity must be finite");
+        let old = self.v.activity[v];
+        self.v.activity[v] = a;
+        let mut order_heap = self.order_heap();
+        if order_heap.in_heap(v) {
+            if a > old {
+                order_heap.decrease(v);
+            } else {
+                order_heap.increase(v);
+            }
         }
+    }
 
-        self.seen[p.var()] = Seen::SOURCE;
+    /// Increase the activity of each variable of `vars` by `amount`.
+    ///
+    /// Unlike the bumps made during conflict analysis, `amount` is absolute:
+    /// it is not scaled by the current variable increment.
+    pub fn bump_vars(&mut self, vars: &[Var], amount: f64) {
+        assert!(amount >= 0.0, "activity can only be increased");
+        for &v in vars {
+            self.v.var_bump_activity_by(&mut self.order_heap_data, v, amount);
+        }
+    }
 
-        for &lit in self.v.trail[self.v.trail_lim[0] as usize..].iter().rev() {
-            let x = lit.var();
-            if self.seen[x].is_seen() {
-                let reason = self.v.reason(x);
-                if reason == CRef::UNDEF {
-                    debug_assert!(self.v.level(x) > 0);
-                    out_conflict.insert(!lit);
-                } else {
-                    let c = self.ca.get_mut(reason);
-                    for j in 1..c.size() {
-                        if self.v.level(c[j].var()) > 0 {
-                            self.seen[c[j].var()] = Seen::SOURCE;
-                        }
-                    }
+    /// Give the variables of `order` higher activities than all other variables,
+    /// in decreasing order, so that the solver branches on them first.
+    ///
+    /// This only sets the initial ordering: activities are then updated as usual
+    /// by conflict analysis, so VSIDS eventually overrides it.
+    pub fn set_decision_priority(&mut self, order: &[Var]) {
+        let top = self.v.activity.iter().map(|(_, &a)| a).fold(0.0, f64::max);
+        let n = order.len();
+        for (i, &v) in order.iter().enumerate() {
+            self.v.activity[v] = top + (n - i) as f64 * self.v.var_inc;
+        }
+        self.rebuild_order_heap();
+    }
+
+    /// Order the decision variables with `cmp(activity, x, y)` instead of by
+    /// decreasing activity: the solver branches first on the variables that
+    /// compare as `Less` (e.g. the most active ones among those of highest priority).
+    ///
+    /// `cmp` must be a total preorder (ties are broken by variable index), and a
+    /// variable must not compare as greater when its activity increases (the heap
+    /// is only repaired in that direction).
+    /// Clones of the solver share the comparator.
+    pub fn set_order_comparator<F>(&mut self, cmp: F)
+        where F: Fn(&VMap<f64>, Var, Var) -> cmp::Ordering + 'static
+    {
+        self.v.order_cmp = OrderCmp(Some(Rc::new(cmp)));
+        self.rebuild_order_heap();
+    }
+
+    /// Go back to ordering the decision variables by decreasing activity.
+    pub fn reset_order_comparator(&mut self) {
+        self.v.order_cmp = OrderCmp(None);
+        self.rebuild_order_heap();
+    }
+
+    /// Set the activity of each variable from its occurrences in the original
+    /// clauses (Jeroslow-Wang): an occurrence in a clause of `k` literals counts
+    /// for `2^-k`, so that variables of short clauses are tried first.
+    /// Must be called at level 0, typically before the first `solve`.
+    ///
+    /// Clauses satisfied at level 0 and false literals are ignored. Activities
+    /// are scaled so that the highest one equals the current variable increment:
+    /// the bumps made by conflict analysis quickly take over.
+    pub fn seed_activities_by_occurrence(&mut self) {
+        assert_eq!(self.v.decision_level(), 0);
+        let mut score: VMap<f64> = VMap::new();
+        score.reserve_default(self.next_var);
+        for c in self.original_clauses() {
+            if c.iter().any(|&lit| self.v.value_lit(lit) == lbool::TRUE) {
+                continue;
+            }
+            let len = c.iter().filter(|&&lit| self.v.value_lit(lit) == lbool::UNDEF).count();
+            let w = f64::powi(0.5, len as i32);
+            for &lit in c.iter() {
+                if self.v.value_lit(lit) == lbool::UNDEF {
+                    score[lit.var()] += w;
                 }
-                self.seen[x] = Seen::UNDEF;
             }
         }
+        let top = score.values().cloned().fold(0.0, f64::max);
+        if top > 0.0 {
+            for v in self.vars() {
+                self.v.activity[v] = self.v.var_inc * score[v] / top;
+            }
+        }
+        self.rebuild_order_heap();
+    }
 
-        self.seen[p.var()] = Seen::UNDEF;
-        debug_assert!(self.seen.iter().all(|(_,&s)| s==Seen::UNDEF));
-        if self.produce_proof { self.proof.create_clause(out_conflict); }
+    /// Read a branching order from `r`, as DIMACS variable indices (`1` is the
+    /// first variable) separated by whitespace, with optional `c` comment lines.
+    /// The variables are then prioritized in this order, as with `set_decision_priority`;
+    /// repeated indices are ignored, and so is `0`, which may terminate lines as
+    /// in DIMACS clauses.
+    ///
+    /// As activities are bumped during the search, VSIDS eventually overrides
+    /// this order. To keep it, use a custom comparator (see `set_order_comparator`).
+    pub fn load_var_order<R: BufRead>(&mut self, mut r: R) -> io::Result<()> {
+        let mut seen = HashSet::new();
+        let mut order = vec![];
+        for i in dimacs::read_ints(&mut r)? {
+            if i == 0 {
+                continue;
+            }
+            if i < 0 || i as u32 > self.num_vars() {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                    format!("invalid variable {} in the variable order", i)));
+            }
+            let v = Var::from_idx(i as u32 - 1);
+            if seen.insert(v) {
+                order.push(v);
+            }
+        }
+        self.set_decision_priority(&order);
+        Ok(())
     }
 
-    // Check if `p` can be removed from a conflict clause. It can be removed
-    // if it is propagation-implied by literals of level 0 exclusively
-    // TODO: port abstract levels from minisat
-    fn lit_redundant(&mut self, mut p: Lit) -> bool {
-        debug_assert!(self.seen[p.var()] == Seen::UNDEF || self.seen[p.var()] == Seen::SOURCE);
-        debug_assert!(self.v.reason(p.var()) != CRef::UNDEF);
+    /// Restrict decisions to `vars`: the other decision variables are temporarily
+    /// made non-decision variables, until `clear_decision_scope` (or another call
+    /// to `set_decision_scope`, which replaces the scope). Must be called at level 0.
+    ///
+    /// Search stops once the variables of the scope are assigned without conflict.
+    /// `solve_limited` then returns `lbool::TRUE` only if this satisfies all the
+    /// clauses (variables that propagation left unassigned are `UNDEF` in the model),
+    /// and `lbool::UNDEF` otherwise: the scope was too small to decide the query.
+    /// `lbool::FALSE` is a real answer. `solve_steps` cannot be used with a scope.
+    pub fn set_decision_scope(&mut self, vars: &[Var]) {
+        assert_eq!(self.v.decision_level(), 0);
+        self.clear_decision_scope();
+        let mut in_scope = vec![false; self.num_vars() as usize];
+        for &v in vars {
+            in_scope[v.idx() as usize] = true;
+        }
+        for v in self.vars() {
+            if self.decision[v] && !in_scope[v.idx() as usize] {
+                self.set_decision_var(v, false);
+                self.out_of_scope.push(v);
+            }
+        }
+        self.rebuild_order_heap();
+    }
 
-        let mut cr = self.v.reason(p.var());
-        let stack = &mut self.analyze_stack;
-        stack.clear();
+    /// Make all the variables excluded by `set_decision_scope` decision variables again
+    /// (except those eliminated since).
+    pub fn clear_decision_scope(&mut self) {
+        if self.out_of_scope.is_empty() {
+            return;
+        }
+        for v in mem::take(&mut self.out_of_scope) {
+            if !self.eliminated[v] {
+                self.set_decision_var(v, true);
+            }
+        }
+        self.rebuild_order_heap();
+    }
 
-        let mut i: u32 = 1;
-        loop {
-            let c = self.ca.get_mut(cr);
-            if i < c.size() {
-                // Checking `p`-parents `l`:
-                let l: Lit = c[i];
+    /// Enable or disable the collection of per-variable assignment statistics.
+    ///
+    /// Statistics are reset when this is enabled. See `var_activity_report`.
+    pub fn set_var_stats(&mut self, b: bool) {
+        if b && !self.v.var_stats {
+            self.v.var_assigns.clear();
+            self.v.var_levels.clear();
+            for v in self.vars() {
+                self.v.var_assigns.insert_default(v, 0);
+                self.v.var_levels.insert_default(v, 0);
+            }
+        }
+        self.v.var_stats = b;
+    }
 
-                // Variable at level 0 or previously removable:
-                if self.v.level(l.var()) == 0 || self.seen[l.var()] == Seen::SOURCE
-                    || self.seen[l.var()] == Seen::REMOVABLE
-                {
-                    i += 1;
-                    continue;
-                }
+    /// For each variable, the average level at which it was assigned and the
+    /// number of times it was assigned, since `set_var_stats(true)` was called.
+    ///
+    /// Returns an empty vector if statistics were never enabled.
+    pub fn var_activity_report(&self) -> Vec<(Var, f64, u64)> {
+        let v = &self.v;
+        v.var_assigns.iter()
+            .map(|(x, &n)| {
+                let avg = if n == 0 { 0.0 } else { v.var_levels[x] as f64 / n as f64 };
+                (x, avg, n)
+            })
+            .collect()
+    }
 
-                // Check if variable can not be removed for some local reason
-                // (e.g. if it's a decision or has failed to be removed already)
-                if self.v.reason(l.var()) == CRef::UNDEF || self.seen[l.var()] == Seen::FAILED {
-                    stack.push(ShrinkStackElem::new(0, p));
-                    for elem in stack.iter() {
-                        // elements in the stack can't justify making a literal
-                        // redundant, so remember that
-                        if self.seen[elem.l.var()] == Seen::UNDEF {
-                            self.seen[elem.l.var()] = Seen::FAILED;
-                            self.analyze_toclear.push(elem.l);
-                        }
-                    }
+    /// Set the initial restart limit, in conflicts (the unit of the Luby sequence
+    /// if `luby_restart` is enabled).
+    pub fn set_restart_first(&mut self, n: i32) {
+        assert!(n >= 1, "restart_first must be at least 1");
+        self.restart_first = n;
+    }
 
-                    return false;
-                }
+    /// Set the factor by which the restart limit grows (the base of the Luby
+    /// sequence if `luby_restart` is enabled).
+    pub fn set_restart_inc(&mut self, f: f64) {
+        assert!(f > 1.0 && f < f64::INFINITY, "restart_inc must be finite and greater than 1");
+        self.restart_inc = f;
+    }
 
-                // Recursively check if `l` is redundant itself
-                stack.push(ShrinkStackElem::new(i, p));
-                i = 0;
-                p = l;
-                cr = self.v.reason(p.var());
-            } else {
-                // Finished with current element `p` and reason `c`:
-                if self.seen[p.var()] == Seen::UNDEF {
-                    self.seen[p.var()] = Seen::REMOVABLE;
-                    self.analyze_toclear.push(p);
-                }
+    /// Set the decay factor of learnt clause activities (see `SolverOpts::clause_decay`).
+    ///
+    /// Learnt clauses involved in conflicts are bumped by an increment that grows
+    /// by `1/d` after each conflict, so a smaller `d` favors recent clauses
+    /// when the learnt clause database is reduced.
+    pub fn set_clause_decay(&mut self, d: f64) {
+        assert!(0.0 < d && d < 1.0, "clause_decay must be in (0,1)");
+        self.clause_decay = d;
+    }
 
-                // Terminate with success if stack is empty:
-                if stack.len() == 0 {
-                    break;
-                }
+    /// Current activity of each learnt clause, as used to choose the clauses
+    /// removed when the learnt clause database is reduced.
+    ///
+    /// Activities are only meaningful relative to each other, as they are
+    /// periodically rescaled.
+    pub fn learnt_activities(&self) -> Vec<f32> {
+        self.learnts.iter().map(|&cr| self.ca.get_ref(cr).activity()).collect()
+    }
 
-                // Continue with top element on stack:
-                let last = stack.pop().expect("stack is empty");
-                i = last.i;
-                p = last.l;
-                cr = self.v.reason(p.var());
-            }
-            i += 1;
+    /// Clean the clause database at level 0 at most once every `conflicts`
+    /// conflicts, rather than depending on the number of propagations since the
+    /// last cleaning. `0` restores the default behavior.
+    ///
+    /// Cleaning only happens when new variables were fixed at level 0, and the
+    /// search only goes back to level 0 on restarts and learnt unit clauses.
+    /// See also `set_simp_props_threshold`.
+    pub fn set_simp_interval(&mut self, conflicts: u64) {
+        self.simp_interval = conflicts;
+    }
+
+    /// Clean the clause database at level 0 only once `props` propagations were
+    /// made since the last cleaning, rather than as many propagations as there
+    /// are literals in the clause database. A negative value restores the default.
+    ///
+    /// Lower values help when cleaning is cheap and removes many clauses, higher
+    /// values when it is expensive. This has no effect while an interval is set
+    /// with `set_simp_interval`, which replaces the propagation count.
+    pub fn set_simp_props_threshold(&mut self, props: i64) {
+        self.simp_props_threshold = props;
+        if props >= 0 {
+            self.simp_db_props = cmp::min(self.simp_db_props, props);
         }
+    }
 
-        true
+    /// Print a trace of the search on standard error: `1` traces restarts,
+    /// `2` also conflicts, and `3` also decisions. `0` (the default) disables it.
+    ///
+    /// Only available with the `trace` feature: without it, the tracing code is
+    /// compiled out of the search loop.
+    ///
+    /// Each event is a line of space-separated fields, with literals in DIMACS format:
+    ///
+    /// - `c trace restart <conflicts> <trail size>`, before backtracking;
+    /// - `c trace conflict <conflicts> <level> <learnt size> <backjump level>`;
+    /// - `c trace decide <lit> <level>`, or `assume` for an assumption, with the new level.
+    #[cfg(feature = "trace")]
+    pub fn set_trace(&mut self, level: u8) {
+        self.trace = level;
     }
 
-    /// Propagates all enqueued facts. If a conflict arises, the conflicting clause is returned,
-    /// otherwise CRef_Undef.
+    /// When collecting garbage, copy the clauses in the order of the learnt and
+    /// original clause lists, rather than in the order of the watch lists.
     ///
-    /// # Post-conditions:
+    /// This makes the scans of these lists (e.g. when reducing the learnt clause
+    /// database) sequential in memory, at the expense of propagation locality.
+    pub fn set_gc_locality(&mut self, b: bool) {
+        self.gc_locality = b;
+    }
+
+    /// Perturb each restart limit by a random fraction of at most `frac` of its
+    /// value, to diversify solvers that only differ by their `random_seed`.
     ///
-    /// - the propagation queue is empty, even if there was a conflict.
-    fn propagate(&mut self) -> CRef {
-        let mut confl = CRef::UNDEF;
-        let mut num_props: u32 = 0;
+    /// The perturbation is drawn from the solver's random number generator,
+    /// so it is reproducible for a given seed. `0` disables it (the default).
+    pub fn set_restart_jitter(&mut self, frac: f64) {
+        assert!((0.0..1.0).contains(&frac), "restart jitter must be in [0,1)");
+        self.restart_jitter = frac;
+    }
 
-        while (self.qhead as usize) < self.v.trail.len() {
-            // 'p` is enqueued fact to propagate.
-            let p = self.v.trail[self.qhead as usize];
+    /// Set the fraction of wasted memory in the clause arena that triggers
+    /// a garbage collection (see `SolverOpts::garbage_frac`).
+    pub fn set_garbage_frac(&mut self, f: f64) {
+        assert!(0.0 < f && f < f64::INFINITY);
+        self.garbage_frac = f;
+    }
 
-            // eprintln!("propagating trail[{}] = {:?}", self.qhead, p);
-            self.qhead += 1;
-            let watches_data_ptr: *mut OccListsData<_, _> = &mut self.watches_data;
-            // let ws = self.watches().lookup_mut(p);
-            let ws = self.watches_data
-                .lookup_mut_pred(p, &WatcherDeleted { ca: &self.ca });
-            // eprintln!("watcher of {:?} = {:?}", p, ws);
-            let mut i: usize = 0;
-            let mut j: usize = 0;
-            let end: usize = ws.len();
-            num_props += 1;
-            'clauses: while i < end {
-                // Try to avoid inspecting the clause:
-                let blocker = ws[i].blocker;
-                if self.v.value_lit(blocker) == lbool::TRUE {
-                    ws[j] = ws[i];
-                    j += 1;
-                    i += 1;
-                    continue;
-                }
+    /// Size in bytes of the clause arena, including the space wasted by
+    /// deleted clauses (see `clause_bytes_wasted`).
+    pub fn clause_bytes_used(&self) -> usize {
+        self.ca.bytes_used()
+    }
 
-                // Make sure the false literal is data[1]:
-                let cr = ws[i].cref;
-                let mut c = self.ca.get_mut(cr);
-                let false_lit = !p;
-                if c[0] == false_lit {
-                    c[0] = c[1];
-                    c[1] = false_lit;
-                }
-                debug_assert_eq!(c[1], false_lit);
-                i += 1;
+    /// Size in bytes of the deleted clauses still in the clause arena, until
+    /// the next garbage collection.
+    pub fn clause_bytes_wasted(&self) -> usize {
+        self.ca.bytes_wasted()
+    }
 
-                // If 0th watch is true, then clause is already satisfied.
-                let first = c[0];
-                let w = Watcher::new(cr, first);
-                if first != blocker && self.v.value_lit(first) == lbool::TRUE {
-                    ws[j] = w;
-                    j += 1;
-                    continue;
-                }
+    /// Also trigger a garbage collection as soon as the wasted memory in the
+    /// clause arena exceeds `b` bytes, even if it is below `garbage_frac`.
+    ///
+    /// By default there is no such absolute threshold.
+    pub fn set_garbage_min_bytes(&mut self, b: usize) {
+        self.garbage_min_bytes = b;
+    }
+
+    /// Protect the learnt clauses that propagated a literal during the last `k`
+    /// conflicts from the periodic reduction of the clause database, so that
+    /// useful clauses are not deleted and learnt again right away.
+    ///
+    /// `0` (the default) disables the protection.
+    pub fn set_reduce_protect(&mut self, k: u64) {
+        self.reduce_protect = k;
+    }
+
+    /// Stamp stored in a learnt clause when it propagates: the number of
+    /// conflicts, truncated, and never `0` (which means "never used").
+    #[inline(always)]
+    fn use_stamp(&self) -> u32 {
+        cmp::max((self.conflicts as u32).wrapping_add(1), 1)
+    }
+
+    /// Whether `cr` is protected by `reduce_protect`.
+    fn recently_used(&self, cr: CRef) -> bool {
+        let c = self.ca.get_ref(cr);
+        if self.reduce_protect == 0 || !c.learnt() || c.touched() == 0 {
+            return false;
+        }
+        let k = cmp::min(self.reduce_protect, u32::MAX as u64) as u32;
+        self.use_stamp().wrapping_sub(c.touched()) < k
+    }
+
+    /// Enable or disable an additional activity bump for the variables of each
+    /// learnt clause, after minimization (disabled by default).
+    ///
+    /// All the variables involved in a conflict are bumped during analysis; with
+    /// this option, those that remain in the learnt clause are bumped twice, which
+    /// focuses the search more on the learnt clauses, as in some Glucose variants.
+    pub fn set_bump_learnt_literals(&mut self, b: bool) {
+        self.bump_learnt_lits = b;
+    }
+
+    /// Enable or disable simp mode, in which occurrence lists of original clauses
+    /// are maintained as clauses are added and removed.
+    ///
+    /// Disabling it frees the occurrence lists.
+    pub fn set_simp_mode(&mut self, b: bool) {
+        if b && !self.use_simp {
+            for v in self.vars() {
+                self.occurs.init(v);
+            }
+            for i in 0..self.clauses.len() {
+                let cr = self.clauses[i];
+                if self.ca.get_ref(cr).mark() != 1 {
+                    for &lit in self.ca.get_ref(cr).iter() {
+                        self.occurs[lit.var()].push(cr);
+                    }
+                }
+            }
+        } else if !b {
+            self.occurs.free();
+        }
+        self.use_simp = b;
+    }
+
+    /// Enable or disable eager propagation of unit clauses.
+    ///
+    /// When enabled, adding a unit clause immediately propagates it, so that
+    /// a conflict at level 0 makes `add_clause_reuse` return `false` right away
+    /// instead of being detected by the next `simplify` or `solve`.
+    pub fn set_eager_unit_propagation(&mut self, b: bool) {
+        self.eager_unit_propagation = b;
+    }
+
+    /// Enable or disable the collection of learnt clause size and LBD histograms.
+    ///
+    /// See `learnt_size_histogram` and `learnt_lbd_histogram`.
+    pub fn set_learnt_histograms(&mut self, b: bool) {
+        self.learnt_histograms = b;
+    }
+
+    /// `learnt_size_histogram()[n]` is the number of learnt clauses of size `n`
+    /// produced so far (only collected if `set_learnt_histograms(true)` was called).
+    pub fn learnt_size_histogram(&self) -> Vec<u64> {
+        self.learnt_size_hist.clone()
+    }
+
+    /// `learnt_lbd_histogram()[n]` is the number of learnt clauses with LBD `n`
+    /// (number of distinct decision levels in the clause) produced so far.
+    pub fn learnt_lbd_histogram(&self) -> Vec<u64> {
+        self.learnt_lbd_hist.clone()
+    }
+
+    /// Enable or disable the collection of the backjump histogram.
+    ///
+    /// See `backjump_histogram`.
+    pub fn set_backjump_histogram(&mut self, b: bool) {
+        self.backjump_histogram = b;
+    }
+
+    /// `backjump_histogram()[n]` is the number of conflicts after which the solver
+    /// backjumped `n` levels (from the conflict level to the assertion level of the
+    /// learnt clause), so far (only collected if `set_backjump_histogram(true)` was called).
+    pub fn backjump_histogram(&self) -> Vec<u64> {
+        self.backjump_hist.clone()
+    }
+
+    /// Literal Block Distance of `lits`: the number of distinct decision levels
+    /// among its literals, which must all be assigned.
+    fn compute_lbd(&mut self, lits: &[Lit]) -> u32 {
+        self.lbd_stamp += 1;
+        let mut lbd = 0;
+        for &lit in lits {
+            let level = self.v.level(lit.var()) as usize;
+            if level >= self.lbd_seen.len() {
+                self.lbd_seen.resize(level + 1, 0);
+            }
+            if self.lbd_seen[level] != self.lbd_stamp {
+                self.lbd_seen[level] = self.lbd_stamp;
+                lbd += 1;
+            }
+        }
+        lbd
+    }
+
+    fn var_decay_activity(&mut self) {
+        self.v.var_inc *= 1.0 / self.var_decay;
+    }
+
+    fn cla_decay_activity(&mut self) {
+        self.cla_inc *= 1.0 / self.clause_decay;
+    }
+
+    /// Increase a clause with the current 'bump' value, rescaling learnt clause activities if needed.
+    fn cla_bump_activity(&mut self, cr: CRef) {
+        let new_activity = {
+            let mut c = self.ca.get_mut(cr);
+            let r = c.activity() + self.cla_inc as f32;
+            c.set_activity(r);
+            r
+        };
+        if new_activity > 1e20 {
+            // Rescale:
+            for &learnt in &self.learnts {
+                let mut c = self.ca.get_mut(learnt);
+                let r = c.activity() * 1e-20;
+                c.set_activity(r);
+            }
+            self.cla_inc *= 1e-20;
+        }
+    }
+
+    fn set_decision_var(&mut self, v: Var, b: bool) {
+        if b && !self.decision[v] {
+            self.dec_vars += 1;
+        } else if !b && self.decision[v] {
+            self.dec_vars -= 1;
+        }
+        self.decision[v] = b;
+        self.insert_var_order(v);
+    }
+
+    fn insert_var_order(&mut self, x: Var) {
+        if !self.order_heap().in_heap(x) && self.decision[x] {
+            self.order_heap().insert(x);
+        }
+    }
+
+    /// Remove the variables fixed at level 0 since the last call from the order heap.
+    fn remove_fixed_from_order(&mut self) {
+        let mut fixed = mem::take(&mut self.v.fixed_pending);
+        {
+            let mut order_heap = self.order_heap();
+            for &x in &fixed {
+                if order_heap.in_heap(x) {
+                    order_heap.remove(x);
+                }
+            }
+        }
+        fixed.clear();
+        self.v.fixed_pending = fixed;
+    }
+
+    /// Pick a literal to make a decision with
+    fn pick_branch_lit(&mut self) -> Lit {
+        if !self.v.fixed_pending.is_empty() {
+            self.remove_fixed_from_order();
+        }
+
+        // Pinned first decision, then user decision:
+        let mut next = mem::replace(&mut self.first_decision, Var::UNDEF);
+        if next != Var::UNDEF && (self.v.value(next) != lbool::UNDEF || !self.decision[next]) {
+            next = Var::UNDEF;
+        }
+        if next == Var::UNDEF {
+            next = self.callback_decision();
+        }
+
+        // Random decision:
+        if next == Var::UNDEF
+            && drand(&mut self.random_seed) < self.random_var_freq && !self.order_heap().is_empty() {
+            let idx_tmp = irand(&mut self.random_seed, self.order_heap_data.len() as i32) as usize;
+            next = self.order_heap_data[idx_tmp];
+            if self.v.value(next) == lbool::UNDEF && self.decision[next] {
+                self.rnd_decisions += 1;
+            }
+        }
+
+        // Activity based decision:
+        while next == Var::UNDEF || self.v.value(next) != lbool::UNDEF || !self.decision[next] {
+            let mut order_heap = self.order_heap();
+            if order_heap.is_empty() {
+                next = Var::UNDEF;
+                break;
+            } else {
+                next = order_heap.remove_min();
+            }
+        }
+
+        // Choose polarity based on different polarity modes (global or per-variable):
+        if next == Var::UNDEF {
+            Lit::UNDEF
+        } else if self.user_pol[next] != lbool::UNDEF {
+            Lit::new(next, self.user_pol[next] == lbool::TRUE)
+        } else if let Some(pol) = self.callback_polarity(next) {
+            Lit::new(next, pol)
+        } else if self.rnd_pol {
+            Lit::new(next, drand(&mut self.random_seed) < 0.5)
+        } else {
+            Lit::new(next, self.polarity[next])
+        }
+    }
+
+    /// Ask the decision callback, if any, for the next decision variable.
+    ///
+    /// Returns `Var::UNDEF` if there is no callback, or if it returned `None` or
+    /// a variable that cannot be decided on.
+    fn callback_decision(&mut self) -> Var {
+        let mut cb = match self.decision_cb.0.take() {
+            None => return Var::UNDEF,
+            Some(cb) => cb,
+        };
+        let res = cb(self);
+        self.decision_cb.0 = Some(cb);
+        match res {
+            Some(v) if v.idx() < self.num_vars()
+                && self.decision[v] && self.v.value(v) == lbool::UNDEF => v,
+            _ => Var::UNDEF,
+        }
+    }
+
+    /// Ask the polarity callback, if any, for the polarity of `v`
+    fn callback_polarity(&mut self, v: Var) -> Option<bool> {
+        match self.polarity_cb.0 {
+            None => None,
+            Some(ref mut f) => {
+                let pol = f(v);
+                if pol == lbool::UNDEF { None } else { Some(pol == lbool::TRUE) }
+            }
+        }
+    }
+
+    /// Begins a new decision level.
+    fn new_decision_level(&mut self) {
+        // eprintln!(
+        //     "decision_level {} -> {}",
+        //     self.v.trail_lim.len(),
+        //     self.v.trail_lim.len() + 1
+        // );
+        self.v.trail_lim.push(self.v.trail.len() as i32);
+    }
+
+    fn simplify_internal(&mut self) -> bool {
+        self.simplify_detailed().ok
+    }
+
+    /// Same as `simplify`, but also report what was simplified.
+    ///
+    /// The clause database is only cleaned if variables were fixed since the last
+    /// time it was, and enough propagations were made since; otherwise, only
+    /// propagation is done and the report only counts fixed variables.
+    pub fn simplify_detailed(&mut self) -> SimplifyReport {
+        debug_assert_eq!(self.v.decision_level(), 0);
+        let mut report = SimplifyReport::default();
+
+        let assigns_before = self.v.num_assigns();
+        if !self.ok || self.propagate() != CRef::UNDEF {
+            self.ok = false;
+            return report;
+        }
+        report.ok = true;
+        report.vars_fixed = self.v.num_assigns() - assigns_before;
+
+        let throttled = if self.simp_interval > 0 {
+            self.conflicts < self.simp_db_conflicts.saturating_add(self.simp_interval)
+        } else {
+            self.simp_db_props > 0
+        };
+        if self.v.num_assigns() as i32 == self.simp_db_assigns || throttled {
+            return report;
+        }
+
+        // Remove satisfied learnt clauses
+        let (clauses, lits) = self.remove_satisfied(ClauseSet::Learnt);
+        report.clauses_removed += clauses;
+        report.literals_trimmed += lits;
+        if self.remove_satisfied {
+            // FIXME: seems very wrong in incremental context (see incremental regression test1)
+            // remove satisfied normal clauses
+            let (clauses, lits) = self.remove_satisfied(ClauseSet::Original);
+            report.clauses_removed += clauses;
+            report.literals_trimmed += lits;
+            if lits > 0 {
+                // the trimmed clauses have a new hash
+                self.rehash_clauses();
+            }
+        }
+        self.check_garbage();
+        self.rebuild_order_heap();
+
+        self.simp_db_assigns = self.v.num_assigns() as i32;
+        self.simp_db_conflicts = self.conflicts;
+        self.simp_db_props = if self.simp_props_threshold >= 0 {
+            self.simp_props_threshold
+        } else {
+            // (shouldn't depend on stats really, but it will do for now)
+            (self.v.clauses_literals + self.v.learnts_literals) as i64
+        };
+
+        report
+    }
+
+    /// Search for a model the specified number of conflicts.
+    /// NOTE! Use negative value for 'nof_conflicts' indicate infinity.
+    ///
+    /// # Output:
+    ///
+    /// 'l_True' if a partial assigment that is consistent with respect to the clauseset is found. If
+    /// all variables are decision variables, this means that the clause set is satisfiable. 'l_False'
+    /// if the clause set is unsatisfiable. 'l_Undef' if the bound on number of conflicts is reached.
+    fn search(&mut self, nof_conflicts: i32) -> lbool {
+        self.starts += 1;
+        let mut conflict_c = 0;
+        self.search_resumable(nof_conflicts, &mut conflict_c, u64::MAX)
+    }
+
+    /// Same as `search`, where `conflict_c` conflicts were already made since the
+    /// last restart, but also stop right after the conflict that makes the total
+    /// number of conflicts reach `pause_at` (the search can then be resumed with
+    /// the same `conflict_c`).
+    fn search_resumable(&mut self, nof_conflicts: i32, conflict_c: &mut i32, pause_at: u64) -> lbool {
+        debug_assert!(self.ok);
+        let mut learnt_clause: Vec<Lit> = vec![];
+
+        loop {
+            let mut confl = self.propagate();
+            if confl == CRef::UNDEF && self.ext_propagator.0.is_some() {
+                match self.external_propagate() {
+                    ExternalClause::None => (),
+                    ExternalClause::Added => continue,
+                    ExternalClause::Conflict(cr) => confl = cr,
+                    ExternalClause::Unsat => return lbool::FALSE,
+                }
+            }
+            if confl != CRef::UNDEF {
+                // CONFLICT
+                self.conflicts += 1;
+                *conflict_c += 1;
+                if self.v.decision_level() == 0 {
+                    return lbool::FALSE;
+                }
+
+                learnt_clause.clear();
+                let backtrack_level = self.analyze(confl, &mut learnt_clause);
+                debug_assert!(!is_tautology(&learnt_clause),
+                    "tautological learnt clause {:?}", learnt_clause);
+                if self.learnt_sort != LearntSort::None && learnt_clause.len() > 2 {
+                    self.sort_learnt(&mut learnt_clause);
+                }
+                self.last_learnt_lbd = self.compute_lbd(&learnt_clause);
+                let glue = learnt_clause.len() > 1 && self.last_learnt_lbd <= 2;
+                if self.produce_proof { self.proof.create_clause(&learnt_clause); } // emit proof
+                let lrat_id = self.lrat_learnt(confl, &learnt_clause);
+                if cfg!(feature = "trace") && self.trace >= 2 {
+                    eprintln!("c trace conflict {} {} {} {}", self.conflicts,
+                        self.v.decision_level(), learnt_clause.len(), backtrack_level);
+                }
+                if self.backjump_histogram {
+                    let dist = self.v.decision_level() - backtrack_level as u32;
+                    incr_hist(&mut self.backjump_hist, dist as usize);
+                }
+                self.cancel_until(backtrack_level as u32);
+
+                // propagate the only lit of `learnt_clause` that isn't false
+                if learnt_clause.len() == 1 {
+                    // directly propagate the unit clause at level 0
+                    self.v.unchecked_enqueue(learnt_clause[0], CRef::UNDEF);
+                    self.lrat_bind(&learnt_clause, CRef::UNDEF, lrat_id);
+                } else {
+                    // propagate the lit, justified by `cr`
+                    let cr = self.ca.alloc_with_learnt(&learnt_clause, true);
+                    let transient = learnt_clause.len() > self.max_learnt_len;
+                    if glue && !transient {
+                        self.ca.get_mut(cr).set_mark(GLUE_MARK);
+                        self.v.glue_clauses += 1;
+                    }
+                    self.lrat_bind(&learnt_clause, cr, lrat_id);
+                    if transient {
+                        self.transient.push(cr);
+                    } else {
+                        self.learnts.push(cr);
+                    }
+                    self.attach_clause(cr);
+                    self.cla_bump_activity(cr);
+                    self.v.unchecked_enqueue(learnt_clause[0], cr);
+                }
+
+                self.var_decay_activity();
+                self.cla_decay_activity();
+
+                self.learntsize_adjust_cnt -= 1;
+                if self.learntsize_adjust_cnt == 0 {
+                    self.learntsize_adjust_confl *= self.learntsize_adjust_inc;
+                    self.learntsize_adjust_cnt = self.learntsize_adjust_confl as i32;
+                    self.max_learnts *= self.learntsize_inc;
+
+                    if self.verbosity >= 1 {
+                        let trail_lim_head = self.v
+                            .trail_lim
+                            .first()
+                            .cloned()
+                            .unwrap_or(self.v.trail.len() as i32);
+                        println!(
+                            "c | {:9} | {:7} {:8} {:8} | {:8} {:8} {:6.0} | {:6.3} % |",
+                            self.conflicts as i32,
+                            self.dec_vars as i32 - trail_lim_head,
+                            self.num_clauses(),
+                            self.v.clauses_literals as i32,
+                            self.max_learnts as i32,
+                            self.num_learnts(),
+                            self.v.learnts_literals as f64 / self.num_learnts() as f64,
+                            self.progress_estimate() * 100.0
+                        );
+                    }
+                }
+
+                // do not wait for the next decision, which may come after more conflicts
+                if self.conflict_budget >= 0 && self.conflicts >= self.conflict_budget as u64 {
+                    self.progress_estimate = self.progress_estimate();
+                    self.cancel_until(0);
+                    return lbool::UNDEF;
+                }
+                if self.conflicts >= pause_at {
+                    // the learnt literal is propagated when the search resumes
+                    return lbool::UNDEF;
+                }
+            } else {
+                // NO CONFLICT
+                if nof_conflicts >= 0 && *conflict_c >= nof_conflicts {
+                    self.last_restart_lbd = self.last_learnt_lbd;
+                    self.last_restart_trail = self.v.trail.len();
+                    if cfg!(feature = "trace") && self.trace >= 1 {
+                        eprintln!("c trace restart {} {}", self.conflicts, self.v.trail.len());
+                    }
+                }
+                if (nof_conflicts >= 0 && *conflict_c >= nof_conflicts) || !self.within_budget() {
+                    // Reached bound on number of conflicts:
+                    self.progress_estimate = self.progress_estimate();
+                    self.cancel_until(0);
+                    return lbool::UNDEF;
+                }
+
+                // Simplify the set of problem clauses:
+                if self.v.decision_level() == 0 && !self.simplify() {
+                    return lbool::FALSE;
+                }
+
+                if self.learnts.len() as f64 - self.v.num_assigns() as f64 >= self.max_learnts {
+                    // Reduce the set of learnt clauses:
+                    self.reduce_db();
+                }
+
+                let mut next = Lit::UNDEF;
+                while (self.v.decision_level() as usize) < self.assumptions.len() {
+                    // Perform user provided assumption:
+                    let p = self.assumptions[self.v.decision_level() as usize];
+                    if self.v.value_lit(p) == lbool::TRUE {
+                        // Dummy decision level, since `p` is true already:
+                        self.new_decision_level();
+                    } else if self.v.value_lit(p) == lbool::FALSE {
+                        // conflict at level 0 because of `p`, unsat
+                        let mut conflict = mem::replace(&mut self.conflict, LSet::new());
+                        self.analyze_final(!p, &mut conflict);
+                        self.conflict = conflict;
+                        return lbool::FALSE;
+                    } else {
+                        next = p;
+                        break;
+                    }
+                }
+                let assumed = next != Lit::UNDEF;
+
+                if next == Lit::UNDEF {
+                    // New variable decision:
+                    self.decisions += 1;
+                    next = self.pick_branch_lit();
+
+                    if next == Lit::UNDEF {
+                        // Model found:
+                        return lbool::TRUE;
+                    }
+                }
+
+                // Increase decision level and enqueue `next`
+                // with no justification since it's a decision
+                self.new_decision_level();
+                debug!("pick-next {:?}", next);
+                if cfg!(feature = "trace") && self.trace >= 3 {
+                    let kind = if assumed { "assume" } else { "decide" };
+                    eprintln!("c trace {} {} {}", kind, next, self.v.decision_level());
+                }
+                self.v.unchecked_enqueue(next, CRef::UNDEF);
+            }
+        }
+    }
+
+    /// Call the external propagator, and add the clause it returns (if any).
+    fn external_propagate(&mut self) -> ExternalClause {
+        let clause = match self.ext_propagator.0 {
+            Some(ref mut p) => p.propagate(&self.v.trail),
+            None => None,
+        };
+        match clause {
+            Some(c) => self.add_external_clause(c),
+            None => ExternalClause::None,
+        }
+    }
+
+    /// Add a clause during search. If it is unit or false under the current
+    /// assignment, backjump to the level where it becomes so, and propagate it
+    /// or return the conflict.
+    fn add_external_clause(&mut self, mut lits: Vec<Lit>) -> ExternalClause {
+        debug!("external clause {:?}", lits);
+        lits.sort();
+        lits.dedup();
+        if lits.windows(2).any(|w| w[0] == !w[1]) {
+            return ExternalClause::Added;
+        }
+        if self.produce_proof { self.proof.create_clause(&lits); }
+
+        // non-false literals first, then false literals by decreasing level
+        {
+            let v = &self.v;
+            lits.sort_by_key(|&lit| {
+                if v.value_lit(lit) == lbool::FALSE { (1, -v.level(lit.var())) } else { (0, 0) }
+            });
+        }
+        let n_free = lits.iter().take_while(|&&lit| self.v.value_lit(lit) != lbool::FALSE).count();
+
+        if lits.is_empty() {
+            return ExternalClause::Unsat;
+        } else if lits.len() == 1 {
+            self.cancel_until(0);
+            let value = self.v.value_lit(lits[0]);
+            if value == lbool::FALSE {
+                return ExternalClause::Unsat;
+            } else if value == lbool::UNDEF {
+                self.v.unchecked_enqueue(lits[0], CRef::UNDEF);
+            }
+            return ExternalClause::Added;
+        }
+
+        if n_free == 0 {
+            let level = self.v.level(lits[0].var()) as u32;
+            self.cancel_until(level);
+        } else if n_free == 1 && self.v.value_lit(lits[0]) == lbool::UNDEF {
+            let level = self.v.level(lits[1].var()) as u32;
+            self.cancel_until(level);
+        }
+        let cr = self.ca.alloc_with_learnt(&lits, true);
+        self.learnts.push(cr);
+        self.attach_clause(cr);
+        self.cla_bump_activity(cr);
+        if n_free == 0 {
+            ExternalClause::Conflict(cr)
+        } else {
+            if n_free == 1 && self.v.value_lit(lits[0]) == lbool::UNDEF {
+                self.v.unchecked_enqueue(lits[0], cr);
+            }
+            ExternalClause::Added
+        }
+    }
+
+    /// Number of conflicts before the restart number `curr_restarts`.
+    fn restart_limit(&mut self, curr_restarts: i32) -> i32 {
+        let rest_base = if self.luby_restart {
+            luby(self.restart_inc, curr_restarts)
+        } else {
+            f64::powi(self.restart_inc, curr_restarts)
+        };
+        let mut limit = rest_base * self.restart_first as f64;
+        if self.restart_jitter > 0.0 {
+            let jitter = self.restart_jitter * (2.0 * drand(&mut self.random_seed) - 1.0);
+            limit *= 1.0 + jitter;
+        }
+        // the base grows exponentially: saturate rather than overflow
+        if limit.is_nan() || limit < 1.0 {
+            1
+        } else if limit >= i32::MAX as f64 {
+            i32::MAX
+        } else {
+            limit as i32
+        }
+    }
+
+    /// Reset the learnt clause limits at the beginning of a `solve`.
+    fn init_learnt_limits(&mut self) {
+        self.max_learnts = self.num_clauses() as f64 * self.learntsize_factor;
+        if self.max_learnts < self.min_learnts_lim as f64 {
+            self.max_learnts = self.min_learnts_lim as f64;
+        }
+
+        self.learntsize_adjust_confl = self.learntsize_adjust_start_confl as f64;
+        self.learntsize_adjust_cnt = self.learntsize_adjust_confl as i32;
+    }
+
+    /// Main solve method (assumptions given in `self.assumptions`).
+    fn solve_internal(&mut self) -> lbool {
+        let status = self.solve_main();
+        self.last_result = status;
+        status
+    }
+
+    fn solve_main(&mut self) -> lbool {
+        assert!(self.v.decision_level()==0);
+        assert!(self.steps.is_none(), "a `solve_steps` run is in progress");
+        self.model.clear();
+        self.conflict.clear();
+        if !self.ok {
+            return lbool::FALSE;
+        }
+
+        self.solves += 1;
+
+        if !self.normalize_assumptions() {
+            return lbool::FALSE;
+        }
+
+        // Fast path: an assumption already falsified at level 0 is a core on its own
+        for i in 0..self.assumptions.len() {
+            let p = self.assumptions[i];
+            if self.v.value_lit(p) == lbool::FALSE {
+                debug_assert_eq!(self.v.level(p.var()), 0);
+                let mut conflict = mem::replace(&mut self.conflict, LSet::new());
+                self.analyze_final(!p, &mut conflict);
+                self.conflict = conflict;
+                return lbool::FALSE;
+            }
+        }
+
+        self.init_learnt_limits();
+        let mut status = lbool::UNDEF;
+
+        if self.verbosity >= 1 {
+            println!( "c ============================[ Search Statistics ]==============================");
+            println!( "c | Conflicts |          ORIGINAL         |          LEARNT          | Progress |");
+            println!( "c |           |    Vars  Clauses Literals |    Limit  Clauses Lit/Cl |          |");
+            println!( "c ===============================================================================");
+        }
+
+        // Search:
+        let mut curr_restarts: i32 = 0;
+        while status == lbool::UNDEF {
+            let nof_clauses = self.restart_limit(curr_restarts);
+            status = self.search(nof_clauses);
+            if !self.within_budget() {
+                break;
+            }
+            curr_restarts += 1;
+        }
+
+        if self.verbosity >= 1 {
+            println!(
+                "c ==============================================================================="
+            );
+        }
+
+        if status == lbool::TRUE {
+            self.copy_model();
+            if !self.out_of_scope.is_empty() && !self.verify_model() {
+                // the scope is assigned, but the assignment is not a model
+                self.model.clear();
+                status = lbool::UNDEF;
+            }
+        } else if status == lbool::FALSE && self.conflict.len() == 0 {
+            // NOTE: we may return `false` without an empty conflict in case we had assumptions. In
+            // this case `self.conflict` contains the unsat-core but adding new clauses might
+            // succeed in the absence of these assumptions.
+            self.ok = false;
+        }
+
+        self.cancel_until(0);
+        debug!("res: {:?}; proved at lvl 0: {:?}", status, self.v.trail.iter().collect::<Vec<_>>());
+        status
+    }
+
+    /// Remove duplicate assumptions, keeping the first occurrence of each.
+    ///
+    /// If a literal and its negation are both assumed, returns `false` after
+    /// setting `conflict` to these two assumptions (negated, as usual).
+    fn normalize_assumptions(&mut self) -> bool {
+        let mut sorted = self.assumptions.clone();
+        sorted.sort();
+        // `l` and `!l` are adjacent once sorted
+        if let Some(w) = sorted.windows(2).find(|w| w[0] == !w[1]) {
+            debug!("contradictory assumptions {:?}", w);
+            self.conflict.insert(!w[0]);
+            self.conflict.insert(!w[1]);
+            return false;
+        }
+        if sorted.windows(2).any(|w| w[0] == w[1]) {
+            let mut kept = HashSet::new();
+            self.assumptions.retain(|&lit| kept.insert(lit));
+        }
+        true
+    }
+
+    /// Extend & copy the current assignment into `model`.
+    fn copy_model(&mut self) {
+        let num_vars = self.num_vars();
+        self.model.resize(num_vars as usize, lbool::UNDEF);
+        for i in 0..num_vars {
+            self.model[i as usize] = self.v.value(Var::from_idx(i));
+        }
+        if !self.elim_clauses.is_empty() {
+            self.extend_model();
+        }
+    }
+
+    /// Remove half of the learnt clauses, minus the clauses locked by the current assignment. Locked
+    /// clauses are clauses that are reason to some assignment. Binary clauses are never removed.
+    fn reduce_db(&mut self) {
+        let extra_lim = self.cla_inc / self.learnts.len() as f64; // Remove any clause below this activity
+
+        info!("reduce_db.start");
+
+        {
+            let ca = &self.ca;
+            self.learnts.sort_unstable_by(|&x, &y| {
+                let x = ca.get_ref(x);
+                let y = ca.get_ref(y);
+                debug_assert!(x.learnt());
+                debug_assert!(y.learnt());
+                Ord::cmp(&(x.size() <= 2), &(y.size() <= 2)).then(
+                    PartialOrd::partial_cmp(&x.activity(), &y.activity()).expect("NaN activity"),
+                )
+            });
+        }
+        // Don't delete binary or locked clauses. From the rest, delete clauses from the first half
+        // and clauses with activity smaller than `extra_lim`:
+        let mut j = 0;
+        for i in 0..self.learnts.len() {
+            let cr = self.learnts[i];
+            let cond = {
+                let c = self.ca.get_ref(cr);
+                c.size() > 2 && !self.v.locked(&self.ca, c)
+                    && (i < self.learnts.len() / 2 || (c.activity() as f64) < extra_lim)
+                    && !self.recently_used(cr)
+            } && !self.keep_clause(cr);
+            if cond {
+                self.v.remove_clause(&mut self.ca, &mut self.watches_data, cr);
+                if self.produce_proof { self.proof.delete_clause(&self.ca.get_ref(cr)); }
+                if let Some(ref mut lrat) = self.lrat { lrat.delete(cr); }
+            } else {
+                self.learnts[j] = cr;
+                j += 1;
+            }
+        }
+
+        // self.learnts.resize_default(j);
+        let before = self.learnts.len();
+        self.learnts.resize(j, CRef::UNDEF);
+
+        debug!("reduce_db.done (deleted {})", before - j);
+        let n_clauses = self.clauses.len();
+        self.notify_reduce(n_clauses + before, n_clauses + j);
+
+        self.check_garbage();
+    }
+
+    /// Shrink the given set to contain only non-satisfied clauses.
+    /// Remove the clauses of `which` that are satisfied at level 0, and the literals
+    /// false at level 0 from the others. Returns the number of clauses and literals removed.
+    fn remove_satisfied(&mut self, which: ClauseSet) -> (u32, u32) {
+        assert_eq!(self.v.decision_level(), 0);
+        let mut n_clauses = 0;
+        let mut n_lits = 0;
+        let cs: &mut Vec<CRef> = match which {
+            ClauseSet::Learnt => &mut self.learnts,
+            ClauseSet::Original => &mut self.clauses,
+        };
+        let ca = &mut self.ca;
+        let watches_data = &mut self.watches_data;
+        let self_v = &mut self.v;
+        let occurs = &mut self.occurs;
+        let lrat = &mut self.lrat;
+        let learnt = which.is_learnt();
+        let use_occurs = self.use_simp && !learnt;
+        cs.retain(|&cr| {
+            let satisfied = self_v.satisfied(ca.get_ref(cr));
+            if satisfied {
+                n_clauses += 1;
+                if use_occurs {
+                    for &lit in ca.get_ref(cr).iter() {
+                        occurs.smudge(lit.var());
+                    }
+                }
+                self_v.remove_clause(ca, watches_data, cr);
+                if let Some(ref mut lrat) = *lrat { lrat.delete(cr); }
+                debug!("remove satisfied clause {}", ca.get_ref(cr).pp_dimacs());
+                // we should not need to tell the proof checker to remove the clause
+            } else {
+                let mut units = vec![];
+                let amount_shaved = {
+                    let mut c = ca.get_mut(cr);
+                    // Trim clause (but keep the 2 first lits as they are watching):
+                    debug_assert_eq!(self_v.value_lit(c[0]), lbool::UNDEF);
+                    debug_assert_eq!(self_v.value_lit(c[1]), lbool::UNDEF);
+                    let mut k = 2;
+                    let orig_size = c.size();
+                    let mut end = c.size();
+                    while k < end {
+                        if self_v.value_lit(c[k]) == lbool::FALSE {
+                            // this lit is false at level 0, remove it from `c`
+                            debug_assert!(self_v.level(c[k].var()) == 0);
+                            if let Some(ref lrat) = *lrat { units.push(lrat.unit_id(c[k].var())); }
+                            if use_occurs {
+                                occurs[c[k].var()].retain(|&mut x| x != cr);
+                            }
+                            end -= 1;
+                            c[k] = c[end];
+                        } else {
+                            k += 1;
+                        }
+                    }
+                    c.shrink(end);
+                    orig_size - end
+                };
+                if let Some(ref mut lrat) = *lrat {
+                    if amount_shaved > 0 { lrat.strengthen(cr, ca.get_ref(cr).lits(), units); }
+                }
+                // It was not in MiniSAT, but it is needed for correct wasted calculation.
+                ca.free_amount(amount_shaved);
+                if learnt {
+                    self_v.learnts_literals -= amount_shaved as u64;
+                } else {
+                    self_v.clauses_literals -= amount_shaved as u64;
+                }
+                n_lits += amount_shaved;
+            }
+            !satisfied
+        });
+        (n_clauses, n_lits)
+    }
+
+    fn rebuild_order_heap(&mut self) {
+        let mut vs = vec![];
+        for v in self.vars() {
+            if self.decision[v] && self.v.value(v) == lbool::UNDEF {
+                vs.push(v);
+            }
+        }
+        self.order_heap().build(&vs);
+    }
+
+    fn attach_clause(&mut self, cr: CRef) {
+        let (c0, c1, learnt, size) = {
+            let c = self.ca.get_ref(cr);
+            debug_assert!(c.size() > 1);
+            (c[0], c[1], c.learnt(), c.size())
+        };
+        self.watches()[!c0].push(Watcher::new(cr, c1));
+        self.watches()[!c1].push(Watcher::new(cr, c0));
+        if learnt {
+            self.v.num_learnts += 1;
+            self.v.learnts_literals += size as u64;
+        } else {
+            self.v.num_clauses += 1;
+            self.v.clauses_literals += size as u64;
+        }
+    }
+
+    /// Revert to the state at given level (keeping all assignment at 'level' but not beyond).
+    fn cancel_until(&mut self, level: u32) {
+        if self.v.decision_level() > level {
+            let trail_lim_last = *self.v.trail_lim.last().expect("trail_lim is empty") as usize;
+            let trail_lim_level = self.v.trail_lim[level as usize] as usize;
+            if self.assign_cb.0.is_some() {
+                self.notify_unassignments(trail_lim_level);
+            }
+            for c in (trail_lim_level..self.v.trail.len()).rev() {
+                let x = self.v.trail[c].var();
+                self.v.assigns[x] = lbool::UNDEF;
+                if self.phase_saving > 1 || (self.phase_saving == 1 && c > trail_lim_last) {
+                    self.polarity[x] = self.v.trail[c].sign();
+                }
+                self.insert_var_order(x);
+            }
+            self.qhead = trail_lim_level as i32;
+            self.v.trail.resize(trail_lim_level, Lit::UNDEF);
+            // eprintln!("decision_level {} -> {}", self.v.trail_lim.len(), level);
+            self.v.trail_lim.resize(level as usize, 0);
+            if level == 0 && !self.transient.is_empty() {
+                self.remove_transient();
+            }
+        }
+    }
+
+    /// Remove the learnt clauses longer than `max_learnt_len`.
+    fn remove_transient(&mut self) {
+        for i in 0..self.transient.len() {
+            let cr = self.transient[i];
+            if self.produce_proof { self.proof.delete_clause(&self.ca.get_ref(cr)); }
+            if let Some(ref mut lrat) = self.lrat { lrat.delete(cr); }
+            self.v.remove_clause(&mut self.ca, &mut self.watches_data, cr);
+        }
+        self.transient.clear();
+        self.check_garbage();
+    }
+
+    pub fn dimacs_model(& self) -> SolverPrintDimacs {
+        SolverPrintDimacs {s: self, model: true}
+    }
+
+    pub fn dimacs_proof(& self) -> SolverPrintDimacs {
+        SolverPrintDimacs {s: self, model: false}
+    }
+
+    /// Write, in Graphviz DOT format, the implication graph explaining why
+    /// `conflict` is falsified by the current assignment.
+    ///
+    /// `conflict` must be a clause whose literals are all false, e.g. a conflict
+    /// clause observed from a callback. Nodes are the assigned literals, labelled
+    /// with their level; there is an edge from each antecedent of a reason clause
+    /// to the literal it implies. Only literals of the conflict level are expanded:
+    /// decisions (boxes) and literals of lower levels (dashed) are leaves.
+    pub fn export_implication_graph_dot<W: io::Write>(
+        &self,
+        conflict: &[Lit],
+        w: &mut W,
+    ) -> io::Result<()> {
+        debug_assert!(conflict.iter().all(|&p| self.v.value_lit(p) == lbool::FALSE));
+        let confl_level = conflict.iter().map(|p| self.v.level(p.var())).max().unwrap_or(0);
+
+        writeln!(w, "digraph implication_graph {{")?;
+        writeln!(w, "  conflict [shape=octagon, color=red];")?;
+        let mut visited = vec![false; self.num_vars() as usize];
+        let mut stack = vec![];
+        for &p in conflict {
+            writeln!(w, "  v{} -> conflict;", p.var().idx())?;
+            if !mem::replace(&mut visited[p.var().idx() as usize], true) {
+                stack.push(p.var());
+            }
+        }
+        while let Some(x) = stack.pop() {
+            let lit = Lit::new(x, self.v.value(x) == lbool::TRUE);
+            let level = self.v.level(x);
+            let reason = self.v.reason(x);
+            let style = if reason == CRef::UNDEF && level > 0 {
+                "shape=box"
+            } else if level < confl_level {
+                "style=dashed"
+            } else {
+                "shape=ellipse"
+            };
+            writeln!(w, "  v{} [label=\"{:?} @ {}\", {}];", x.idx(), lit, level, style)?;
+            if reason == CRef::UNDEF || level < confl_level {
+                continue;
+            }
+            for &q in self.ca.get_ref(reason).iter().skip(1) {
+                writeln!(w, "  v{} -> v{};", q.var().idx(), x.idx())?;
+                if !mem::replace(&mut visited[q.var().idx() as usize], true) {
+                    stack.push(q.var());
+                }
+            }
+        }
+        writeln!(w, "}}")
+    }
+
+    /// Analyze conflict and produce a reason clause.
+    ///
+    /// # Pre-conditions:
+    ///
+    /// - `out_learnt` is assumed to be cleared.
+    /// - Current decision level must be greater than root level.
+    ///
+    /// # Post-conditions:
+    ///
+    /// - `btlevel` is returned.
+    /// - `out_learnt[0]` is the asserting literal at level `btlevel`.
+    /// - If out_learnt.size() > 1 then `out_learnt[1]` has the greatest decision level of the
+    ///   rest of literals. There may be others from the same level though.
+    fn analyze(&mut self, mut confl: CRef, out_learnt: &mut Vec<Lit>) -> i32 {
+        let mut path_c = 0;
+        let mut p = Lit::UNDEF;
+
+        debug!("analyze.start [{}]", self.ca.get_ref(confl).pp_dimacs());
+
+        // Generate conflict clause:
+        //
+        out_learnt.push(Lit::from_idx(0)); // (leave room for the asserting literal)
+        let mut index = self.v.trail.len();
+
+        loop {
+            debug_assert_ne!(confl, CRef::UNDEF); // (otherwise should be UIP)
+            if self.ca.get_ref(confl).learnt() {
+                self.cla_bump_activity(confl);
+            }
+
+            let c = self.ca.get_mut(confl);
+
+            debug!("analyze.resolve-with [{}]", c.pp_dimacs());
+
+            let mut iter = c.iter();
+            if p != Lit::UNDEF {
+                // skip first literal of `c`, because it should be the one propagated (`p`), on
+                // which we do resolution, so it can't appear in the learnt clause
+                let first = iter.next();
+                debug_assert_eq!(Some(&p), first);
+            }
+            for &q in iter {
+                if !self.seen[q.var()].is_seen() && self.v.level(q.var()) > 0 {
+                    self.v.var_bump_activity(&mut self.order_heap_data, q.var());
+                    self.seen[q.var()] = Seen::SOURCE;
+                    if self.v.level(q.var()) >= self.v.decision_level() as i32 {
+                        // at decision level: need to eliminate this lit by resolution
+                        path_c += 1;
+                    } else {
+                        out_learnt.push(q); // part of the learnt clause
+                    }
+                }
+            }
+
+            // Select next literal in the trail to look at:
+            while !self.seen[self.v.trail[index - 1].var()].is_seen() {
+                index -= 1;
+            }
+            p = self.v.trail[index - 1];
+            index -= 1;
+            confl = self.v.reason(p.var());
+            self.seen[p.var()] = Seen::UNDEF;
+            path_c -= 1;
+
+            if path_c <= 0 {
+                break;
+            }
+        }
+        out_learnt[0] = !p; // literal that will be propagated when the clause is learnt
+        debug_assert!(self.v.value_lit(!p) != lbool::TRUE);
+
+        // Simplify conflict clause:
+        self.analyze_toclear.clear();
+        self.analyze_toclear.extend_from_slice(&out_learnt);
+        let new_size = if self.ccmin_mode == 2 {
+            let mut j = 1;
+            for i in 1..out_learnt.len() {
+                let lit = out_learnt[i];
+                // can eliminate `lit` only if it's redundant *and* not a decision
+                if self.v.reason(lit.var()) == CRef::UNDEF || !self.lit_redundant(lit) {
+                    out_learnt[j] = lit;
+                    j += 1;
+                }
+            }
+            j
+        } else if self.ccmin_mode == 1 {
+            let mut j = 1;
+            for i in 1..out_learnt.len() {
+                let lit = out_learnt[i];
+                let x = lit.var();
+                let reason = self.v.reason(x);
+
+                let mut retain = false;
+                if reason == CRef::UNDEF {
+                    retain = true;
+                } else {
+                    let c = self.ca.get_ref(reason);
+                    for k in 1..c.size() {
+                        let v = c[k].var();
+                        if !self.seen[v].is_seen() && self.v.level(v) > 0 {
+                            retain = true;
+                            break;
+                        }
+                    }
+                }
+                if retain {
+                    out_learnt[j] = lit;
+                    j += 1;
+                }
+            }
+            j
+        } else {
+            out_learnt.len()
+        };
+
+        self.max_literals += out_learnt.len() as u64;
+        self.tot_literals += new_size as u64;
+        out_learnt.resize(new_size, Lit::UNDEF);
+
+        if self.bump_learnt_lits {
+            for &lit in out_learnt.iter() {
+                self.v.var_bump_activity(&mut self.order_heap_data, lit.var());
+            }
+        }
+
+        // Find correct backtrack level:
+        //
+        let mut btlevel = if new_size == 1 {
+            0
+        } else {
+            let mut max_i = 1;
+            let mut max_level = self.v.level(out_learnt[max_i].var());
+            // Find the first literal assigned at the next-highest level:
+            for i in 2..out_learnt.len() {
+                let level = self.v.level(out_learnt[i].var());
+                if level > max_level {
+                    max_i = i;
+                    max_level = level;
+                }
+            }
+            // Swap-in this literal at index 1:
+            let p = out_learnt[max_i];
+            out_learnt[max_i] = out_learnt[1];
+            out_learnt[1] = p;
+            self.v.level(p.var())
+        };
+
+        for &lit in &self.analyze_toclear {
+            self.seen[lit.var()] = Seen::UNDEF; // (`seen[]` is now cleared)
+        }
+
+        if self.uip_mode != UipMode::First && out_learnt.len() > 1 {
+            let other = self.other_uip_clause(out_learnt);
+            let lbd = self.compute_lbd(out_learnt);
+            let other_lbd = self.compute_lbd(&other);
+            if (other_lbd, other.len()) < (lbd, out_learnt.len()) {
+                debug!("analyze.other-uip {:?} (lbd {} -> {})", other, lbd, other_lbd);
+                *out_learnt = other;
+                btlevel = if out_learnt.len() == 1 {
+                    0
+                } else {
+                    self.v.level(out_learnt[1].var())
+                };
+            }
+        }
+
+        if self.learnt_histograms {
+            let lbd = self.compute_lbd(out_learnt) as usize;
+            incr_hist(&mut self.learnt_size_hist, out_learnt.len());
+            incr_hist(&mut self.learnt_lbd_hist, lbd);
+        }
+        debug_assert!(out_learnt.iter().all(|&l| self.v.value_lit(l) == lbool::FALSE));
+        btlevel
+    }
+
+    /// Continue resolution from the first-UIP clause `learnt` (as produced by `analyze`),
+    /// to obtain the last-UIP or all-UIP clause, depending on `uip_mode`.
+    ///
+    /// With `UipMode::Last`, the literal of the current level is resolved away until only
+    /// the decision of the level remains. With `UipMode::All`, every level is resolved
+    /// until a single literal (its UIP) remains.
+    ///
+    /// The result satisfies the same post-conditions as `analyze`'s `out_learnt`.
+    fn other_uip_clause(&mut self, learnt: &[Lit]) -> Vec<Lit> {
+        let all = self.uip_mode == UipMode::All;
+        let mut pending = vec![0u32; self.v.decision_level() as usize + 1];
+        let mut n_pending = 0;
+        let mut toclear = vec![];
+        for &q in learnt {
+            self.seen[q.var()] = Seen::SOURCE;
+            pending[self.v.level(q.var()) as usize] += 1;
+            n_pending += 1;
+            toclear.push(q.var());
+        }
+
+        // walk down the trail: literals are ordered by decreasing level
+        let mut out = vec![];
+        let mut index = self.v.trail.len();
+        while n_pending > 0 {
+            index -= 1;
+            let p = self.v.trail[index];
+            if !self.seen[p.var()].is_seen() {
+                continue;
+            }
+            let level = self.v.level(p.var());
+            let reason = self.v.reason(p.var());
+            n_pending -= 1;
+            pending[level as usize] -= 1;
+            let keep = if level == self.v.decision_level() as i32 {
+                reason == CRef::UNDEF || (all && pending[level as usize] == 0)
+            } else {
+                !all || pending[level as usize] == 0
+            };
+            if keep {
+                out.push(!p);
+                continue;
+            }
+            debug_assert_ne!(reason, CRef::UNDEF);
+            if self.ca.get_ref(reason).learnt() {
+                self.cla_bump_activity(reason);
+            }
+            let c = self.ca.get_ref(reason);
+            debug_assert_eq!(c[0], p);
+            for &q in c.iter().skip(1) {
+                let lvl = self.v.level(q.var());
+                if !self.seen[q.var()].is_seen() && lvl > 0 {
+                    self.seen[q.var()] = Seen::SOURCE;
+                    pending[lvl as usize] += 1;
+                    n_pending += 1;
+                    toclear.push(q.var());
+                }
+            }
+        }
+
+        for x in toclear {
+            self.seen[x] = Seen::UNDEF;
+        }
+        out
+    }
+
+    // COULD THIS BE IMPLEMENTED BY THE ORDINARY "analyze" BY SOME REASONABLE GENERALIZATION?
+    /// Specialized analysis procedure to express the final conflict in terms of assumptions.
+    /// Calculates the (possibly empty) set of assumptions that led to the assignment of `p`, and
+    /// stores the result in `out_conflict`.
+    fn analyze_final(&mut self, p: Lit, out_conflict: &mut LSet) {
+        out_conflict.clear();
+        out_conflict.insert(p);
+        debug!("analyze_final lit={:?}", p);
+
+        if self.v.decision_level() == 0 {
+            if self.produce_proof { self.proof.create_clause(out_conflict); }
+            return;
+        }
+
+        self.seen[p.var()] = Seen::SOURCE;
+
+        for &lit in self.v.trail[self.v.trail_lim[0] as usize..].iter().rev() {
+            let x = lit.var();
+            if self.seen[x].is_seen() {
+                let reason = self.v.reason(x);
+                if reason == CRef::UNDEF {
+                    debug_assert!(self.v.level(x) > 0);
+                    out_conflict.insert(!lit);
+                } else {
+                    let c = self.ca.get_mut(reason);
+                    for j in 1..c.size() {
+                        if self.v.level(c[j].var()) > 0 {
+                            self.seen[c[j].var()] = Seen::SOURCE;
+                        }
+                    }
+                }
+                self.seen[x] = Seen::UNDEF;
+            }
+        }
+
+        self.seen[p.var()] = Seen::UNDEF;
+        debug_assert!(self.seen.iter().all(|(_,&s)| s==Seen::UNDEF));
+        if self.produce_proof { self.proof.create_clause(out_conflict); }
+    }
+
+    // Check if `p` can be removed from a conflict clause. It can be removed
+    // if it is propagation-implied by literals of level 0 exclusively
+    // TODO: port abstract levels from minisat
+    fn lit_redundant(&mut self, mut p: Lit) -> bool {
+        debug_assert!(self.seen[p.var()] == Seen::UNDEF || self.seen[p.var()] == Seen::SOURCE);
+        debug_assert!(self.v.reason(p.var()) != CRef::UNDEF);
+
+        let mut cr = self.v.reason(p.var());
+        let stack = &mut self.analyze_stack;
+        stack.clear();
+
+        let mut i: u32 = 1;
+        loop {
+            let c = self.ca.get_mut(cr);
+            if i < c.size() {
+                // Checking `p`-parents `l`:
+                let l: Lit = c[i];
+
+                // Variable at level 0 or previously removable:
+                if self.v.level(l.var()) == 0 || self.seen[l.var()] == Seen::SOURCE
+                    || self.seen[l.var()] == Seen::REMOVABLE
+                {
+                    i += 1;
+                    continue;
+                }
+
+                // Check if variable can not be removed for some local reason
+                // (e.g. if it's a decision or has failed to be removed already)
+                if self.v.reason(l.var()) == CRef::UNDEF || self.seen[l.var()] == Seen::FAILED {
+                    stack.push(ShrinkStackElem::new(0, p));
+                    for elem in stack.iter() {
+                        // elements in the stack can't justify making a literal
+                        // redundant, so remember that
+                        if self.seen[elem.l.var()] == Seen::UNDEF {
+                            self.seen[elem.l.var()] = Seen::FAILED;
+                            self.analyze_toclear.push(elem.l);
+                        }
+                    }
+
+                    return false;
+                }
+
+                // Recursively check if `l` is redundant itself
+                stack.push(ShrinkStackElem::new(i, p));
+                i = 0;
+                p = l;
+                cr = self.v.reason(p.var());
+            } else {
+                // Finished with current element `p` and reason `c`:
+                if self.seen[p.var()] == Seen::UNDEF {
+                    self.seen[p.var()] = Seen::REMOVABLE;
+                    self.analyze_toclear.push(p);
+                }
+
+                // Terminate with success if stack is empty:
+                if stack.len() == 0 {
+                    break;
+                }
+
+                // Continue with top element on stack:
+                let last = stack.pop().expect("stack is empty");
+                i = last.i;
+                p = last.l;
+                cr = self.v.reason(p.var());
+            }
+            i += 1;
+        }
+
+        true
+    }
+
+    /// Propagates all enqueued facts. If a conflict arises, the conflicting clause is returned,
+    /// otherwise CRef_Undef.
+    ///
+    /// # Post-conditions:
+    ///
+    /// - the propagation queue is empty, even if there was a conflict.
+    fn propagate(&mut self) -> CRef {
+        let mut confl = CRef::UNDEF;
+        let mut num_props: u32 = 0;
+        let stamp = self.use_stamp();
+
+        while (self.qhead as usize) < self.v.trail.len() {
+            // 'p` is enqueued fact to propagate.
+            let p = self.v.trail[self.qhead as usize];
+
+            // eprintln!("propagating trail[{}] = {:?}", self.qhead, p);
+            self.qhead += 1;
+            let watches_data_ptr: *mut OccListsData<_, _> = &mut self.watches_data;
+            // let ws = self.watches().lookup_mut(p);
+            let ws = self.watches_data
+                .lookup_mut_pred(p, &WatcherDeleted { ca: &self.ca });
+            // eprintln!("watcher of {:?} = {:?}", p, ws);
+            let mut i: usize = 0;
+            let mut j: usize = 0;
+            let end: usize = ws.len();
+            num_props += 1;
+            'clauses: while i < end {
+                // Try to avoid inspecting the clause:
+                let blocker = ws[i].blocker;
+                if self.v.value_lit(blocker) == lbool::TRUE {
+                    ws[j] = ws[i];
+                    j += 1;
+                    i += 1;
+                    continue;
+                }
+
+                // Make sure the false literal is data[1]:
+                let cr = ws[i].cref;
+                let mut c = self.ca.get_mut(cr);
+                let false_lit = !p;
+                if c[0] == false_lit {
+                    c[0] = c[1];
+                    c[1] = false_lit;
+                }
+                debug_assert_eq!(c[1], false_lit);
+                i += 1;
+
+                // If 0th watch is true, then clause is already satisfied.
+                let first = c[0];
+                let w = Watcher::new(cr, first);
+                if first != blocker && self.v.value_lit(first) == lbool::TRUE {
+                    ws[j] = w;
+                    j += 1;
+                    continue;
+                }
+
+                // Look for new watch:
+                if let Some(k) = find_new_watch(&self.v.assigns, c.lits()) {
+                    let k = k as u32;
+                    c[1] = c[k];
+                    c[k] = false_lit;
+
+                    // self.watches()[!c[1]].push(w);
+                    // safe because `!c[1]!=p`, so watches are not aliased
+                    debug_assert_ne!(!c[1], p);
+                    unsafe { &mut (*watches_data_ptr)[!c[1]] }.push(w);
+                    continue 'clauses;
+                }
+
+                // Did not find watch -- clause is unit under assignment:
+                ws[j] = w;
+                j += 1;
+                if self.v.value_lit(first) == lbool::FALSE {
+                    // eprintln!("propagation: conflict at {:?}", first);
+                    confl = cr;
+                    self.qhead = self.v.trail.len() as i32;
+                    // Copy the remaining watches:
+                    while i < end {
+                        ws[j] = ws[i];
+                        j += 1;
+                        i += 1;
+                    }
+                } else {
+                    // eprintln!("propagation: got {:?}", first);
+                    if self.reduce_protect > 0 && c.learnt() {
+                        c.set_touched(stamp);
+                    }
+                    self.v.unchecked_enqueue(first, cr);
+                }
+            }
+            let dummy = Watcher::DUMMY;
+            ws.resize(j, dummy);
+        }
+        self.propagations += num_props as u64;
+        self.simp_db_props -= num_props as i64;
+
+        if self.assign_cb.0.is_some() {
+            self.notify_assignments();
+        }
+        if self.lrat.is_some() && self.v.decision_level() == 0 {
+            self.lrat_sync_units(confl);
+        }
+
+        confl
+    }
+
+    /// Whether `keep_cb` vetoes the deletion of `cr`.
+    fn keep_clause(&mut self, cr: CRef) -> bool {
+        match self.keep_cb.0 {
+            Some(ref mut cb) => cb(self.ca.get_ref(cr).lits()),
+            None => false,
+        }
+    }
+
+    /// Report a reduction of the clause database from `before` to `after` clauses to `reduce_cb`.
+    fn notify_reduce(&mut self, before: usize, after: usize) {
+        if let Some(ref mut cb) = self.reduce_cb.0 {
+            cb(before, after);
+        }
+    }
+
+    /// Report the literals of the trail that `assign_cb` has not seen yet.
+    fn notify_assignments(&mut self) {
+        if let Some(ref mut cb) = self.assign_cb.0 {
+            for &lit in &self.v.trail[self.assign_cb_head..] {
+                cb(lit, true);
+            }
+        }
+        self.assign_cb_head = self.v.trail.len();
+    }
+
+    /// Report to `assign_cb` that the literals of the trail starting at `start` are
+    /// about to be unassigned, in reverse order.
+    fn notify_unassignments(&mut self, start: usize) {
+        // literals that were never reported as assigned are not reported as unassigned either
+        let end = cmp::max(start, self.assign_cb_head);
+        if let Some(ref mut cb) = self.assign_cb.0 {
+            for &lit in self.v.trail[start..end].iter().rev() {
+                cb(lit, false);
+            }
+        }
+        self.assign_cb_head = cmp::min(start, self.assign_cb_head);
+    }
+
+    /// Check whether the space wasted by dead clauses in the clause allocator exceeds
+    /// the threshold
+    fn check_garbage(&mut self) {
+        if self.ca.wasted() as f64 > self.ca.len() as f64 * self.garbage_frac
+            || self.ca.bytes_wasted() > self.garbage_min_bytes
+        {
+            self.garbage_collect();
+        }
+    }
+
+    /// Garbage collect the clause allocator by moving alive clauses into
+    /// another allocator.
+    fn garbage_collect(&mut self) {
+        // Initialize the next region to a size corresponding to the estimated utilization degree. This
+        // is not precise but should avoid some unnecessary reallocations for the new region:
+        let mut to = ClauseAllocator::with_start_cap(self.ca.len() - self.ca.wasted());
+
+        let before = self.clauses.len() + self.learnts.len();
+        self.reloc_all(&mut to);
+        let after = self.clauses.len() + self.learnts.len();
+        self.notify_reduce(before, after);
+        if self.verbosity >= 2 {
+            println!(
+                "|  Garbage collection:   {:12} bytes => {:12} bytes             |",
+                self.ca.bytes_used(),
+                to.bytes_used()
+            );
+        }
+        self.ca = to;
+    }
+
+    fn progress_estimate(&self) -> f64 {
+        let mut progress = 0.0;
+        let f = 1.0 / self.num_vars() as f64;
+
+        for i in 0..self.v.decision_level() + 1 {
+            let beg: i32 = if i == 0 {
+                0
+            } else {
+                self.v.trail_lim[i as usize - 1]
+            };
+            let end: i32 = if i == self.v.decision_level() {
+                self.v.trail.len() as i32
+            } else {
+                self.v.trail_lim[i as usize]
+            };
+            progress += f64::powi(f, i as i32) * (end - beg) as f64;
+        }
+
+        progress / self.num_vars() as f64
+    }
+
+    /// Set a predicate that will be called regularly to check whether
+    /// to interrupt search
+    pub fn set_stop_pred<F: Fn() -> bool + 'static>(&mut self, f: F) {
+        self.stop_pred = StopPredicate::new(f)
+    }
+
+    /// Set the initial saved phase of the variables created afterwards: with `true`,
+    /// their first decision makes them true (the default is `false`).
+    ///
+    /// User polarities (see `new_var`) and the polarity callback take precedence,
+    /// and phase saving updates the phase as usual afterwards.
+    pub fn set_default_polarity(&mut self, p: bool) {
+        self.default_polarity = p;
+    }
+
+    /// Set a callback called with `(clauses_before, clauses_after)` after each
+    /// reduction of the clause database.
+    ///
+    /// The numbers are those of stored clauses, original and learnt. It is called
+    /// after the periodic reduction of the learnt clauses, after `shrink_clause_db_to`,
+    /// and after each garbage collection of the clause allocator. Garbage collection
+    /// only compacts memory, so it reports the same number twice.
+    pub fn set_reduce_callback<F: FnMut(usize, usize) + 'static>(&mut self, f: F) {
+        self.reduce_cb = Callback(Some(Box::new(f)));
+    }
+
+    /// Set a callback consulted by the periodic reduction of the clause database
+    /// and by `shrink_clause_db_to` with the literals of each learnt clause they
+    /// are about to delete; if it returns `true`, the clause is kept.
+    ///
+    /// Clauses that are kept anyway (reasons of the current assignment, recently
+    /// used ones, see `set_reduce_protect`, and binary clauses in the periodic
+    /// reduction) are not submitted.
+    pub fn set_keep_clause_callback<F: FnMut(&[Lit]) -> bool + 'static>(&mut self, f: F) {
+        self.keep_cb = Callback(Some(Box::new(f)));
+    }
+
+    /// Set a callback that chooses the polarity of each decision.
+    ///
+    /// It is called with the decision variable, unless the variable has a user
+    /// polarity (see `new_var`). Returning `lbool::UNDEF` falls back to the default
+    /// polarity selection (phase saving).
+    pub fn set_polarity_callback<F: FnMut(Var) -> lbool + 'static>(&mut self, f: F) {
+        self.polarity_cb = Callback(Some(Box::new(f)));
+    }
+
+    /// Make `v` the variable of the first decision of the next search, after
+    /// level-0 propagation and the assumptions, without assuming it.
+    ///
+    /// This only applies to one decision (the polarity is chosen as usual); it is
+    /// ignored if `v` is already assigned or is not a decision variable then.
+    /// Later decisions, and decisions after restarts, use the usual heuristic.
+    pub fn set_first_decision(&mut self, v: Var) {
+        assert!(v.idx() < self.num_vars(), "unknown variable {:?}", v);
+        self.first_decision = v;
+    }
+
+    /// Set a callback that can choose the next decision variable.
+    ///
+    /// It is called before each decision; returning `Some(v)` makes `v` the
+    /// next decision, returning `None` defers to the activity-based heuristic.
+    /// Variables that are already assigned or are not decision variables are ignored.
+    pub fn set_decision_callback<F: FnMut(&Solver) -> Option<Var> + 'static>(&mut self, f: F) {
+        self.decision_cb = Callback(Some(Box::new(f)));
+    }
+
+    /// Set a callback notified whenever a literal is assigned (with `true`) or
+    /// unassigned when backtracking (with `false`).
+    ///
+    /// Assignments are reported in trail order after each round of unit propagation;
+    /// unassignments are reported in reverse trail order. Literals that are already
+    /// assigned when the callback is set are reported at the next propagation.
+    pub fn set_assignment_callback<F: FnMut(Lit, bool) + 'static>(&mut self, f: F) {
+        self.assign_cb = Callback(Some(Box::new(f)));
+        self.assign_cb_head = 0;
+    }
+
+    /// Set an external propagator, called during search whenever unit
+    /// propagation reaches a fixpoint. See `Propagator`.
+    pub fn set_external_propagator<P: Propagator + 'static>(&mut self, p: P) {
+        assert!(self.lrat.is_none(), "LRAT proofs are not supported with an external propagator");
+        self.ext_propagator = Callback(Some(Box::new(p)));
+    }
+
+    /// Limit the next calls to `solve_limited` to `x` more conflicts, counted from now.
+    ///
+    /// The budget is shared by all the following calls, until it is changed or
+    /// `budget_off` is called. A negative `x` means no limit.
+    pub fn set_conflict_budget(&mut self, x: i64) {
+        self.conflict_budget = if x < 0 { -1 } else { self.conflicts as i64 + x };
+    }
+
+    /// Limit the next calls to `solve_limited` to `x` more propagations, counted from now
+    /// (see `set_conflict_budget`).
+    pub fn set_propagation_budget(&mut self, x: i64) {
+        self.propagation_budget = if x < 0 { -1 } else { self.propagations as i64 + x };
+    }
+
+    /// Remove the conflict and propagation budgets.
+    pub fn budget_off(&mut self) {
+        self.conflict_budget = -1;
+        self.propagation_budget = -1;
+    }
+
+    /// Number of conflicts left in the budget (`-1` if there is no conflict budget).
+    ///
+    /// After `solve_limited` returned `lbool::UNDEF`, this is `0` if the conflict
+    /// budget was exhausted.
+    pub fn remaining_conflict_budget(&self) -> i64 {
+        if self.conflict_budget < 0 {
+            -1
+        } else {
+            cmp::max(0, self.conflict_budget - self.conflicts as i64)
+        }
+    }
+
+    /// Number of propagations left in the budget (`-1` if there is no propagation budget).
+    pub fn remaining_propagation_budget(&self) -> i64 {
+        if self.propagation_budget < 0 {
+            -1
+        } else {
+            cmp::max(0, self.propagation_budget - self.propagations as i64)
+        }
+    }
+
+    /// Interrupt search asynchronously
+    pub fn interrupt_async(&self) {
+        self.asynch_interrupt.store(true, Ordering::Relaxed);
+    }
+
+    fn has_been_interrupted(&self) -> bool {
+        self.asynch_interrupt.load(Ordering::Relaxed)
+    }
+
+    fn within_budget(&self) -> bool {
+        ! self.has_been_interrupted()
+            && (self.conflict_budget < 0 || self.conflicts < self.conflict_budget as u64)
+            && (self.propagation_budget < 0 || self.propagations < self.propagation_budget as u64)
+            && (! self.stop_pred.stop())
+    }
+
+    /// Relocate the learnt and original clauses, and forget the removed ones.
+    fn reloc_clause_lists(&mut self, to: &mut ClauseAllocator) {
+        // All learnt:
+        {
+            let mut j = 0;
+            for i in 0..self.learnts.len() {
+                let mut cr = self.learnts[i];
+                if self.ca.get_ref(cr).mark() != 1 {
+                    self.ca.reloc(&mut cr, to);
+                    self.learnts[j] = cr;
+                    j += 1;
+                }
+            }
+            self.learnts.resize(j, CRef::UNDEF);
+        }
+
+        // Learnt clauses kept until the next restart:
+        {
+            let ca = &mut self.ca;
+            self.transient.retain(|&cr| ca.get_ref(cr).mark() != 1);
+            for cr in self.transient.iter_mut() {
+                ca.reloc(cr, to);
+            }
+        }
+
+        // All original:
+        {
+            let mut j = 0;
+            for i in 0..self.clauses.len() {
+                let mut cr = self.clauses[i];
+                if self.ca.get_ref(cr).mark() != 1 {
+                    self.ca.reloc(&mut cr, to);
+                    self.clauses[j] = cr;
+                    j += 1;
+                }
+            }
+            self.clauses.resize(j, CRef::UNDEF);
+        }
+    }
+
+    /// Move to the given clause allocator, where clause indices might differ
+    fn reloc_all(&mut self, to: &mut ClauseAllocator) {
+        macro_rules! is_removed {
+            ($ca:expr, $cr:expr) => {
+                $ca.get_ref($cr).mark() == 1
+            };
+        }
+        // Clauses are copied in the order they are first relocated
+        if self.gc_locality {
+            self.reloc_clause_lists(to);
+        }
+
+        // All watchers:
+        self.watches().clean_all();
+        for p in self.lits() {
+            for watch in &mut self.watches_data[p] {
+                self.ca.reloc(&mut watch.cref, to);
+            }
+        }
+
+        // All reasons:
+        for &lit in &self.v.trail {
+            let v = lit.var();
+
+            // Note: it is not safe to call `locked()` on a relocated clause. This is why we keep
+            // `dangling` reasons here. It is safe and does not hurt.
+            let reason = self.v.reason(v);
+            if reason != CRef::UNDEF {
+                let cond = {
+                    let c = self.ca.get_ref(reason);
+                    c.reloced() || self.v.locked(&self.ca, c)
+                };
+                if cond {
+                    debug_assert!(!is_removed!(self.ca, reason));
+                    self.ca.reloc(&mut self.v.vardata[v].reason, to);
+                }
+            }
+        }
+
+        if !self.gc_locality {
+            self.reloc_clause_lists(to);
+        }
+
+        // Clause handles:
+        for cr in self.clause_handles.iter_mut() {
+            if *cr != CRef::UNDEF {
+                if is_removed!(self.ca, *cr) {
+                    *cr = CRef::UNDEF;
+                } else {
+                    self.ca.reloc(cr, to);
+                }
+            }
+        }
+
+        // Occurrence lists:
+        if self.use_simp {
+            self.occurs.clean_all_pred(&ClauseDeleted { ca: &self.ca });
+            for v in self.vars() {
+                for cr in self.occurs[v].iter_mut() {
+                    self.ca.reloc(cr, to);
+                }
+            }
+        }
+
+        // Duplicate detection index:
+        {
+            let ca = &mut self.ca;
+            self.clause_hashes.retain(|_, crs| {
+                crs.retain(|&cr| !is_removed!(ca, cr));
+                for cr in crs.iter_mut() {
+                    ca.reloc(cr, to);
+                }
+                !crs.is_empty()
+            });
+        }
+
+        // LRAT clause IDs:
+        if let Some(ref mut lrat) = self.lrat {
+            lrat.reloc(&mut self.ca, to);
+        }
+    }
+
+    fn order_heap(&mut self) -> Heap<Var, VarOrder> {
+        self.order_heap_data.promote(self.v.var_order())
+    }
+    fn watches(&mut self) -> OccLists<Lit, Watcher, WatcherDeleted> {
+        self.watches_data.promote(WatcherDeleted { ca: &self.ca })
+    }
+}
+
+impl SolverV {
+    #[inline(always)]
+    pub fn num_assigns(&self) -> u32 {
+        self.trail.len() as u32
+    }
+
+    #[inline(always)]
+    pub fn value(&self, x: Var) -> lbool {
+        self.assigns[x]
+    }
+
+    #[inline(always)]
+    pub fn value_lit(&self, x: Lit) -> lbool {
+        self.assigns[x.var()] ^ !x.sign()
+    }
+
+    fn var_order(&self) -> VarOrder<'_> {
+        VarOrder {
+            activity: &self.activity,
+            custom: self.order_cmp.0.as_deref(),
+        }
+    }
+
+    /// Increase a variable with the current 'bump' value.
+    fn var_bump_activity(&mut self, order_heap_data: &mut HeapData<Var>, v: Var) {
+        let inc = self.var_inc;
+        self.var_bump_activity_by(order_heap_data, v, inc)
+    }
+
+    /// Increase a variable's activity by `inc`.
+    fn var_bump_activity_by(&mut self, order_heap_data: &mut HeapData<Var>, v: Var, inc: f64) {
+        self.activity[v] += inc;
+        if self.activity[v] > 1e100 {
+            // Rescale:
+            for (_, x) in self.activity.iter_mut() {
+                *x *= 1e-100;
+            }
+            self.var_inc *= 1e-100;
+        }
+
+        // Update order_heap with respect to new activity:
+        let mut order_heap = order_heap_data.promote(self.var_order());
+        if order_heap.in_heap(v) {
+            order_heap.decrease(v);
+        }
+    }
+
+    /// Detach a clause from watcher lists.
+    ///
+    /// param `strict` means we remove the clause from watchers eagerly, instead
+    /// of just marking the watchlist as "dirty"
+    fn detach_clause(
+        &mut self,
+        ca: &mut ClauseAllocator,
+        watches_data: &mut OccListsData<Lit, Watcher>,
+        cr: CRef,
+        strict: bool,
+    ) {
+        let (c0, c1, csize, clearnt) = {
+            let c = ca.get_ref(cr);
+            (c[0], c[1], c.size(), c.learnt())
+        };
+        debug_assert!(csize > 1);
+
+        let mut watches = watches_data.promote(WatcherDeleted { ca });
+
+        // Strict or lazy detaching:
+        if strict {
+            // watches[!c0].remove_item(&Watcher::new(cr, c1));
+            // watches[!c1].remove_item(&Watcher::new(cr, c0));
+            let pos = watches[!c0]
+                .iter()
+                .position(|x| x == &Watcher::new(cr, c1))
+                .expect("Watcher not found");
+            watches[!c0].remove(pos);
+            let pos = watches[!c1]
+                .iter()
+                .position(|x| x == &Watcher::new(cr, c0))
+                .expect("Watcher not found");
+            watches[!c1].remove(pos);
+        } else {
+            watches.smudge(!c0);
+            watches.smudge(!c1);
+        }
+
+        if clearnt {
+            self.num_learnts -= 1;
+            self.learnts_literals -= csize as u64;
+        } else {
+            self.num_clauses -= 1;
+            self.clauses_literals -= csize as u64;
+        }
+    }
+
+    /// Detach and free a clause.
+    fn remove_clause(
+        &mut self,
+        ca: &mut ClauseAllocator,
+        watches_data: &mut OccListsData<Lit, Watcher>,
+        cr: CRef,
+    ) {
+        self.detach_clause(ca, watches_data, cr, false);
+        {
+            let c = ca.get_ref(cr);
+            // Don't leave pointers to free'd memory!
+            if self.locked(ca, c) {
+                self.vardata[c[0].var()].reason = CRef::UNDEF;
+            }
+        }
+        if ca.get_ref(cr).mark() == GLUE_MARK {
+            self.glue_clauses -= 1;
+        }
+        ca.get_mut(cr).set_mark(1); // used in reloc
+        ca.free(cr);
+    }
+
+    pub fn satisfied(&self, c: ClauseRef) -> bool {
+        c.iter().any(|&lit| self.value_lit(lit) == lbool::TRUE)
+    }
+
+    #[inline(always)]
+    pub fn decision_level(&self) -> u32 {
+        self.trail_lim.len() as u32
+    }
+
+    #[inline(always)]
+    fn reason(&self, x: Var) -> CRef {
+        self.vardata[x].reason
+    }
+
+    #[inline(always)]
+    fn level(&self, x: Var) -> i32 {
+        self.vardata[x].level
+    }
+
+    fn unchecked_enqueue(&mut self, p: Lit, from: CRef) {
+        debug_assert_eq!(self.value_lit(p), lbool::UNDEF);
+        self.assigns[p.var()] = lbool::new(p.sign());
+        self.vardata[p.var()] = VarData::new(from, self.decision_level() as i32);
+        self.trail.push(p);
+        if self.decision_level() == 0 {
+            self.fixed_pending.push(p.var());
+        }
+        if self.var_stats {
+            self.var_assigns[p.var()] += 1;
+            self.var_levels[p.var()] += self.decision_level() as u64;
+        }
+    }
+
+    /// Returns TRUE if a clause is a reason for some implication in the current state.
+    fn locked(&self, ca: &ClauseAllocator, c: ClauseRef) -> bool {
+        let reason = self.reason(c[0].var());
+        self.value_lit(c[0]) == lbool::TRUE && reason != CRef::UNDEF && ca.get_ref(reason) == c
+    }
+    // inline bool     Solver::locked          (const Clause& c) const { return value(c[0]) == l_True && reason(var(c[0])) != CRef_Undef && ca.lea(reason(var(c[0]))) == &c; }
+}
+
+/// Position of a search interrupted by `Solver::solve_steps`.
+#[derive(Debug, Clone)]
+struct Steps {
+    /// Number of restarts so far.
+    curr_restarts: i32,
+    /// Number of conflicts before the next restart.
+    nof_conflicts: i32,
+    /// Number of conflicts since the last restart.
+    conflict_c: i32,
+}
+
+/// What happened to a clause returned by the external propagator.
+#[derive(Debug)]
+enum ExternalClause {
+    /// No clause was returned.
+    None,
+    /// The clause was added, possibly propagating a literal.
+    Added,
+    /// The clause is false under the current assignment.
+    Conflict(CRef),
+    /// The clause is false at level 0.
+    Unsat,
+}
+
+#[derive(Debug)]
+enum ClauseSet {
+    Original,
+    Learnt,
+}
+
+impl ClauseSet {
+    fn is_learnt(&self) -> bool {
+        match *self {
+            ClauseSet::Learnt => true,
+            ClauseSet::Original => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct VarData {
+    reason: CRef,
+    level: i32,
+}
+
+impl Default for VarData {
+    fn default() -> Self {
+        Self {
+            reason: CRef::UNDEF,
+            level: 0,
+        }
+    }
+}
+
+impl VarData {
+    fn new(reason: CRef, level: i32) -> Self {
+        Self { reason, level }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Watcher {
+    cref: CRef,
+    blocker: Lit,
+}
+
+impl Watcher {
+    const DUMMY : Watcher = Watcher {
+        cref: CRef::UNDEF,
+        blocker: Lit::UNDEF,
+    };
+    fn new(cref: CRef, blocker: Lit) -> Self {
+        Self { cref, blocker }
+    }
+}
+
+impl PartialEq for Watcher {
+    fn eq(&self, rhs: &Self) -> bool {
+        self.cref == rhs.cref
+    }
+}
+impl Eq for Watcher {}
+
+struct VarOrder<'a> {
+    activity: &'a VMap<f64>,
+    custom: Option<&'a OrderCmpFn>,
+}
+
+impl<'a> PartialComparator<Var> for VarOrder<'a> {
+    fn partial_cmp(&self, lhs: &Var, rhs: &Var) -> Option<cmp::Ordering> {
+        Some(self.cmp(lhs, rhs))
+    }
+}
+impl<'a> Comparator<Var> for VarOrder<'a> {
+    fn cmp(&self, lhs: &Var, rhs: &Var) -> cmp::Ordering {
+        let ord = match self.custom {
+            Some(f) => f(self.activity, *lhs, *rhs),
+            None => PartialOrd::partial_cmp(&self.activity[*rhs], &self.activity[*lhs])
+                .expect("NaN activity"),
+        };
+        // break ties by index, so that the order does not depend on the heap's history
+        ord.then(lhs.idx().cmp(&rhs.idx()))
+    }
+}
+
+/// Predicate to test whether a clause has been removed from some lit's watchlist
+struct WatcherDeleted<'a> {
+    ca: &'a ClauseAllocator,
+}
+
+impl<'a> DeletePred<Watcher> for WatcherDeleted<'a> {
+    fn deleted(&self, w: &Watcher) -> bool {
+        self.ca.get_ref(w.cref).mark() == 1
+    }
+}
+
+struct ClauseDeleted<'a> {
+    ca: &'a ClauseAllocator,
+}
+
+impl<'a> DeletePred<CRef> for ClauseDeleted<'a> {
+    fn deleted(&self, cr: &CRef) -> bool {
+        self.ca.get_ref(*cr).mark() == 1
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Elements of the stack used for conflict analysis
+struct ShrinkStackElem {
+    i: u32,
+    l: Lit,
+}
+
+impl ShrinkStackElem {
+    fn new(i: u32, l: Lit) -> Self {
+        Self { i, l }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Seen {
+    UNDEF,
+    SOURCE,
+    REMOVABLE,
+    FAILED,
+}
+
+impl Default for Seen {
+    fn default() -> Self {
+        Seen::UNDEF
+    }
+}
+
+impl Seen {
+    fn is_seen(&self) -> bool {
+        *self != Seen::UNDEF
+    }
+}
+
+/// Handle on an original clause, obtained from `Solver::add_clause_with_handle`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ClauseHandle(u32);
+
+/// Outcome of adding a clause with `Solver::add_clause_detailed`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddResult {
+    /// The clause was stored (or enqueued, if it is unit)
+    Added,
+    /// The clause is a tautology or is satisfied at level 0; nothing was stored
+    Trivial,
+    /// An identical original clause is already present (requires duplicate detection)
+    AlreadyPresent,
+    /// The solver is in an `UNSAT` state
+    Unsat,
+}
+
+/// Result of `Solver::try_solve`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SatResult {
+    /// A model was found (see `get_model`)
+    Sat,
+    /// The problem is unsatisfiable
+    Unsat,
+}
+
+/// Error of the `Result`-based API (`Solver::try_add_clause`, `Solver::try_solve`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SolverError {
+    /// The solver is in a state that does not allow the operation (already
+    /// unsatisfiable, or in the middle of a `solve_steps` search)
+    Inconsistent,
+    /// The search was interrupted, or ran out of budget
+    Interrupted,
+}
+
+impl fmt::Display for SolverError {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        out.write_str(match *self {
+            SolverError::Inconsistent => "solver in inconsistent state",
+            SolverError::Interrupted => "interrupted",
+        })
+    }
+}
+
+impl error::Error for SolverError {}
+
+/// What `Solver::simplify_detailed` did
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SimplifyReport {
+    /// Number of clauses removed because they were satisfied at level 0
+    pub clauses_removed: u32,
+    /// Number of literals false at level 0 removed from the remaining clauses
+    pub literals_trimmed: u32,
+    /// Number of variables fixed at level 0 by propagation
+    pub vars_fixed: u32,
+    /// `false` if the solver is in an `UNSAT` state
+    pub ok: bool,
+}
+
+/// Result of `Solver::solve_steps`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SolveStatus {
+    /// The conflict limit was reached (or the solver was interrupted) before an answer was found
+    InProgress,
+    /// The problem is satisfiable, the model is available
+    Sat,
+    /// The problem is unsatisfiable
+    Unsat,
+}
+
+/// Order of the literals of original clauses, see `Solver::set_watch_selection`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchSelect {
+    /// Sort the literals, and watch the two smallest ones (the default)
+    Sorted,
+    /// Keep the literals in the order they were given, except that the two best
+    /// literals for watching are moved to the front (stable for clauses added at
+    /// level 0: the first two literals are watched)
+    KeepOrder,
+}
+
+/// UIP clauses considered by conflict analysis, see `Solver::set_uip_mode`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UipMode {
+    /// Learn the first-UIP clause (the default)
+    First,
+    /// Also compute the last-UIP clause, where the only literal of the conflict
+    /// level is its decision, and learn it if it has a lower LBD (or is shorter)
+    Last,
+    /// Also compute the all-UIP clause, with a single literal per decision level,
+    /// and learn it if it has a lower LBD (or is shorter)
+    All,
+}
+
+/// Order of the literals of learnt clauses, see `Solver::set_learnt_sort`.
+///
+/// The asserting literal is always first, and a literal of the highest remaining
+/// decision level second: these two are watched. The order of the others
+/// decides which literal becomes watched when one of them is falsified, and
+/// thus how often the clause is visited by propagation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LearntSort {
+    /// Keep the order produced by conflict analysis (the default)
+    None,
+    /// Sort by decreasing decision level: literals unassigned first on
+    /// backjumping become the new watches
+    ByLevel,
+    /// Sort the literals after the second one by decreasing variable activity
+    ByActivity,
+}
+
+/// Snapshot of the statistics of a solver, see `Solver::stats`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Statistics {
+    pub solves: u64,
+    pub starts: u64,
+    pub decisions: u64,
+    pub rnd_decisions: u64,
+    pub propagations: u64,
+    pub conflicts: u64,
+    /// Number of decision variables
+    pub dec_vars: u64,
+    pub num_clauses: u64,
+    pub num_learnts: u64,
+    pub clauses_literals: u64,
+    pub learnts_literals: u64,
+    /// Number of learnt clauses whose LBD was at most 2 when they were learnt
+    pub glue_clauses: u64,
+    /// Number of literals in learnt clauses, before minimization
+    pub max_literals: u64,
+    /// Number of literals in learnt clauses, after minimization
+    pub tot_literals: u64,
+}
+
+impl Statistics {
+    /// Fraction of the literals of learnt clauses that were removed by
+    /// conflict clause minimization (`0.0` if nothing was learnt yet)
+    pub fn minimization_ratio(&self) -> f64 {
+        if self.max_literals == 0 {
+            0.0
+        } else {
+            (self.max_literals - self.tot_literals) as f64 / self.max_literals as f64
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SolverOpts {
+    pub var_decay: f64,
+    pub clause_decay: f64,
+    pub random_var_freq: f64,
+    pub random_seed: f64,
+    pub ccmin_mode: i32,
+    pub phase_saving: i32,
+    pub rnd_init_act: bool,
+    pub luby_restart: bool,
+    pub restart_first: i32,
+    pub restart_inc: f64,
+    pub garbage_frac: f64,
+    pub min_learnts_lim: i32,
+    pub produce_proof: bool,
+}
+
+impl Default for SolverOpts {
+    fn default() -> SolverOpts {
+        Self {
+            var_decay: 0.95,
+            clause_decay: 0.999,
+            random_var_freq: 0.0,
+            random_seed: 91648253.0,
+            ccmin_mode: 2,
+            phase_saving: 2,
+            rnd_init_act: false,
+            luby_restart: true,
+            restart_first: 100,
+            restart_inc: 2.0,
+            garbage_frac: 0.20,
+            min_learnts_lim: 0,
+            produce_proof: false,
+        }
+    }
+}
+
+impl SolverOpts {
+    pub fn check(&self) -> bool {
+        self.invalid_option().is_none()
+    }
+
+    /// Name of the first option that is out of range, if any
+    pub fn invalid_option(&self) -> Option<&'static str> {
+        if !(0.0 < self.var_decay && self.var_decay < 1.0) {
+            Some("var_decay")
+        } else if !(0.0 < self.clause_decay && self.clause_decay < 1.0) {
+            Some("clause_decay")
+        } else if !(0.0 <= self.random_var_freq && self.random_var_freq <= 1.0) {
+            Some("random_var_freq")
+        } else if !(0.0 < self.random_seed && self.random_seed < f64::INFINITY) {
+            Some("random_seed")
+        } else if !(0 <= self.ccmin_mode && self.ccmin_mode <= 2) {
+            Some("ccmin_mode")
+        } else if !(0 <= self.phase_saving && self.phase_saving <= 2) {
+            Some("phase_saving")
+        } else if self.restart_first < 1 {
+            Some("restart_first")
+        } else if !(1.0 < self.restart_inc && self.restart_inc < f64::INFINITY) {
+            Some("restart_inc")
+        } else if !(0.0 < self.garbage_frac && self.garbage_frac < f64::INFINITY) {
+            Some("garbage_frac")
+        } else if self.min_learnts_lim < 0 {
+            Some("min_learnts_lim")
+        } else {
+            None
+        }
+    }
+}
+
+/// Fluent construction of a `Solver`.
+///
+/// ```
+/// use batsat::SolverBuilder;
+/// let solver = SolverBuilder::new()
+///     .var_decay(0.9)
+///     .luby_restart(false)
+///     .restart_first(50)
+///     .build()
+///     .unwrap();
+/// # drop(solver);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SolverBuilder {
+    opts: SolverOpts,
+    verbosity: i32,
+    num_vars: u32,
+    num_clauses: usize,
+}
+
+impl SolverBuilder {
+    /// A builder with the default options.
+    pub fn new() -> Self { Self::default() }
+
+    /// Start from the given options.
+    pub fn from_opts(opts: SolverOpts) -> Self {
+        SolverBuilder { opts, ..Self::default() }
+    }
+
+    /// Verbosity level (0=silent, 1=some, 2=more).
+    pub fn verbosity(mut self, verbosity: i32) -> Self { self.verbosity = verbosity; self }
+
+    /// Space to reserve for variables and clauses (see `Solver::reserve`).
+    pub fn capacity(mut self, num_vars: u32, num_clauses: usize) -> Self {
+        self.num_vars = num_vars;
+        self.num_clauses = num_clauses;
+        self
+    }
+
+    /// Variable activity decay factor, in `(0,1)`.
+    pub fn var_decay(mut self, d: f64) -> Self { self.opts.var_decay = d; self }
+
+    /// Learnt clause activity decay factor, in `(0,1)`.
+    pub fn clause_decay(mut self, d: f64) -> Self { self.opts.clause_decay = d; self }
+
+    /// Frequency of random decisions, in `[0,1]`.
+    pub fn random_var_freq(mut self, f: f64) -> Self { self.opts.random_var_freq = f; self }
+
+    /// Seed of the random number generator, positive.
+    pub fn random_seed(mut self, seed: f64) -> Self { self.opts.random_seed = seed; self }
+
+    /// Randomize the initial activities of variables.
+    pub fn rnd_init_act(mut self, b: bool) -> Self { self.opts.rnd_init_act = b; self }
+
+    /// Conflict clause minimization (0=none, 1=basic, 2=deep).
+    pub fn ccmin_mode(mut self, mode: i32) -> Self { self.opts.ccmin_mode = mode; self }
+
+    /// Phase saving (0=none, 1=limited, 2=full).
+    pub fn phase_saving(mut self, mode: i32) -> Self { self.opts.phase_saving = mode; self }
+
+    /// Use the Luby restart sequence, rather than a geometric one.
+    pub fn luby_restart(mut self, b: bool) -> Self { self.opts.luby_restart = b; self }
+
+    /// Base restart interval, in conflicts, at least 1.
+    pub fn restart_first(mut self, n: i32) -> Self { self.opts.restart_first = n; self }
+
+    /// Restart interval increase factor, greater than 1.
+    pub fn restart_inc(mut self, f: f64) -> Self { self.opts.restart_inc = f; self }
+
+    /// Fraction of wasted memory allowed before a garbage collection, positive.
+    pub fn garbage_frac(mut self, f: f64) -> Self { self.opts.garbage_frac = f; self }
+
+    /// Minimum learnt clause limit, non-negative.
+    pub fn min_learnts_lim(mut self, n: i32) -> Self { self.opts.min_learnts_lim = n; self }
+
+    /// Produce a DRAT proof.
+    pub fn produce_proof(mut self, b: bool) -> Self { self.opts.produce_proof = b; self }
+
+    /// Build the solver, or return an error naming the first parameter that is out of range.
+    pub fn build(self) -> Result<Solver, String> {
+        if let Some(name) = self.opts.invalid_option() {
+            return Err(format!("invalid value for solver option `{}`", name));
+        }
+        if !(0 <= self.verbosity && self.verbosity <= 2) {
+            return Err("invalid value for solver option `verbosity`".to_string());
+        }
+        let mut s = Solver::new(self.opts);
+        s.set_verbosity(self.verbosity);
+        s.reserve(self.num_vars, self.num_clauses);
+        Ok(s)
+    }
+}
+
+/// Finite subsequences of the Luby-sequence:
+///
+/// > 0: 1
+/// > 1: 1 1 2
+/// > 2: 1 1 2 1 1 2 4
+/// > 3: 1 1 2 1 1 2 4 1 1 2 1 1 2 4 8
+/// ...
+fn luby(y: f64, mut x: i32) -> f64 {
+    // Find the finite subsequence that contains index 'x', and the
+    // size of that subsequence:
+    let mut size = 1;
+    let mut seq = 0;
+    while size < x + 1 {
+        seq += 1;
+        size = 2 * size + 1;
+    }
+
+    while size - 1 != x {
+        size = (size - 1) >> 1;
+        seq -= 1;
+        x = x % size;
+    }
 
-                // Look for new watch:
-                for k in 2..c.size() {
-                    if self.v.value_lit(c[k]) != lbool::FALSE {
-                        c[1] = c[k];
-                        c[k] = false_lit;
-
-                        // self.watches()[!c[1]].push(w);
-                        // safe because `!c[1]!=p`, so watches are not aliased
-                        debug_assert_ne!(!c[1], p);
-                        unsafe { &mut (*watches_data_ptr)[!c[1]] }.push(w);
-                        continue 'clauses;
-                    }
-                }
+    return f64::powi(y, seq);
+}
 
-                // Did not find watch -- clause is unit under assignment:
-                ws[j] = w;
-                j += 1;
-                if self.v.value_lit(first) == lbool::FALSE {
-                    // eprintln!("propagation: conflict at {:?}", first);
-                    confl = cr;
-                    self.qhead = self.v.trail.len() as i32;
-                    // Copy the remaining watches:
-                    while i < end {
-                        ws[j] = ws[i];
-                        j += 1;
-                        i += 1;
-                    }
-                } else {
-                    // eprintln!("propagation: got {:?}", first);
-                    self.v.unchecked_enqueue(first, cr);
-                }
+/// `part` as a percentage of `total`, or `0` if `total` is `0`
+fn percent(part: u64, total: u64) -> f64 {
+    if total == 0 { 0.0 } else { part as f64 * 100.0 / total as f64 }
+}
+
+/// Increment the `i`-th bucket of `hist`, growing it if needed
+fn incr_hist(hist: &mut Vec<u64>, i: usize) {
+    if i >= hist.len() {
+        hist.resize(i + 1, 0);
+    }
+    hist[i] += 1;
+}
+
+/// Reorder the sorted, duplicate-free `clause` like the literals of `user_order`,
+/// from which it was simplified.
+fn restore_order(clause: &mut Vec<Lit>, user_order: &[Lit]) {
+    let mut done = vec![false; clause.len()];
+    let sorted = mem::replace(clause, Vec::with_capacity(done.len()));
+    for lit in user_order {
+        if let Ok(i) = sorted.binary_search(lit) {
+            if !done[i] {
+                done[i] = true;
+                clause.push(*lit);
             }
-            let dummy = Watcher::DUMMY;
-            ws.resize(j, dummy);
         }
-        self.propagations += num_props as u64;
-        self.simp_db_props -= num_props as i64;
+    }
+}
 
-        confl
+/// Index of the first literal of `c`, after the two watched ones, that is not false
+#[cfg(not(feature="parallel"))]
+#[inline(always)]
+fn find_new_watch(assigns: &VMap<lbool>, c: &[Lit]) -> Option<usize> {
+    (2..c.len()).find(|&k| assigns[c[k].var()] ^ !c[k].sign() != lbool::FALSE)
+}
+
+/// Minimum size of the clauses whose new watch is searched in parallel
+#[cfg(feature="parallel")]
+const PAR_WATCH_MIN_LEN: usize = 2048;
+
+/// Index of the first literal of `c`, after the two watched ones, that is not false.
+///
+/// Long clauses are scanned in parallel; the result is the same as the serial scan.
+#[cfg(feature="parallel")]
+#[inline]
+fn find_new_watch(assigns: &VMap<lbool>, c: &[Lit]) -> Option<usize> {
+    if c.len() < PAR_WATCH_MIN_LEN {
+        (2..c.len()).find(|&k| assigns[c[k].var()] ^ !c[k].sign() != lbool::FALSE)
+    } else {
+        c[2..].par_iter()
+            .position_first(|&lit| assigns[lit.var()] ^ !lit.sign() != lbool::FALSE)
+            .map(|k| k + 2)
     }
+}
 
-    /// Check whether the space wasted by dead clauses in the clause allocator exceeds
-    /// the threshold
-    fn check_garbage(&mut self) {
-        if self.ca.wasted() as f64 > self.ca.len() as f64 * self.garbage_frac {
-            self.garbage_collect();
+/// Mark of the learnt clauses that had an LBD of at most 2 when learnt
+/// (deleted clauses have mark `1`).
+const GLUE_MARK: u32 = 2;
+
+/// Does `lits` contain a literal and its negation?
+#[cfg_attr(not(debug_assertions), allow(dead_code))]
+fn is_tautology(lits: &[Lit]) -> bool {
+    let mut sorted = lits.to_vec();
+    sorted.sort_unstable();
+    sorted.windows(2).any(|w| w[0] == !w[1])
+}
+
+/// Hash of a sorted clause, used for duplicate detection
+fn hash_lits(lits: &[Lit]) -> u64 {
+    let mut h = DefaultHasher::new();
+    lits.hash(&mut h);
+    h.finish()
+}
+
+/// Generate a random double:
+fn drand(seed: &mut f64) -> f64 {
+    *seed *= 1389796.0;
+    let q = (*seed / 2147483647.0) as i32;
+    *seed -= q as f64 * 2147483647.0;
+    return *seed / 2147483647.0;
+}
+
+/// Generate a random integer:
+fn irand(seed: &mut f64, size: i32) -> i32 {
+    (drand(seed) * size as f64) as i32
+}
+
+#[cfg(test)]
+mod test {
+    use super::Solver;
+    use {Lit, Var};
+
+    /// Add a random 3-SAT instance with `n_clauses` clauses over `n_vars` new
+    /// variables to `s`, generated from `seed`, and return the variables.
+    fn random_3sat(s: &mut Solver, mut seed: u64, n_vars: u32, n_clauses: usize) -> Vec<Var> {
+        use interface::SolverInterface;
+        let vars: Vec<Var> = (0..n_vars).map(|_| s.new_var_default()).collect();
+        for _ in 0..n_clauses {
+            let mut c: Vec<Lit> = (0..3).map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                Lit::new(vars[(seed % n_vars as u64) as usize], seed & 1024 == 0)
+            }).collect();
+            s.add_clause_reuse(&mut c);
         }
+        vars
     }
 
-    /// Garbage collect the clause allocator by moving alive clauses into
-    /// another allocator.
-    fn garbage_collect(&mut self) {
-        // Initialize the next region to a size corresponding to the estimated utilization degree. This
-        // is not precise but should avoid some unnecessary reallocations for the new region:
-        let mut to = ClauseAllocator::with_start_cap(self.ca.len() - self.ca.wasted());
+    /// Add the pigeonhole principle for `n + 1` pigeons in `n` holes to `s`,
+    /// over new variables, and return them: `p[i][j]` is pigeon `i` in hole `j`.
+    fn pigeonhole(s: &mut Solver, n: usize) -> Vec<Vec<Lit>> {
+        pigeonhole_guarded(s, n, &[])
+    }
 
-        self.reloc_all(&mut to);
-        if self.verbosity >= 2 {
-            println!(
-                "|  Garbage collection:   {:12} bytes => {:12} bytes             |",
-                self.ca.len() * ClauseAllocator::UNIT_SIZE,
-                to.len() * ClauseAllocator::UNIT_SIZE
-            );
+    /// Same as `pigeonhole`, but each pigeon may also be nowhere if one of `guard` holds.
+    fn pigeonhole_guarded(s: &mut Solver, n: usize, guard: &[Lit]) -> Vec<Vec<Lit>> {
+        use interface::SolverInterface;
+        let p: Vec<Vec<Lit>> = (0..n + 1)
+            .map(|_| (0..n).map(|_| Lit::new(s.new_var_default(), true)).collect())
+            .collect();
+        for i in 0..n + 1 {
+            let mut c = p[i].clone();
+            c.extend_from_slice(guard);
+            s.add_clause_reuse(&mut c);
+            for k in 0..i {
+                for (&x, &y) in p[i].iter().zip(&p[k]) {
+                    s.add_clause_reuse(&mut vec![!x, !y]);
+                }
+            }
         }
-        self.ca = to;
+        p
     }
 
-    fn progress_estimate(&self) -> f64 {
-        let mut progress = 0.0;
-        let f = 1.0 / self.num_vars() as f64;
+    /// clauses are relocated by garbage collection, and still propagate afterwards
+    #[test]
+    fn test_gc_reloc() {
+        use super::{Solver, CRef};
+        use interface::SolverInterface;
+        use {lbool, Lit};
+        let mut s = Solver::default();
+        s.set_garbage_frac(1e9); // only collect explicitly
+        let vars: Vec<_> = (0..10).map(|_| s.new_var_default()).collect();
+        let x = |i: usize| Lit::new(vars[i], true);
+        let mut handles = vec![];
+        for i in 0..9 {
+            s.add_clause_reuse(&mut vec![!x(i), x(i + 1)]);
+            handles.push(s.add_clause_with_handle(&mut vec![x(i), x(i + 1)]).unwrap());
+        }
+        for h in handles {
+            assert!(s.remove_clause_by_handle(h));
+        }
+        let lits_of = |s: &Solver| -> Vec<Vec<Lit>> {
+            s.clauses.iter().map(|&cr| s.ca.get_ref(cr).iter().cloned().collect()).collect()
+        };
+        let before = lits_of(&s);
+        assert!(s.ca.wasted() > 0);
+        s.garbage_collect();
+        assert_eq!(s.ca.wasted(), 0);
+        assert_eq!(before, lits_of(&s));
+
+        s.add_clause_reuse(&mut vec![x(0)]);
+        assert_eq!(s.propagate(), CRef::UNDEF);
+        for i in 0..10 {
+            assert_eq!(s.v.value_lit(x(i)), lbool::TRUE);
+        }
+    }
 
-        for i in 0..self.v.decision_level() + 1 {
-            let beg: i32 = if i == 0 {
-                0
-            } else {
-                self.v.trail_lim[i as usize - 1]
-            };
-            let end: i32 = if i == self.v.decision_level() {
-                self.v.trail.len() as i32
-            } else {
-                self.v.trail_lim[i as usize]
-            };
-            progress += f64::powi(f, i as i32) * (end - beg) as f64;
+    /// restart limits stay positive when the restart base overflows
+    #[test]
+    fn test_restart_limit_overflow() {
+        use super::Solver;
+        use interface::SolverInterface;
+        use lbool;
+        for &luby in &[false, true] {
+            let mut s = Solver { luby_restart: luby, ..Solver::default() };
+            s.set_restart_first(1000);
+            s.set_restart_inc(1e300);
+            for i in 0..200 {
+                let limit = s.restart_limit(i);
+                assert!(limit >= 1, "restart {}: limit {}", i, limit);
+            }
+
+            // pigeonhole principle, 6 pigeons in 5 holes: needs restarts
+            s.set_restart_first(1);
+            pigeonhole(&mut s, 5);
+            assert_eq!(s.solve_limited(&[]), lbool::FALSE);
+            assert!(s.starts >= 2, "only {} restarts", s.starts);
         }
+    }
 
-        progress / self.num_vars() as f64
+    /// the literals of a clause can be read from its handle, also after garbage collection
+    #[test]
+    fn test_clause_lits() {
+        use super::{Solver, Lit};
+        use interface::SolverInterface;
+        let mut s = Solver::default();
+        let x: Vec<Lit> = (0..4).map(|_| Lit::new(s.new_var_default(), true)).collect();
+        let h1 = s.add_clause_with_handle(&mut vec![x[0], !x[1]]).unwrap();
+        let h2 = s.add_clause_with_handle(&mut vec![x[1], x[2], !x[3]]).unwrap();
+        assert_eq!(s.clause_lits(h2), Some(&[x[1], x[2], !x[3]][..]));
+        assert!(s.remove_clause_by_handle(h1));
+        assert_eq!(s.clause_lits(h1), None);
+        s.garbage_collect();
+        assert_eq!(s.clause_lits(h2), Some(&[x[1], x[2], !x[3]][..]));
     }
 
-    /// Set a predicate that will be called regularly to check whether
-    /// to interrupt search
-    pub fn set_stop_pred<F: Fn() -> bool + 'static>(&mut self, f: F) {
-        self.stop_pred = StopPredicate::new(f)
+    /// redundant clauses added by the user are deletable learnt clauses
+    #[test]
+    fn test_add_learnt_clause() {
+        use super::Solver;
+        use interface::SolverInterface;
+        use {lbool, Lit};
+        let mut s = Solver::default();
+        let vars: Vec<_> = (0..3).map(|_| s.new_var_default()).collect();
+        let x = |i: usize| Lit::new(vars[i], true);
+        s.add_clause_reuse(&mut vec![x(0), x(1)]);
+        s.add_clause_reuse(&mut vec![!x(1), x(2)]);
+        assert!(s.add_learnt_clause(&[x(0), x(2)]));
+        assert!(s.add_learnt_clause(&[x(0), !x(0)])); // tautology: ignored
+        assert_eq!(s.num_clauses(), 2);
+        assert_eq!(s.num_learnts(), 1);
+        s.shrink_clause_db_to(0);
+        assert_eq!(s.num_learnts(), 0);
+        assert_eq!(s.num_clauses(), 2);
+
+        assert!(s.add_learnt_clause(&[x(2)]));
+        assert_eq!(s.value_lvl_0(x(2)), lbool::TRUE);
+        assert_eq!(s.solve_limited(&[]), lbool::TRUE);
     }
 
-    /// Interrupt search asynchronously
-    pub fn interrupt_async(&self) {
-        self.asynch_interrupt.store(true, Ordering::Relaxed);
+    /// resetting the statistics keeps the formula
+    #[test]
+    fn test_reset_stats() {
+        use super::Solver;
+        use interface::SolverInterface;
+        use lbool;
+        let mut s = Solver::default();
+        let p = pigeonhole(&mut s, 3);
+        let before = s.stats();
+        assert_eq!(s.solve_limited(&[p[0][0]]), lbool::FALSE);
+        assert!(s.stats().conflicts > 0);
+        s.reset_stats();
+        let stats = s.stats();
+        assert_eq!((stats.solves, stats.conflicts, stats.propagations), (0, 0, 0));
+        assert_eq!(stats.minimization_ratio(), 0.0);
+        assert_eq!(stats.num_clauses, before.num_clauses);
+        assert_eq!(s.solve_limited(&[]), lbool::FALSE);
+        assert_eq!(s.stats().solves, 1);
     }
 
-    fn has_been_interrupted(&self) -> bool {
-        self.asynch_interrupt.load(Ordering::Relaxed)
+    /// a conflict budget stops the search, and can be renewed
+    #[test]
+    fn test_conflict_budget() {
+        use super::Solver;
+        use interface::SolverInterface;
+        use lbool;
+        let mut s = Solver::default();
+        pigeonhole(&mut s, 6);
+        assert_eq!(s.remaining_conflict_budget(), -1);
+        s.set_conflict_budget(10);
+        assert_eq!(s.remaining_conflict_budget(), 10);
+        assert_eq!(s.solve_limited(&[]), lbool::UNDEF);
+        assert_eq!(s.remaining_conflict_budget(), 0);
+        assert_eq!(s.stats().conflicts, 10);
+        s.budget_off();
+        assert_eq!(s.solve_limited(&[]), lbool::FALSE);
     }
 
-    fn within_budget(&self) -> bool {
-        ! self.has_been_interrupted()
-            && (self.conflict_budget < 0 || self.conflicts < self.conflict_budget as u64)
-            && (self.propagation_budget < 0 || self.propagations < self.propagation_budget as u64)
-            && (! self.stop_pred.stop())
+    /// duplicate assumptions are removed, and complementary ones are a core
+    #[test]
+    fn test_degenerate_assumptions() {
+        use super::Solver;
+        use interface::SolverInterface;
+        use {lbool, Lit};
+        let mut s = Solver::default();
+        let vars: Vec<_> = (0..3).map(|_| s.new_var_default()).collect();
+        let x = |i: usize| Lit::new(vars[i], true);
+        s.add_clause_reuse(&mut vec![!x(0), x(1)]);
+        s.add_clause_reuse(&mut vec![!x(1), !x(2)]);
+
+        // duplicates
+        assert_eq!(s.solve_limited(&[x(0), x(0), x(1), x(0)]), lbool::TRUE);
+        assert_eq!(s.assumptions(), &[x(0), x(1)]);
+        assert_eq!(s.value_lit(x(1)), lbool::TRUE);
+
+        // implied by a previous assumption
+        assert_eq!(s.solve_limited(&[x(0), x(1), !x(2)]), lbool::TRUE);
+
+        // complementary
+        assert_eq!(s.solve_limited(&[x(2), x(0), !x(2)]), lbool::FALSE);
+        let mut core = s.unsat_core().to_vec();
+        core.sort();
+        assert_eq!(core, vec![x(2), !x(2)]);
+        assert!(s.verify_core());
+        assert!(s.is_ok());
+
+        // duplicates in a core
+        assert_eq!(s.solve_limited(&[x(2), x(0), x(2)]), lbool::FALSE);
+        let mut core = s.unsat_core().to_vec();
+        core.sort();
+        assert_eq!(core, vec![!x(0), !x(2)]);
+        assert_eq!(s.solve_limited(&[]), lbool::TRUE);
     }
 
-    /// Move to the given clause allocator, where clause indices might differ
-    fn reloc_all(&mut self, to: &mut ClauseAllocator) {
-        macro_rules! is_removed {
-            ($ca:expr, $cr:expr) => {
-                $ca.get_ref($cr).mark() == 1
-            };
-        }
-        // All watchers:
-        self.watches().clean_all();
-        for v in (0..self.num_vars()).map(Var::from_idx) {
-            for s in 0..2 {
-                let p = Lit::new(v, s != 0);
-                for watch in &mut self.watches_data[p] {
-                    self.ca.reloc(&mut watch.cref, to);
-                }
-            }
+    /// tautological learnt clauses are dropped
+    #[test]
+    fn test_tautological_learnt() {
+        use super::{Solver, is_tautology};
+        use interface::SolverInterface;
+        use {lbool, Lit};
+        let mut s = Solver::default();
+        let vars: Vec<_> = (0..3).map(|_| s.new_var_default()).collect();
+        let x = |i: usize| Lit::new(vars[i], true);
+        s.add_clause_reuse(&mut vec![x(0), x(1)]);
+        assert!(is_tautology(&[x(1), x(2), !x(1)]));
+        assert!(!is_tautology(&[x(1), x(2), x(1)]));
+
+        assert!(s.add_learnt_clause(&[x(1), x(2), !x(1)]));
+        assert!(s.add_learnt_clause(&[!x(2), x(2)]));
+        assert_eq!(s.num_learnts(), 0);
+        assert_eq!(s.value_lvl_0(x(2)), lbool::UNDEF);
+        assert_eq!(s.solve_limited(&[!x(2)]), lbool::TRUE);
+        assert_eq!(s.solve_limited(&[x(2)]), lbool::TRUE);
+
+        // clauses learnt by the search are checked by `debug_assert` in debug builds
+        let mut s = Solver::default();
+        random_3sat(&mut s, 1, 100, 426);
+        assert_eq!(s.solve_limited(&[]), lbool::TRUE);
+        assert!(s.num_learnts() > 0);
+        for &cr in &s.learnts {
+            let c: Vec<Lit> = s.ca.get_ref(cr).iter().cloned().collect();
+            assert!(!is_tautology(&c), "{:?}", c);
         }
+    }
 
-        // All reasons:
-        for &lit in &self.v.trail {
-            let v = lit.var();
+    /// the glue clause counter follows learning and deletion of clauses
+    #[test]
+    fn test_glue_clauses() {
+        use super::{Solver, GLUE_MARK};
+        use interface::SolverInterface;
+        use lbool;
+        let mut s = Solver::default();
+        random_3sat(&mut s, 1, 100, 426);
+        let count_glue = |s: &Solver| {
+            s.learnts.iter().filter(|&&cr| s.ca.get_ref(cr).mark() == GLUE_MARK).count() as u64
+        };
+        assert_eq!(s.solve_limited(&[]), lbool::TRUE);
+        assert!(s.glue_clauses() > 0);
+        assert_eq!(s.glue_clauses(), count_glue(&s));
+        assert_eq!(s.stats().glue_clauses, s.glue_clauses());
+        s.shrink_clause_db_to(0);
+        assert_eq!(s.glue_clauses(), 0);
+        assert_eq!(s.glue_clauses(), count_glue(&s));
+    }
 
-            // Note: it is not safe to call `locked()` on a relocated clause. This is why we keep
-            // `dangling` reasons here. It is safe and does not hurt.
-            let reason = self.v.reason(v);
-            if reason != CRef::UNDEF {
-                let cond = {
-                    let c = self.ca.get_ref(reason);
-                    c.reloced() || self.v.locked(&self.ca, c)
-                };
-                if cond {
-                    debug_assert!(!is_removed!(self.ca, reason));
-                    self.ca.reloc(&mut self.v.vardata[v].reason, to);
-                }
-            }
-        }
+    /// glue clauses are deleted after the others by `shrink_clause_db_to`
+    #[test]
+    fn test_shrink_keeps_glue() {
+        use super::{Solver, GLUE_MARK};
+        use interface::SolverInterface;
+        use lbool;
+        let mut s = Solver::default();
+        random_3sat(&mut s, 1, 100, 426);
+        assert_eq!(s.solve_limited(&[]), lbool::TRUE);
+        let glue = s.glue_clauses();
+        let binary = s.learnts.iter()
+            .filter(|&&cr| {
+                let c = s.ca.get_ref(cr);
+                c.size() <= 2 && c.mark() != GLUE_MARK
+            })
+            .count();
+        assert!(s.num_learnts() as usize > glue as usize + binary);
+        s.shrink_clause_db_to(glue as usize + binary);
+        assert_eq!(s.glue_clauses(), glue);
+    }
 
-        // All learnt:
-        {
-            let mut j = 0;
-            for i in 0..self.learnts.len() {
-                let mut cr = self.learnts[i];
-                if !is_removed!(self.ca, cr) {
-                    self.ca.reloc(&mut cr, to);
-                    self.learnts[j] = cr;
-                    j += 1;
+    #[test]
+    fn test_lrat_proof() {
+        use super::Solver;
+        use interface::SolverInterface;
+        use lbool;
+        let mut s = Solver::default();
+        s.set_lrat_proof(true);
+        let p = pigeonhole(&mut s, 3);
+        assert_eq!(s.solve_limited(&[]), lbool::FALSE);
+
+        // the clauses by ID, with literals in the proof encoding
+        let enc = |lit: Lit| 2 * (lit.var().idx() as u64 + 1) + if lit.sign() { 0 } else { 1 };
+        let mut db: Vec<Vec<u64>> = vec![vec![]];
+        for i in 0..4 {
+            db.push(p[i].iter().map(|&lit| enc(lit)).collect());
+            for k in 0..i {
+                for (&x, &y) in p[i].iter().zip(&p[k]) {
+                    db.push(vec![enc(!x), enc(!y)]);
                 }
             }
-            self.learnts.resize(j, CRef::UNDEF);
         }
-
-        // All original:
-        {
-            let mut j = 0;
-            for i in 0..self.clauses.len() {
-                let mut cr = self.clauses[i];
-                if !is_removed!(self.ca, cr) {
-                    self.ca.reloc(&mut cr, to);
-                    self.clauses[j] = cr;
-                    j += 1;
+        let n_clauses = db.len() as u64 - 1;
+
+        let proof = s.lrat_proof();
+        let read = |it: &mut ::std::slice::Iter<u8>| {
+            let mut x = 0;
+            let mut shift = 0;
+            loop {
+                let b = *it.next().unwrap();
+                x |= ((b & 0x7f) as u64) << shift;
+                shift += 7;
+                if b & 0x80 == 0 { return x; }
+            }
+        };
+        let mut it = proof.iter();
+        let mut last_added = vec![];
+        let mut next_id = n_clauses + 1;
+        while let Some(&kind) = it.next() {
+            if kind == b'd' {
+                while read(&mut it) != 0 {}
+                continue;
+            }
+            assert_eq!(kind, b'a');
+            // derived clauses are numbered after the original ones, in order
+            assert_eq!(read(&mut it), 2 * next_id);
+            next_id += 1;
+            last_added.clear();
+            loop {
+                let lit = read(&mut it);
+                if lit == 0 { break; }
+                last_added.push(lit);
+            }
+            // each hint is unit under the negation of the clause, and the last one
+            // is falsified
+            let mut falsified = last_added.clone();
+            let mut conflict = false;
+            loop {
+                let h = read(&mut it);
+                if h == 0 { break; }
+                assert!(h / 2 < next_id);
+                assert!(!conflict, "hints after the conflict");
+                let rest: Vec<u64> =
+                    db[(h / 2) as usize].iter().cloned().filter(|l| !falsified.contains(l)).collect();
+                match rest.len() {
+                    0 => conflict = true,
+                    1 => {
+                        assert!(!falsified.contains(&(rest[0] ^ 1)), "satisfied hint");
+                        falsified.push(rest[0] ^ 1);
+                    }
+                    _ => panic!("hint {} is not unit", h / 2),
                 }
             }
-            self.clauses.resize(j, CRef::UNDEF);
+            assert!(conflict, "the hints do not imply {:?}", last_added);
+            db.push(last_added.clone());
         }
+        // the proof ends with the empty clause
+        assert!(next_id > n_clauses + 1);
+        assert!(last_added.is_empty());
     }
 
-    fn order_heap(&mut self) -> Heap<Var, VarOrder> {
-        self.order_heap_data.promote(VarOrder {
-            activity: &self.v.activity,
-        })
-    }
-    fn watches(&mut self) -> OccLists<Lit, Watcher, WatcherDeleted> {
-        self.watches_data.promote(WatcherDeleted { ca: &self.ca })
+    #[test]
+    fn test_learnts_retained() {
+        use super::Solver;
+        use interface::SolverInterface;
+        use {lbool, Lit};
+        let mut s = Solver::default();
+        let vars = random_3sat(&mut s, 1, 100, 426);
+        assert_eq!(s.solve_limited(&[]), lbool::TRUE);
+        let learnts = s.num_learnts();
+        assert!(learnts > 0);
+
+        // clauses satisfied by the model do not invalidate the learnt clauses
+        let lits: Vec<Lit> = vars.iter().map(|&v| Lit::new(v, s.value_var(v) == lbool::TRUE)).collect();
+        s.add_clause_reuse(&mut vec![lits[0], !lits[1]]);
+        s.add_clause_reuse(&mut vec![lits[2]]);
+        assert_eq!(s.num_learnts(), learnts);
+        assert_eq!(s.solve_limited(&[]), lbool::TRUE);
+        assert!(s.num_learnts() > 0);
+        assert_eq!(s.value_lit(lits[2]), lbool::TRUE);
+        assert!(s.verify_model());
     }
-}
 
-impl SolverV {
-    #[inline(always)]
-    pub fn num_assigns(&self) -> u32 {
-        self.trail.len() as u32
+    #[test]
+    fn test_assume_and_backtrack() {
+        use super::Solver;
+        use interface::SolverInterface;
+        use {lbool, Lit, Var};
+        let x = |i: u32| Lit::new(Var::from_idx(i), true);
+        let mut s = Solver::from_clauses(4, &[&[!x(0), x(1)], &[!x(0), x(2)], &[!x(1), !x(2)]]);
+        assert_eq!(s.assume_and_propagate(x(3)), None);
+        assert_eq!(s.decision_level(), 1);
+        assert_eq!(s.value_lvl_0(x(3)), lbool::UNDEF);
+        let confl = s.assume_and_propagate(x(0)).expect("conflict");
+        assert_eq!(s.decision_level(), 2);
+        assert_eq!(confl.len(), 2);
+        assert!(confl.iter().all(|&p| s.trail_with_levels().any(|(q, lvl)| q == !p && lvl == 2)));
+
+        s.backtrack_to(0);
+        assert_eq!(s.decision_level(), 0);
+        assert_eq!(s.trail_with_levels().count(), 0);
+        assert_eq!(s.solve_limited(&[x(0)]), lbool::FALSE);
+        assert_eq!(s.solve_limited(&[]), lbool::TRUE);
     }
 
-    #[inline(always)]
-    pub fn value(&self, x: Var) -> lbool {
-        self.assigns[x]
+    #[test]
+    fn test_sat_unsat_predicates() {
+        use super::Solver;
+        use interface::SolverInterface;
+        use {lbool, Lit, Var};
+        let x = |i: u32| Lit::new(Var::from_idx(i), true);
+        let mut s = Solver::from_clauses(2, &[&[x(0), x(1)], &[!x(0), x(1)]]);
+        assert!(!s.is_sat() && !s.is_unsat());
+        assert_eq!(s.solve_limited(&[]), lbool::TRUE);
+        assert!(s.is_sat() && !s.is_unsat());
+        assert_eq!(s.solve_limited(&[!x(1)]), lbool::FALSE);
+        assert!(!s.is_sat() && s.is_unsat());
+        assert!(s.is_ok());
+
+        // pigeon hole 5 -> 4 needs conflicts
+        let mut s = Solver::default();
+        pigeonhole(&mut s, 4);
+        s.set_conflict_budget(1);
+        assert_eq!(s.solve_limited(&[]), lbool::UNDEF);
+        assert!(!s.is_sat() && !s.is_unsat());
+        s.budget_off();
+        assert_eq!(s.solve_limited(&[]), lbool::FALSE);
+        assert!(!s.is_sat() && s.is_unsat());
+        assert!(!s.is_ok());
     }
 
-    #[inline(always)]
-    pub fn value_lit(&self, x: Lit) -> lbool {
-        self.assigns[x.var()] ^ !x.sign()
+    #[test]
+    fn test_order_comparator() {
+        use super::Solver;
+        use interface::SolverInterface;
+        use {lbool, Lit, Var};
+        let x = |i: u32| Lit::new(Var::from_idx(i), true);
+        // the first decision (negative by default) is on the most active variable
+        let mut s = Solver::from_clauses(5, &[&[x(0), x(4)]]);
+        s.bump_vars(&[Var::from_idx(0)], 1.0);
+        assert_eq!(s.solve_limited(&[]), lbool::TRUE);
+        assert_eq!(s.value_lit(x(4)), lbool::TRUE);
+
+        // static priority on the variable index, ignoring activities
+        let mut s = Solver::from_clauses(5, &[&[x(0), x(4)]]);
+        s.bump_vars(&[Var::from_idx(0)], 1.0);
+        s.set_order_comparator(|_, a, b| b.idx().cmp(&a.idx()));
+        assert_eq!(s.solve_limited(&[]), lbool::TRUE);
+        assert_eq!(s.value_lit(x(0)), lbool::TRUE);
+        s.reset_order_comparator();
+        assert_eq!(s.solve_limited(&[]), lbool::TRUE);
     }
 
-    /// Increase a variable with the current 'bump' value.
-    fn var_bump_activity(&mut self, order_heap_data: &mut HeapData<Var>, v: Var) {
-        self.activity[v] += self.var_inc;
-        if self.activity[v] > 1e100 {
-            // Rescale:
-            for (_, x) in self.activity.iter_mut() {
-                *x *= 1e-100;
-            }
-            self.var_inc *= 1e-100;
-        }
+    /// UNSAT under assumptions, even after search, does not make the solver inconsistent
+    #[test]
+    fn test_unsat_under_assumptions_recovers() {
+        use super::Solver;
+        use interface::SolverInterface;
+        use {lbool, Lit};
+        // pigeon hole 4 -> 3, guarded by the selector `sel`
+        let mut s = Solver::default();
+        let sel = Lit::new(s.new_var_default(), true);
+        let p = pigeonhole_guarded(&mut s, 3, &[!sel]);
+        let x = |i: usize| p[i / 3][i % 3];
+        assert_eq!(s.solve_limited(&[sel]), lbool::FALSE);
+        assert_eq!(s.unsat_core(), &[!sel]);
+        assert!(s.is_ok());
+        // the conflict is now found at level 0 (`!sel` was learnt)
+        assert_eq!(s.value_lvl_0(sel), lbool::FALSE);
+        assert_eq!(s.solve_limited(&[x(0), sel]), lbool::FALSE);
+        assert!(s.is_ok());
+
+        s.clear_assumptions();
+        assert_eq!(s.solve_limited(&[]), lbool::TRUE);
+        assert!(s.add_clause_reuse(&mut vec![x(0), x(1)]));
+        assert_eq!(s.solve_limited(&[]), lbool::TRUE);
+
+        // only an unconditional conflict is final
+        assert!(!s.add_clause_reuse(&mut vec![sel]));
+        assert!(!s.is_ok());
+        assert_eq!(s.solve_limited(&[]), lbool::FALSE);
+    }
 
-        // Update order_heap with respect to new activity:
-        let mut order_heap = order_heap_data.promote(VarOrder {
-            activity: &self.activity,
-        });
-        if order_heap.in_heap(v) {
-            order_heap.decrease(v);
+    #[test]
+    fn test_add_unit_anytime() {
+        use super::{Solver, SolveStatus};
+        use interface::SolverInterface;
+        use {lbool, Lit, Var};
+        let x = |i: u32| Lit::new(Var::from_idx(i), true);
+        // pigeon hole 5 -> 4, plus a free variable x(20)
+        let mut s = Solver::default();
+        pigeonhole(&mut s, 4);
+        s.new_var_default();
+        assert_eq!(s.solve_steps(5), SolveStatus::InProgress);
+        assert!(s.add_unit_anytime(!x(20)));
+        assert_eq!(s.decision_level(), 0);
+        assert_eq!(s.value_lvl_0(x(20)), lbool::FALSE);
+        let mut status = SolveStatus::InProgress;
+        while status == SolveStatus::InProgress {
+            status = s.solve_steps(5);
         }
+        assert_eq!(status, SolveStatus::Unsat);
+
+        // a unit that contradicts level 0 ends the search
+        let mut s = Solver::from_clauses(3, &[&[x(0), x(1)], &[x(0), !x(1)], &[x(1), x(2)]]);
+        s.assume_and_propagate(!x(2));
+        assert!(s.add_unit_anytime(x(2)));
+        assert!(s.add_unit_anytime(x(0)));
+        assert_eq!(s.decision_level(), 0);
+        assert!(!s.add_unit_anytime(!x(0)));
+        assert_eq!(s.solve_steps(5), SolveStatus::Unsat);
     }
 
-    /// Detach a clause from watcher lists.
-    ///
-    /// param `strict` means we remove the clause from watchers eagerly, instead
-    /// of just marking the watchlist as "dirty"
-    fn detach_clause(
-        &mut self,
-        ca: &mut ClauseAllocator,
-        watches_data: &mut OccListsData<Lit, Watcher>,
-        cr: CRef,
-        strict: bool,
-    ) {
-        let (c0, c1, csize, clearnt) = {
-            let c = ca.get_ref(cr);
-            (c[0], c[1], c.size(), c.learnt())
+    /// the decisions do not depend on the order of the clauses when activities tie
+    #[test]
+    fn test_deterministic_ties() {
+        use super::Solver;
+        use interface::SolverInterface;
+        use std::cell::RefCell;
+        use std::rc::Rc;
+        use {lbool, Lit, Var};
+        let lit = |i: i32| Lit::new(Var::from_idx(i.unsigned_abs() - 1), i > 0);
+        let decisions = |clauses: &[[i32; 2]]| {
+            let clauses: Vec<Vec<Lit>> =
+                clauses.iter().map(|c| c.iter().map(|&i| lit(i)).collect()).collect();
+            let refs: Vec<&[Lit]> = clauses.iter().map(|c| &c[..]).collect();
+            let mut s = Solver::from_clauses(10, &refs);
+            let trace = Rc::new(RefCell::new(vec![]));
+            let trace2 = trace.clone();
+            s.set_polarity_callback(move |v| {
+                trace2.borrow_mut().push(v);
+                lbool::UNDEF
+            });
+            assert_eq!(s.solve_limited(&[]), lbool::TRUE);
+            let res = trace.borrow().clone();
+            res
         };
-        debug_assert!(csize > 1);
+        // deciding `-1` leads to a conflict, after which the trail is put back in
+        // the heap in an order that depends on the watch lists
+        let mut clauses = vec![
+            [1, 2], [1, 3], [1, 4], [1, 5], [5, 7], [5, 9], [6, 10], [-2, -3], [-10, -6],
+        ];
+        let trace = decisions(&clauses);
+        clauses.reverse();
+        assert_eq!(decisions(&clauses), trace);
+    }
 
-        let mut watches = watches_data.promote(WatcherDeleted { ca });
+    /// literals false at level 0 are removed from the learnt clauses by `simplify`
+    #[test]
+    fn test_trim_learnts() {
+        use super::Solver;
+        use interface::SolverInterface;
+        use {lbool, Lit};
+        let mut s = Solver::default();
+        let vars = random_3sat(&mut s, 1, 100, 426);
+        assert_eq!(s.solve_limited(&[]), lbool::TRUE);
+        let lits_before = s.v.learnts_literals;
+
+        // fix a fifth of the variables as in the model
+        for &v in vars.iter().step_by(5) {
+            let value = s.value_var(v) == lbool::TRUE;
+            s.add_clause_reuse(&mut vec![Lit::new(v, value)]);
+        }
+        s.set_simp_props_threshold(0);
+        assert!(s.simplify());
+        assert!(s.num_learnts() > 0);
+        assert!(s.v.learnts_literals < lits_before);
+        let mut learnts_literals = 0;
+        for &cr in &s.learnts {
+            let c = s.ca.get_ref(cr);
+            assert!(c.iter().all(|&lit| s.v.value_lit(lit) == lbool::UNDEF));
+            learnts_literals += c.size() as u64;
+        }
+        assert_eq!(s.v.learnts_literals, learnts_literals);
+        let clauses_literals: u64 = s.clauses.iter().map(|&cr| s.ca.get_ref(cr).size() as u64).sum();
+        assert_eq!(s.v.clauses_literals, clauses_literals);
+        assert_eq!(s.solve_limited(&[]), lbool::TRUE);
+    }
 
-        // Strict or lazy detaching:
-        if strict {
-            // watches[!c0].remove_item(&Watcher::new(cr, c1));
-            // watches[!c1].remove_item(&Watcher::new(cr, c0));
-            let pos = watches[!c0]
-                .iter()
-                .position(|x| x == &Watcher::new(cr, c1))
-                .expect("Watcher not found");
-            watches[!c0].remove(pos);
-            let pos = watches[!c1]
-                .iter()
-                .position(|x| x == &Watcher::new(cr, c0))
-                .expect("Watcher not found");
-            watches[!c1].remove(pos);
-        } else {
-            watches.smudge(!c0);
-            watches.smudge(!c1);
+    /// variables removed by preprocessing get values in the model
+    #[test]
+    fn test_preprocess_extends_model() {
+        use super::Preprocessor;
+        use interface::SolverInterface;
+        use {lbool, Lit};
+        let mut s = Solver::default();
+        let vars = random_3sat(&mut s, 1, 50, 150);
+        let original: Vec<Vec<Lit>> =
+            s.clauses.iter().map(|&cr| s.ca.get_ref(cr).iter().cloned().collect()).collect();
+        let report = s.preprocess(&Preprocessor::default());
+        assert!(report.ok);
+        assert!(report.eliminated_vars > 0);
+        assert!(report.clauses_after < report.clauses_before);
+        assert_eq!(s.solve_limited(&[]), lbool::TRUE);
+        assert!(vars.iter().all(|&v| s.value_var(v) != lbool::UNDEF));
+        for c in &original {
+            assert!(c.iter().any(|&lit| s.value_lit(lit) == lbool::TRUE), "{:?}", c);
         }
+        assert!(s.verify_model());
+    }
 
-        if clearnt {
-            self.num_learnts -= 1;
-            self.learnts_literals -= csize as u64;
-        } else {
-            self.num_clauses -= 1;
-            self.clauses_literals -= csize as u64;
+    #[test]
+    fn test_preprocess_unsat() {
+        use super::Preprocessor;
+        use interface::SolverInterface;
+        use lbool;
+        let mut s = Solver::default();
+        pigeonhole(&mut s, 3);
+        let report = s.preprocess(&Preprocessor::default());
+        assert_eq!(report.ok, s.is_ok());
+        assert_eq!(s.solve_limited(&[]), lbool::FALSE);
+        assert!(!s.is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "eliminated variable")]
+    fn test_preprocess_eliminated_var_in_clause() {
+        use super::Preprocessor;
+        use interface::SolverInterface;
+        let mut s = Solver::default();
+        let x: Vec<Lit> = (0..3).map(|_| Lit::new(s.new_var_default(), true)).collect();
+        s.add_clause_reuse(&mut vec![x[0], x[1]]);
+        s.add_clause_reuse(&mut vec![!x[1], x[2]]);
+        let cfg = Preprocessor { frozen: vec![x[0].var(), x[2].var()], ..Preprocessor::default() };
+        s.preprocess(&cfg);
+        assert!(s.is_eliminated(x[1].var()));
+        s.add_clause_reuse(&mut vec![x[1], x[2]]);
+    }
+
+    /// Horn and 2-SAT formulas are solved without search, other formulas by `solve_limited`
+    #[test]
+    fn test_solve_structured() {
+        use super::FormulaClass;
+        use interface::SolverInterface;
+        use {lbool, Var};
+        let x = |i: u32| Lit::new(Var::from_idx(i), true);
+
+        let mut s = Solver::from_clauses(5, &[&[!x(0), !x(1), x(2)], &[!x(2), !x(3), x(4)], &[x(3)]]);
+        assert_eq!(s.detect_structure(), FormulaClass::Horn);
+        assert_eq!(s.solve_structured(), lbool::TRUE);
+        assert!(s.is_sat() && s.verify_model());
+        assert_eq!(s.value_lit(x(0)), lbool::FALSE);
+        assert_eq!(s.value_lit(x(3)), lbool::TRUE);
+        assert_eq!((s.stats().solves, s.stats().decisions), (1, 0));
+
+        let clauses: &[&[Lit]] = &[&[x(0), x(1)], &[!x(0), !x(1)], &[!x(0), x(2)], &[x(1), !x(2)]];
+        let mut s = Solver::from_clauses(3, clauses);
+        assert_eq!(s.detect_structure(), FormulaClass::TwoSat);
+        assert_eq!(s.solve_structured(), lbool::TRUE);
+        assert!(s.is_sat() && s.verify_model());
+        assert_eq!(s.value_lit(x(0)), lbool::FALSE);
+        assert_eq!((s.stats().solves, s.stats().decisions), (1, 0));
+        s.add_clause_reuse(&mut vec![x(0), !x(1)]);
+        assert_eq!(s.solve_structured(), lbool::FALSE);
+        assert!(s.is_unsat() && !s.is_ok());
+        assert_eq!((s.stats().solves, s.stats().conflicts), (2, 0));
+
+        let mut s = Solver::default();
+        pigeonhole(&mut s, 3);
+        assert_eq!(s.detect_structure(), FormulaClass::General);
+        assert_eq!(s.solve_structured(), lbool::FALSE);
+        assert!(s.is_unsat());
+        assert_eq!(s.stats().solves, 1);
+        assert!(s.stats().conflicts > 0);
+    }
+
+    /// `maxsat_linear` finds the minimal cost, with and without weights
+    #[test]
+    fn test_maxsat_linear() {
+        use interface::SolverInterface;
+        use {lbool, Var};
+        let x = |i: u32| Lit::new(Var::from_idx(i), true);
+        // vertex cover of the path 0 - 1 - 2 - 3
+        let edges: &[&[Lit]] = &[&[x(0), x(1)], &[x(1), x(2)], &[x(2), x(3)]];
+        let cost_of = |model: &[lbool], soft: &[(Lit, u64)]| -> u64 {
+            soft.iter().filter(|&&(lit, _)| model[lit.var().idx() as usize] ^ !lit.sign() != lbool::TRUE)
+                .map(|&(_, w)| w).sum()
+        };
+        for &(weights, optimum) in &[([1, 1, 1, 1], 2), ([1, 5, 5, 1], 6), ([3, 1, 1, 3], 2)] {
+            let mut s = Solver::from_clauses(4, edges);
+            let soft: Vec<(Lit, u64)> = (0..4).map(|i| (!x(i), weights[i as usize])).collect();
+            let (cost, model) = s.maxsat_linear(&soft);
+            assert_eq!(cost, optimum, "weights {:?}", weights);
+            assert_eq!(model.len(), 4);
+            assert_eq!(cost_of(&model, &soft), cost);
+            let value = |lit: Lit| model[lit.var().idx() as usize] ^ !lit.sign();
+            assert!(edges.iter().all(|c| c.iter().any(|&lit| value(lit) == lbool::TRUE)));
+            // the bound on the cost does not remain
+            assert_eq!(s.solve_limited(&[x(0), x(1), x(2), x(3)]), lbool::TRUE);
+        }
+
+        let mut s = Solver::from_clauses(1, &[&[x(0)], &[!x(0)]]);
+        assert_eq!(s.maxsat_linear(&[(x(0), 1)]), (u64::MAX, vec![]));
+    }
+
+    /// the `Result`-based API reports unknown results and invalid states as errors
+    #[test]
+    fn test_try_solve() {
+        use super::{AddResult, SatResult, SolverError};
+        use Var;
+        let x = |i: u32| Lit::new(Var::from_idx(i), true);
+        let mut s = Solver::from_clauses(2, &[&[x(0), x(1)]]);
+        assert_eq!(s.try_solve(), Ok(SatResult::Sat));
+        assert_eq!(s.try_add_clause(&[!x(0)]), Ok(AddResult::Added));
+        assert_eq!(s.try_solve(), Ok(SatResult::Sat));
+
+        let mut s = Solver::default();
+        pigeonhole(&mut s, 4);
+        s.set_conflict_budget(1);
+        assert_eq!(s.try_solve(), Err(SolverError::Interrupted));
+        s.budget_off();
+        assert_eq!(s.try_solve(), Ok(SatResult::Unsat));
+        assert_eq!(s.try_add_clause(&[x(0)]), Err(SolverError::Inconsistent));
+        assert_eq!(SolverError::Inconsistent.to_string(), "solver in inconsistent state");
+    }
+
+    /// the search stops as soon as the conflict budget is exhausted, even when
+    /// conflicts follow each other without a decision in between
+    #[test]
+    fn test_conflict_budget_exact() {
+        use interface::SolverInterface;
+        use lbool;
+        for budget in 1..40 {
+            let mut s = Solver::default();
+            pigeonhole(&mut s, 5);
+            s.set_conflict_budget(budget);
+            assert_eq!(s.solve_limited(&[]), lbool::UNDEF);
+            assert_eq!(s.stats().conflicts, budget as u64, "budget {}", budget);
+            assert_eq!(s.decision_level(), 0);
         }
     }
 
-    /// Detach and free a clause.
-    fn remove_clause(
-        &mut self,
-        ca: &mut ClauseAllocator,
-        watches_data: &mut OccListsData<Lit, Watcher>,
-        cr: CRef,
-    ) {
-        self.detach_clause(ca, watches_data, cr, false);
-        {
-            let c = ca.get_ref(cr);
-            // Don't leave pointers to free'd memory!
-            if self.locked(ca, c) {
-                self.vardata[c[0].var()].reason = CRef::UNDEF;
+    /// basic conflict clause minimization removes literals, and is counted
+    #[test]
+    fn test_basic_minimization() {
+        use super::SolverOpts;
+        use interface::SolverInterface;
+        use lbool;
+        let mut s = Solver::new(SolverOpts { ccmin_mode: 1, ..SolverOpts::default() });
+        pigeonhole(&mut s, 5);
+        assert_eq!(s.solve_limited(&[]), lbool::FALSE);
+        let stats = s.stats();
+        assert!(stats.tot_literals < stats.max_literals);
+        assert!(s.minimization_ratio() > 0.0);
+    }
+
+    /// a search run by steps gives the same result as a single call to `solve`
+    #[test]
+    fn test_solve_steps() {
+        use super::SolveStatus;
+        use interface::SolverInterface;
+        use lbool;
+        let mut s = Solver::default();
+        pigeonhole(&mut s, 4);
+        assert_eq!(s.solve_limited(&[]), lbool::FALSE);
+        let conflicts = s.stats().conflicts;
+
+        let mut s = Solver::default();
+        pigeonhole(&mut s, 4);
+        let mut n_steps = 0;
+        loop {
+            let before = s.stats().conflicts;
+            let status = s.solve_steps(1);
+            assert!(s.stats().conflicts <= before + 1);
+            n_steps += 1;
+            if status != SolveStatus::InProgress {
+                assert_eq!(status, SolveStatus::Unsat);
+                break;
             }
+            assert!(!s.is_sat() && !s.is_unsat());
         }
-        ca.get_mut(cr).set_mark(1); // used in reloc
-        ca.free(cr);
+        assert!(n_steps > 1);
+        assert_eq!(s.stats().conflicts, conflicts);
+        assert_eq!(s.stats().solves, 1);
+        assert!(s.is_unsat());
+
+        let mut s = Solver::default();
+        random_3sat(&mut s, 1, 100, 426);
+        let mut status = SolveStatus::InProgress;
+        while status == SolveStatus::InProgress {
+            status = s.solve_steps(10);
+        }
+        assert_eq!(status, SolveStatus::Sat);
+        assert!(s.is_sat() && s.verify_model());
+        assert_eq!(s.decision_level(), 0);
+
+        // a search in progress can be abandoned
+        let mut s = Solver::default();
+        pigeonhole(&mut s, 4);
+        assert_eq!(s.solve_steps(1), SolveStatus::InProgress);
+        s.cancel_steps();
+        assert_eq!(s.decision_level(), 0);
+        assert_eq!(s.solve_limited(&[]), lbool::FALSE);
     }
 
-    pub fn satisfied(&self, c: ClauseRef) -> bool {
-        c.iter().any(|&lit| self.value_lit(lit) == lbool::TRUE)
+    /// with a decision scope, a model is only returned if it satisfies the clauses
+    #[test]
+    fn test_decision_scope() {
+        use interface::SolverInterface;
+        use {lbool, Var};
+        let x = |i: u32| Lit::new(Var::from_idx(i), true);
+        // x(0) implies x(1), and x(2) or x(3) is left to decisions
+        let mut s = Solver::from_clauses(4, &[&[!x(0), x(1)], &[x(2), x(3)]]);
+        s.set_decision_scope(&[Var::from_idx(0)]);
+        assert_eq!(s.solve_limited(&[x(0)]), lbool::UNDEF);
+        assert!(!s.is_sat() && !s.is_unsat());
+        assert!(s.get_model().is_empty());
+        assert_eq!(s.solve_limited(&[x(0), x(2)]), lbool::TRUE);
+        assert!(s.verify_model());
+        assert_eq!(s.value_lit(x(1)), lbool::TRUE);
+        assert_eq!(s.value_var(Var::from_idx(3)), lbool::UNDEF);
+        assert_eq!(s.solve_limited(&[x(0), !x(1)]), lbool::FALSE);
+
+        s.set_decision_scope(&[Var::from_idx(0), Var::from_idx(2)]);
+        assert_eq!(s.solve_limited(&[x(0)]), lbool::TRUE);
+        assert!(s.is_sat() && s.verify_model());
+
+        s.clear_decision_scope();
+        assert_eq!(s.solve_limited(&[]), lbool::TRUE);
+        assert!(s.get_model().iter().all(|&v| v != lbool::UNDEF));
     }
 
-    #[inline(always)]
-    pub fn decision_level(&self) -> u32 {
-        self.trail_lim.len() as u32
+    /// the reduce callback reports the number of stored clauses
+    #[test]
+    fn test_reduce_callback() {
+        use interface::SolverInterface;
+        use std::cell::RefCell;
+        use std::rc::Rc;
+        use lbool;
+        let mut s = Solver::default();
+        pigeonhole(&mut s, 8);
+        let n_clauses = s.num_clauses() as usize;
+        let events = Rc::new(RefCell::new(vec![]));
+        let events2 = events.clone();
+        s.set_reduce_callback(move |before, after| events2.borrow_mut().push((before, after)));
+        s.set_conflict_budget(3000);
+        assert_eq!(s.solve_limited(&[]), lbool::UNDEF);
+        assert!(events.borrow().iter().any(|&(before, after)| after < before));
+        for &(before, after) in events.borrow().iter() {
+            assert!(after >= n_clauses && after <= before, "{} -> {}", before, after);
+        }
+
+        events.borrow_mut().clear();
+        let learnts = s.num_learnts() as usize;
+        assert!(learnts > 0);
+        s.shrink_clause_db_to(0);
+        let locked = s.num_learnts() as usize;
+        s.garbage_collect();
+        let total = n_clauses + locked;
+        assert_eq!(events.borrow()[0], (n_clauses + learnts, total));
+        assert_eq!(events.borrow().last(), Some(&(total, total)));
     }
 
-    #[inline(always)]
-    fn reason(&self, x: Var) -> CRef {
-        self.vardata[x].reason
+    /// `contains_clause` finds clauses with each lookup method, also after they are trimmed
+    #[test]
+    fn test_contains_clause() {
+        use super::Preprocessor;
+        use interface::SolverInterface;
+        use Var;
+        let x = |i: u32| Lit::new(Var::from_idx(i), true);
+        let only_clean = Preprocessor {
+            eliminate: false, subsume: false, probe: false, substitute_equiv: false, vivify: false,
+            ..Preprocessor::default()
+        };
+        for &(hash, simp) in &[(true, false), (false, true), (false, false)] {
+            for &trim_by_simplify in &[false, true] {
+                let mut s = Solver::from_clauses(4, &[&[x(0), x(1), x(2)], &[x(0), !x(1), x(3)]]);
+                s.set_detect_duplicates(hash);
+                s.set_simp_mode(simp);
+                assert!(s.contains_clause(&[x(2), x(0), x(1)]));
+                assert!(s.contains_clause(&[x(0), !x(1), x(3), x(0)]));
+                assert!(s.contains_clause(&[x(0), !x(0)]));
+                assert!(!s.contains_clause(&[x(0), x(1)]));
+                assert!(!s.contains_clause(&[x(0), x(1), x(3)]));
+
+                // `x(2)` is removed from the first clause
+                s.add_clause_reuse(&mut vec![!x(2)]);
+                if trim_by_simplify {
+                    s.remove_satisfied = true;
+                    s.set_simp_props_threshold(0);
+                    assert!(s.simplify());
+                } else {
+                    assert!(s.preprocess(&only_clean).ok);
+                }
+                assert_eq!(s.v.clauses_literals, 5);
+                assert!(s.contains_clause(&[x(1), x(0)]), "hash {} simp {}", hash, simp);
+                assert!(s.contains_clause(&[x(0), x(1), x(2)]));
+                assert!(!s.contains_clause(&[x(0), x(2)]));
+            }
+        }
     }
 
-    #[inline(always)]
-    fn level(&self, x: Var) -> i32 {
-        self.vardata[x].level
+    #[test]
+    fn test_load_var_order() {
+        use interface::SolverInterface;
+        use std::cell::RefCell;
+        use std::rc::Rc;
+        use {lbool, Var};
+        let mut s = Solver::from_clauses(5, &[]);
+        let trace = Rc::new(RefCell::new(vec![]));
+        let trace2 = trace.clone();
+        s.set_polarity_callback(move |v| {
+            trace2.borrow_mut().push(v);
+            lbool::UNDEF
+        });
+        s.load_var_order(&b"c preferred first\n4 2 0\n4 5 0\n"[..]).unwrap();
+        assert_eq!(s.solve_limited(&[]), lbool::TRUE);
+        let first: Vec<Var> = trace.borrow()[..3].to_vec();
+        assert_eq!(first, vec![Var::from_idx(3), Var::from_idx(1), Var::from_idx(4)]);
+
+        assert!(s.load_var_order(&b"1 6"[..]).is_err());
+        assert!(s.load_var_order(&b"1 -2"[..]).is_err());
+        assert!(s.load_var_order(&b"1 x"[..]).is_err());
+        assert!(s.load_var_order(&b""[..]).is_ok());
     }
 
-    fn unchecked_enqueue(&mut self, p: Lit, from: CRef) {
-        debug_assert_eq!(self.value_lit(p), lbool::UNDEF);
-        self.assigns[p.var()] = lbool::new(p.sign());
-        self.vardata[p.var()] = VarData::new(from, self.decision_level() as i32);
-        self.trail.push(p);
+    /// removing a clause by handle drops the learnt clauses, and ignores unknown handles
+    #[test]
+    fn test_remove_clause_by_handle() {
+        use interface::SolverInterface;
+        use lbool;
+        let mut other = Solver::default();
+        let y: Vec<Lit> = (0..3).map(|_| Lit::new(other.new_var_default(), true)).collect();
+        let foreign: Vec<_> = (0..3)
+            .map(|i| other.add_clause_with_handle(&mut vec![y[i], y[(i + 1) % 3]]).unwrap())
+            .collect();
+
+        let mut s = Solver::default();
+        let vars = random_3sat(&mut s, 1, 100, 426);
+        let x = |i: usize| Lit::new(vars[i], true);
+        let h = s.add_clause_with_handle(&mut vec![x(0), x(1), x(2)]).unwrap();
+        assert_eq!(s.solve_limited(&[]), lbool::TRUE);
+        assert!(s.num_learnts() > 0);
+        let n_clauses = s.num_clauses();
+        assert!(s.remove_clause_by_handle(h));
+        assert_eq!(s.num_clauses(), n_clauses - 1);
+        assert_eq!(s.num_learnts(), 0);
+        assert!(!s.remove_clause_by_handle(h));
+        assert_eq!(s.clause_lits(h), None);
+        assert_eq!(s.solve_limited(&[]), lbool::TRUE);
+
+        // not a handle of `s`
+        assert!(!s.remove_clause_by_handle(foreign[2]));
+        assert_eq!(s.clause_lits(foreign[2]), None);
     }
 
-    /// Returns TRUE if a clause is a reason for some implication in the current state.
-    fn locked(&self, ca: &ClauseAllocator, c: ClauseRef) -> bool {
-        let reason = self.reason(c[0].var());
-        self.value_lit(c[0]) == lbool::TRUE && reason != CRef::UNDEF && ca.get_ref(reason) == c
+    /// occurrence lists follow the clauses removed or trimmed by `simplify`
+    #[test]
+    fn test_occurrences_after_simplify() {
+        use interface::SolverInterface;
+        use lbool;
+        let mut s = Solver::default();
+        let vars = random_3sat(&mut s, 1, 100, 300);
+        s.set_simp_mode(true);
+        for &v in vars.iter().step_by(7) {
+            s.add_clause_reuse(&mut vec![Lit::new(v, true)]);
+        }
+        s.remove_satisfied = true;
+        s.set_simp_props_threshold(0);
+        let report = s.simplify_detailed();
+        assert!(report.clauses_removed > 0 && report.literals_trimmed > 0);
+        for &v in &vars {
+            let expected: Vec<_> = s.clauses.iter().cloned()
+                .filter(|&cr| s.ca.get_ref(cr).iter().any(|lit| lit.var() == v))
+                .collect();
+            let occs = s.occurrences(v).to_vec();
+            assert_eq!(occs.len(), expected.len(), "{:?}", v);
+            assert!(occs.iter().all(|cr| expected.contains(cr)));
+        }
+        assert_eq!(s.solve_limited(&[]), lbool::TRUE);
     }
-    // inline bool     Solver::locked          (const Clause& c) const { return value(c[0]) == l_True && reason(var(c[0])) != CRef_Undef && ca.lea(reason(var(c[0]))) == &c; }
-}
 
-#[derive(Debug)]
-enum ClauseSet {
-    Original,
-    Learnt,
-}
+    /// propagator clauses are propagated during search
+    #[test]
+    fn test_propagator_implies() {
+        use interface::{Propagator, SolverInterface};
+        use lbool;
+        // theory: each var implies the next one
+        struct Chain(Vec<Var>);
+        impl Propagator for Chain {
+            fn propagate(&mut self, trail: &[Lit]) -> Option<Vec<Lit>> {
+                for w in self.0.windows(2) {
+                    let a = Lit::new(w[0], true);
+                    let b = Lit::new(w[1], true);
+                    if trail.contains(&a) && !trail.contains(&b) {
+                        return Some(vec![!a, b]);
+                    }
+                }
+                None
+            }
+        }
+        let mut s = Solver::default();
+        let vars: Vec<Var> = (0..10).map(|_| s.new_var_default()).collect();
+        s.set_external_propagator(Chain(vars.clone()));
+        s.add_clause_reuse(&mut vec![Lit::new(vars[3], true)]);
+        assert_eq!(s.solve_limited(&[]), lbool::TRUE);
+        for &v in &vars[3..] {
+            assert_eq!(s.value_var(v), lbool::TRUE, "{:?}", v);
+        }
+        assert_eq!(s.solve_limited(&[Lit::new(vars[9], false)]), lbool::FALSE);
+    }
 
-#[derive(Debug, Clone, Copy)]
-struct VarData {
-    reason: CRef,
-    level: i32,
-}
+    /// propagator clauses that are false under the trail are conflicts
+    #[test]
+    fn test_propagator_conflict() {
+        use interface::{Propagator, SolverInterface};
+        use lbool;
+        // theory: at most one of the vars is true
+        struct AtMostOne(Vec<Var>);
+        impl Propagator for AtMostOne {
+            fn propagate(&mut self, trail: &[Lit]) -> Option<Vec<Lit>> {
+                let mut true_lits = trail.iter().filter(|l| l.sign() && self.0.contains(&l.var()));
+                match (true_lits.next(), true_lits.next()) {
+                    (Some(&a), Some(&b)) => Some(vec![!a, !b]),
+                    _ => None,
+                }
+            }
+        }
+        let mut s = Solver::default();
+        let vars: Vec<Var> = (0..6).map(|_| s.new_var_default()).collect();
+        s.set_external_propagator(AtMostOne(vars.clone()));
+        for w in vars.windows(2) {
+            s.add_clause_reuse(&mut vec![Lit::new(w[0], true), Lit::new(w[1], true)]);
+        }
+        // only possible with exactly one true var, but the chain needs 3
+        assert_eq!(s.solve_limited(&[]), lbool::FALSE);
+
+        let mut s = Solver::default();
+        let vars: Vec<Var> = (0..6).map(|_| s.new_var_default()).collect();
+        s.set_external_propagator(AtMostOne(vars.clone()));
+        s.add_clause_reuse(&mut vars.iter().map(|&v| Lit::new(v, true)).collect());
+        assert_eq!(s.solve_limited(&[]), lbool::TRUE);
+        assert_eq!(vars.iter().filter(|&&v| s.value_var(v) == lbool::TRUE).count(), 1);
+    }
 
-impl Default for VarData {
-    fn default() -> Self {
-        Self {
-            reason: CRef::UNDEF,
-            level: 0,
+    /// last-UIP and all-UIP analysis learn implied clauses, and agree with first-UIP
+    #[test]
+    fn test_uip_modes() {
+        use interface::SolverInterface;
+        use super::UipMode;
+        use lbool;
+        let mut n_learnts = 0;
+        for &mode in &[UipMode::Last, UipMode::All] {
+            for seed in 1..9 {
+                let mut reference = Solver::default();
+                random_3sat(&mut reference, seed, 30, 128);
+                let expected = reference.solve_limited(&[]);
+
+                let mut s = Solver::default();
+                s.set_uip_mode(mode);
+                random_3sat(&mut s, seed, 30, 128);
+                let original: Vec<Vec<Lit>> =
+                    s.clauses.iter().map(|&cr| s.ca.get_ref(cr).lits().to_vec()).collect();
+                assert_eq!(s.solve_limited(&[]), expected, "{:?} {}", mode, seed);
+                if expected == lbool::TRUE {
+                    for c in &original {
+                        assert!(c.iter().any(|&lit| s.value_lit(lit) == lbool::TRUE));
+                    }
+                }
+                for &cr in &s.learnts {
+                    let learnt = s.ca.get_ref(cr).lits().to_vec();
+                    let mut check = Solver::default();
+                    random_3sat(&mut check, seed, 30, 128);
+                    for &lit in &learnt {
+                        check.add_clause_reuse(&mut vec![!lit]);
+                    }
+                    assert_eq!(check.solve_limited(&[]), lbool::FALSE, "{:?} {:?}", mode, learnt);
+                    n_learnts += 1;
+                }
+            }
+            let mut s = Solver::default();
+            s.set_uip_mode(mode);
+            pigeonhole(&mut s, 5);
+            assert_eq!(s.solve_limited(&[]), lbool::FALSE);
         }
+        assert!(n_learnts > 0);
     }
-}
 
-impl VarData {
-    fn new(reason: CRef, level: i32) -> Self {
-        Self { reason, level }
+    /// new watches of long clauses are found past the parallel scan threshold
+    #[test]
+    fn test_long_clause_watches() {
+        use interface::SolverInterface;
+        use super::find_new_watch;
+        use lbool;
+        let mut s = Solver::default();
+        let lits: Vec<Lit> = (0..5000).map(|_| Lit::new(s.new_var_default(), true)).collect();
+        s.add_clause_reuse(&mut lits.clone());
+        for &free in &[4999, 2500, 2] {
+            let assumps: Vec<Lit> = lits.iter().enumerate()
+                .filter(|&(i, _)| i != free)
+                .map(|(_, &lit)| !lit)
+                .collect();
+            assert_eq!(s.solve_limited(&assumps), lbool::TRUE);
+            assert_eq!(s.value_lit(lits[free]), lbool::TRUE, "{}", free);
+        }
+
+        // same answer as a serial scan, under partial assignments
+        for &n_false in &[0, 1, 2, 100, 3000, 4998, 5000] {
+            s.cancel_until(0);
+            s.new_decision_level();
+            for &lit in &lits[..n_false] {
+                s.v.unchecked_enqueue(!lit, super::CRef::UNDEF);
+            }
+            let expected = (2..lits.len()).find(|&k| s.v.value_lit(lits[k]) != lbool::FALSE);
+            assert_eq!(find_new_watch(&s.v.assigns, &lits), expected, "{}", n_false);
+        }
+        s.cancel_until(0);
     }
-}
 
-#[derive(Debug, Clone, Copy)]
-struct Watcher {
-    cref: CRef,
-    blocker: Lit,
-}
+    /// variables fixed at level 0 leave the order heap
+    #[test]
+    fn test_fixed_vars_leave_heap() {
+        use interface::SolverInterface;
+        use lbool;
+        let mut s = Solver::default();
+        let vars = random_3sat(&mut s, 5, 60, 240);
+        let mut model = s.clone();
+        assert_eq!(model.solve_limited(&[]), lbool::TRUE);
+        for &v in vars.iter().step_by(5) {
+            s.add_clause_reuse(&mut vec![Lit::new(v, model.value_var(v) == lbool::TRUE)]);
+        }
+        assert_eq!(s.propagate(), super::CRef::UNDEF);
+        let fixed: Vec<Var> = s.v.trail.iter().map(|lit| lit.var()).collect();
+        assert!(fixed.len() >= vars.len() / 5);
+
+        // the first decision flushes the fixed variables out of the heap
+        let next = s.pick_branch_lit();
+        assert!(!fixed.contains(&next.var()));
+        for &v in &fixed {
+            assert!(!s.order_heap().in_heap(v), "{:?}", v);
+        }
+        let n_free = vars.len() - fixed.len();
+        assert_eq!(vars.iter().filter(|&&v| s.order_heap().in_heap(v)).count(), n_free - 1);
+        s.insert_var_order(next.var());
+        assert_eq!(s.solve_limited(&[]), lbool::TRUE);
+    }
 
-impl Watcher {
-    const DUMMY : Watcher = Watcher {
-        cref: CRef::UNDEF,
-        blocker: Lit::UNDEF,
-    };
-    fn new(cref: CRef, blocker: Lit) -> Self {
-        Self { cref, blocker }
+    /// the first decision variable is used once, unless it is already assigned
+    #[test]
+    fn test_first_decision() {
+        use interface::SolverInterface;
+        use lbool;
+        let mut s = Solver::default();
+        let vars = random_3sat(&mut s, 3, 20, 40);
+        let mut reference = s.clone();
+        s.set_first_decision(vars[13]);
+        assert_eq!(s.pick_branch_lit().var(), vars[13]);
+        // then back to the heuristic
+        assert_eq!(s.pick_branch_lit(), reference.pick_branch_lit());
+
+        let mut s = Solver::default();
+        let vars = random_3sat(&mut s, 3, 20, 40);
+        s.add_clause_reuse(&mut vec![Lit::new(vars[2], true)]);
+        assert_eq!(s.propagate(), super::CRef::UNDEF);
+        let mut reference = s.clone();
+        s.set_first_decision(vars[2]);
+        assert_eq!(s.pick_branch_lit(), reference.pick_branch_lit());
+
+        let mut s = Solver::default();
+        let vars = random_3sat(&mut s, 3, 20, 40);
+        s.set_first_decision(vars[13]);
+        assert_eq!(s.solve_limited(&[]), lbool::TRUE);
+        assert!(s.verify_model());
     }
-}
 
-impl PartialEq for Watcher {
-    fn eq(&self, rhs: &Self) -> bool {
-        self.cref == rhs.cref
+    /// blocking each model with its negated cube enumerates all of them
+    #[test]
+    fn test_negated_cube() {
+        use interface::SolverInterface;
+        use lbool;
+        let mut s = Solver::default();
+        let a = Lit::new(s.new_var_default(), true);
+        let b = Lit::new(s.new_var_default(), true);
+        s.new_var_default();
+        s.add_clause_reuse(&mut vec![a, b]);
+        let mut models = vec![];
+        while s.solve_limited(&[]) == lbool::TRUE {
+            let cube = s.model_lits();
+            assert!(!models.contains(&cube));
+            models.push(cube.clone());
+            s.add_clause_negated_cube(&cube);
+        }
+        assert_eq!(models.len(), 6);
+
+        // a cube over a subset of the variables blocks all its extensions
+        let mut s = Solver::default();
+        let a = Lit::new(s.new_var_default(), true);
+        let b = Lit::new(s.new_var_default(), true);
+        s.new_var_default();
+        assert!(s.add_clause_negated_cube(&[a, !b]));
+        assert_eq!(s.solve_limited(&[a]), lbool::TRUE);
+        assert_eq!(s.value_lit(b), lbool::TRUE);
+        assert!(s.add_clause_negated_cube(&[a]));
+        assert!(!s.add_clause_negated_cube(&[!a]));
+        assert_eq!(s.solve_limited(&[]), lbool::FALSE);
     }
-}
-impl Eq for Watcher {}
 
-struct VarOrder<'a> {
-    activity: &'a VMap<f64>,
-}
+    #[test]
+    #[should_panic(expected = "complementary")]
+    fn test_negated_cube_complementary() {
+        use interface::SolverInterface;
+        let mut s = Solver::default();
+        let a = Lit::new(s.new_var_default(), true);
+        s.add_clause_negated_cube(&[a, !a]);
+    }
 
-impl<'a> PartialComparator<Var> for VarOrder<'a> {
-    fn partial_cmp(&self, lhs: &Var, rhs: &Var) -> Option<cmp::Ordering> {
-        Some(self.cmp(lhs, rhs))
+    /// the model accessors agree with `value_var`, and are empty without a model
+    #[test]
+    fn test_model_accessors() {
+        use interface::SolverInterface;
+        use lbool;
+        let mut s = Solver::default();
+        let vars = random_3sat(&mut s, 9, 30, 100);
+        assert!(s.model_vec().is_empty() && s.model_lits().is_empty());
+        assert_eq!(s.solve_limited(&[]), lbool::TRUE);
+        assert_eq!(s.model_vec(), s.get_model());
+        assert_eq!(s.model_vec().len(), vars.len());
+        let lits = s.model_lits();
+        assert_eq!(lits.len(), vars.len());
+        for (&lit, &v) in lits.iter().zip(&vars) {
+            assert_eq!(lit.var(), v);
+            assert_eq!(s.value_lit(lit), lbool::TRUE);
+        }
+        assert_eq!(s.solve_limited(&[!lits[0], lits[0]]), lbool::FALSE);
+        assert!(s.model_vec().is_empty() && s.model_lits().is_empty());
     }
-}
-impl<'a> Comparator<Var> for VarOrder<'a> {
-    fn cmp(&self, lhs: &Var, rhs: &Var) -> cmp::Ordering {
-        PartialOrd::partial_cmp(&self.activity[*rhs], &self.activity[*lhs]).expect("NaN activity")
+
+    /// new variables take the default polarity at their first decision
+    #[test]
+    fn test_default_polarity() {
+        use interface::SolverInterface;
+        use lbool;
+        let mut s = Solver::default();
+        let before: Vec<Var> = (0..5).map(|_| s.new_var_default()).collect();
+        s.set_default_polarity(true);
+        let after: Vec<Var> = (0..5).map(|_| s.new_var_default()).collect();
+        let user = s.new_var(lbool::FALSE, true);
+        assert_eq!(s.solve_limited(&[]), lbool::TRUE);
+        assert!(before.iter().all(|&v| s.value_var(v) == lbool::FALSE));
+        assert!(after.iter().all(|&v| s.value_var(v) == lbool::TRUE));
+        assert_eq!(s.value_var(user), lbool::FALSE);
     }
-}
 
-/// Predicate to test whether a clause has been removed from some lit's watchlist
-struct WatcherDeleted<'a> {
-    ca: &'a ClauseAllocator,
-}
+    /// each stored clause is counted in the watch lists of two literals
+    #[test]
+    fn test_watch_count() {
+        let mut s = Solver::default();
+        let vars = random_3sat(&mut s, 11, 20, 50);
+        let x = |i: usize| Lit::new(vars[i], true);
+        let h = s.add_clause_with_handle(&mut vec![x(0), x(1), x(2), x(3)]).unwrap();
+        let all_lits = || vars.iter().flat_map(|&v| vec![Lit::new(v, true), Lit::new(v, false)]);
+        let total: usize = all_lits().map(|lit| s.watch_count(lit)).sum();
+        assert_eq!(total, 2 * s.clauses.len());
+
+        // the new clause is watched by two of its literals
+        let counts: Vec<usize> = (0..4).map(|i| s.watch_count(!x(i))).collect();
+        assert!(s.remove_clause_by_handle(h));
+        let removed: usize = (0..4).map(|i| counts[i] - s.watch_count(!x(i))).sum();
+        assert_eq!(removed, 2);
+        let total: usize = all_lits().map(|lit| s.watch_count(lit)).sum();
+        assert_eq!(total, 2 * s.clauses.len());
+    }
 
-impl<'a> DeletePred<Watcher> for WatcherDeleted<'a> {
-    fn deleted(&self, w: &Watcher) -> bool {
-        self.ca.get_ref(w.cref).mark() == 1
+    /// `would_propagate` reports the implied literals and leaves the solver unchanged
+    #[test]
+    fn test_would_propagate() {
+        use interface::SolverInterface;
+        use lbool;
+        let mut s = Solver::default();
+        let x: Vec<Lit> = (0..5).map(|_| Lit::new(s.new_var_default(), true)).collect();
+        // x0 -> x1 -> x2, x0 & x3 -> x4
+        s.add_clause_reuse(&mut vec![!x[0], x[1]]);
+        s.add_clause_reuse(&mut vec![!x[1], x[2]]);
+        s.add_clause_reuse(&mut vec![!x[0], !x[3], x[4]]);
+        assert_eq!(s.would_propagate(&[!x[0]]), vec![x[1], x[2]]);
+        let mut implied = s.would_propagate(&[!x[0], !x[3]]);
+        implied.sort();
+        assert_eq!(implied, vec![x[1], x[2], x[4]]);
+        assert_eq!(s.would_propagate(&[x[2]]), vec![!x[1], !x[0]]);
+        assert_eq!(s.would_propagate(&[!x[4]]), vec![]);
+        assert_eq!(s.v.decision_level(), 0);
+        assert!(s.v.trail.is_empty());
+
+        // implied clause: propagation reaches a conflict
+        s.would_propagate(&[!x[0], x[2]]);
+        assert_eq!(s.v.decision_level(), 0);
+        assert!(s.v.trail.is_empty());
+        assert_eq!(s.solve_limited(&[x[0], x[3]]), lbool::TRUE);
+        assert_eq!(s.value_lit(x[4]), lbool::TRUE);
     }
-}
 
-#[derive(Debug, Clone, Copy)]
-/// Elements of the stack used for conflict analysis
-struct ShrinkStackElem {
-    i: u32,
-    l: Lit,
-}
+    /// learnt clauses are sorted when learnt, after their two watches
+    #[test]
+    fn test_learnt_sort() {
+        use super::{LearntSort, SolveStatus};
+        for &mode in &[LearntSort::ByLevel, LearntSort::ByActivity] {
+            let mut s = Solver::default();
+            s.set_learnt_sort(mode);
+            pigeonhole(&mut s, 6);
+            let mut n_checked = 0;
+            while s.solve_steps(1) == SolveStatus::InProgress {
+                let n = s.learnts.len();
+                if n == 0 {
+                    continue;
+                }
+                let c = s.ca.get_ref(s.learnts[n - 1]).lits().to_vec();
+                if c.len() <= 3 {
+                    continue;
+                }
+                match mode {
+                    LearntSort::ByLevel => {
+                        let levels: Vec<i32> = c[1..].iter().map(|l| s.v.level(l.var())).collect();
+                        assert!(levels.windows(2).all(|w| w[0] >= w[1]), "{:?}", levels);
+                    }
+                    _ => {
+                        let act: Vec<f64> = c[2..].iter().map(|l| s.v.activity[l.var()]).collect();
+                        assert!(act.windows(2).all(|w| w[0] >= w[1]), "{:?}", act);
+                    }
+                }
+                n_checked += 1;
+            }
+            assert!(n_checked > 10, "{:?}", mode);
+        }
+    }
 
-impl ShrinkStackElem {
-    fn new(i: u32, l: Lit) -> Self {
-        Self { i, l }
+    /// learnt clauses that propagated recently survive the reduction of the database
+    #[test]
+    fn test_reduce_protect() {
+        use super::SolveStatus;
+        let mut s = Solver::default();
+        s.set_reduce_protect(50);
+        pigeonhole(&mut s, 7);
+        assert_eq!(s.solve_steps(400), SolveStatus::InProgress);
+        // compare literals, as garbage collection may relocate the clauses
+        let protected: Vec<Vec<Lit>> = s.learnts.iter().cloned()
+            .filter(|&cr| s.recently_used(cr))
+            .map(|cr| s.ca.get_ref(cr).lits().to_vec())
+            .collect();
+        assert!(!protected.is_empty());
+        let before = s.learnts.len();
+        s.reduce_db();
+        assert!(s.learnts.len() < before);
+        let kept: Vec<Vec<Lit>> = s.learnts.iter()
+            .map(|&cr| s.ca.get_ref(cr).lits().to_vec())
+            .collect();
+        for c in &protected {
+            assert!(kept.contains(c), "{:?}", c);
+        }
+        // the protection moves with the clauses
+        s.garbage_collect();
+        let still: Vec<Vec<Lit>> = s.learnts.iter().cloned()
+            .filter(|&cr| s.recently_used(cr))
+            .map(|cr| s.ca.get_ref(cr).lits().to_vec())
+            .collect();
+        for c in &protected {
+            assert!(still.contains(c), "{:?}", c);
+        }
+        s.cancel_steps();
     }
-}
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-enum Seen {
-    UNDEF,
-    SOURCE,
-    REMOVABLE,
-    FAILED,
-}
+    /// the keep callback sees the clauses about to be deleted, and can keep them
+    #[test]
+    fn test_keep_clause_callback() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+        use super::SolveStatus;
+        let mut s = Solver::default();
+        pigeonhole(&mut s, 7);
+        assert_eq!(s.solve_steps(400), SolveStatus::InProgress);
+        let mut plain = s.clone();
+        let asked = Rc::new(Cell::new(0));
+        let asked2 = asked.clone();
+        s.set_keep_clause_callback(move |c| {
+            assert!(c.len() > 2);
+            asked2.set(asked2.get() + 1);
+            true
+        });
+        let before = s.learnts.len();
+        s.reduce_db();
+        assert_eq!(s.learnts.len(), before);
+        plain.reduce_db();
+        assert_eq!(plain.learnts.len() + asked.get(), before);
+        s.cancel_steps();
+    }
 
-impl Default for Seen {
-    fn default() -> Self {
-        Seen::UNDEF
+    /// `shrink_clause_db_to` keeps the clauses vetoed by the keep callback
+    #[test]
+    fn test_shrink_keep_clause_callback() {
+        use super::SolveStatus;
+        let mut s = Solver::default();
+        pigeonhole(&mut s, 7);
+        assert_eq!(s.solve_steps(400), SolveStatus::InProgress);
+        s.cancel_steps();
+        let mut vetoed: Vec<Vec<Lit>> = s.learnts.iter()
+            .map(|&cr| s.ca.get_ref(cr).lits().to_vec())
+            .filter(|c| c.len() % 2 == 0)
+            .collect();
+        assert!(!vetoed.is_empty());
+        s.set_keep_clause_callback(|c| c.len() % 2 == 0);
+        s.shrink_clause_db_to(0);
+        let mut kept: Vec<Vec<Lit>> = s.learnts.iter()
+            .map(|&cr| s.ca.get_ref(cr).lits().to_vec())
+            .collect();
+        kept.sort();
+        vetoed.sort();
+        assert_eq!(kept, vetoed);
     }
-}
 
-impl Seen {
-    fn is_seen(&self) -> bool {
-        *self != Seen::UNDEF
+    /// learnt clauses longer than the limit do not outlive the next restart
+    #[test]
+    fn test_max_learnt_len() {
+        use interface::SolverInterface;
+        use lbool;
+        let mut s = Solver::default();
+        s.set_max_learnt_len(3);
+        pigeonhole(&mut s, 6);
+        assert_eq!(s.solve_limited(&[]), lbool::FALSE);
+        assert!(s.transient.is_empty());
+        assert!(s.learnts.iter().all(|&cr| s.ca.get_ref(cr).size() <= 3));
+
+        let mut s = Solver::default();
+        s.set_max_learnt_len(2);
+        random_3sat(&mut s, 21, 80, 330);
+        let mut reference = Solver::default();
+        random_3sat(&mut reference, 21, 80, 330);
+        let expected = reference.solve_limited(&[]);
+        assert_eq!(s.solve_limited(&[]), expected);
+        assert!(s.conflicts > 0);
+        assert!(s.transient.is_empty());
+        assert!(s.learnts.iter().all(|&cr| s.ca.get_ref(cr).size() <= 2));
+        if expected == lbool::TRUE {
+            assert!(s.verify_model());
+        }
     }
-}
 
-pub struct SolverOpts {
-    pub var_decay: f64,
-    pub clause_decay: f64,
-    pub random_var_freq: f64,
-    pub random_seed: f64,
-    pub ccmin_mode: i32,
-    pub phase_saving: i32,
-    pub rnd_init_act: bool,
-    pub luby_restart: bool,
-    pub restart_first: i32,
-    pub restart_inc: f64,
-    pub garbage_frac: f64,
-    pub min_learnts_lim: i32,
-    pub produce_proof: bool,
-}
+    /// the LBD and trail length are recorded when a restart is decided
+    #[test]
+    fn test_last_restart() {
+        use interface::SolverInterface;
+        use lbool;
+        let mut s = Solver::default();
+        pigeonhole(&mut s, 8);
+        assert_eq!((s.last_restart_lbd(), s.last_restart_trail()), (0, 0));
+        s.set_conflict_budget(1000);
+        assert_eq!(s.solve_limited(&[]), lbool::UNDEF);
+        assert!(s.starts > 1);
+        assert!(s.last_restart_lbd() >= 1);
+        assert!(s.last_restart_lbd() as usize <= s.last_restart_trail());
+        assert!(s.last_restart_trail() <= s.num_vars() as usize);
+    }
 
-impl Default for SolverOpts {
-    fn default() -> SolverOpts {
-        Self {
-            var_decay: 0.95,
-            clause_decay: 0.999,
-            random_var_freq: 0.0,
-            random_seed: 91648253.0,
-            ccmin_mode: 2,
-            phase_saving: 2,
-            rnd_init_act: false,
-            luby_restart: true,
-            restart_first: 100,
-            restart_inc: 2.0,
-            garbage_frac: 0.20,
-            min_learnts_lim: 0,
-            produce_proof: false,
+    /// a lower propagation threshold lets `simplify` clean the database again sooner
+    #[test]
+    fn test_simp_props_threshold() {
+        use interface::SolverInterface;
+        let mut s = Solver { remove_satisfied: true, ..Solver::default() };
+        let vars = random_3sat(&mut s, 13, 100, 420);
+        s.add_clause_reuse(&mut vec![Lit::new(vars[2], true)]);
+        assert!(s.simplify_detailed().clauses_removed > 0);
+        let mut eager = s.clone();
+        eager.set_simp_props_threshold(0);
+        for solver in &mut [&mut s, &mut eager] {
+            solver.add_clause_reuse(&mut vec![Lit::new(vars[0], true)]);
         }
+        // the default waits for as many propagations as there are literals
+        assert_eq!(s.simplify_detailed().clauses_removed, 0);
+        assert!(eager.simplify_detailed().clauses_removed > 0);
+
+        // back to the default after the next cleaning
+        eager.set_simp_props_threshold(-1);
+        eager.add_clause_reuse(&mut vec![Lit::new(vars[1], true)]);
+        assert!(eager.simplify_detailed().clauses_removed > 0);
+        eager.add_clause_reuse(&mut vec![Lit::new(vars[3], true)]);
+        assert_eq!(eager.simplify_detailed().clauses_removed, 0);
     }
-}
 
-impl SolverOpts {
-    pub fn check(&self) -> bool {
-        (0.0 < self.var_decay && self.var_decay < 1.0)
-            && (0.0 < self.clause_decay && self.clause_decay < 1.0)
-            && (0.0 <= self.random_var_freq && self.random_var_freq <= 1.0)
-            && (0.0 < self.random_seed && self.random_seed < f64::INFINITY)
-            && (0 <= self.ccmin_mode && self.ccmin_mode <= 2)
-            && (0 <= self.phase_saving && self.phase_saving <= 2) && 1 <= self.restart_first
-            && (1.0 < self.restart_inc && self.restart_inc < f64::INFINITY)
-            && (0.0 < self.garbage_frac && self.garbage_frac < f64::INFINITY)
-            && 0 <= self.min_learnts_lim
+    /// activities are seeded from the occurrences in the remaining short clauses
+    #[test]
+    fn test_seed_activities() {
+        use interface::SolverInterface;
+        let mut s = Solver::default();
+        let x: Vec<Lit> = (0..6).map(|_| Lit::new(s.new_var_default(), true)).collect();
+        s.add_clause_reuse(&mut vec![x[0], x[1], x[2]]);
+        s.add_clause_reuse(&mut vec![x[1], x[2], x[3]]);
+        s.add_clause_reuse(&mut vec![x[2], x[3]]);
+        // satisfied at level 0: ignored
+        s.add_clause_reuse(&mut vec![x[4]]);
+        s.add_clause_reuse(&mut vec![x[4], x[5]]);
+        s.seed_activities_by_occurrence();
+        let act: Vec<f64> = x.iter().map(|lit| s.activity(lit.var())).collect();
+        // scores: 1/8, 2/8, 2/8 + 1/4, 1/8 + 1/4, 0, 0
+        assert_eq!(act[2], s.v.var_inc);
+        assert_eq!(act[0], s.v.var_inc / 4.0);
+        assert_eq!(act[1], s.v.var_inc / 2.0);
+        assert_eq!(act[3], s.v.var_inc * 0.75);
+        assert_eq!(act[5], 0.0);
+        assert_eq!(s.pick_branch_lit().var(), x[2].var());
     }
-}
 
-/// Finite subsequences of the Luby-sequence:
-///
-/// > 0: 1
-/// > 1: 1 1 2
-/// > 2: 1 1 2 1 1 2 4
-/// > 3: 1 1 2 1 1 2 4 1 1 2 1 1 2 4 8
-/// ...
-fn luby(y: f64, mut x: i32) -> f64 {
-    // Find the finite subsequence that contains index 'x', and the
-    // size of that subsequence:
-    let mut size = 1;
-    let mut seq = 0;
-    while size < x + 1 {
-        seq += 1;
-        size = 2 * size + 1;
+    /// polling the literals fixed at level 0 with a marker
+    #[test]
+    fn test_new_fixed_literals() {
+        use interface::SolverInterface;
+        use lbool;
+        let mut s = Solver::default();
+        let x: Vec<Lit> = (0..4).map(|_| Lit::new(s.new_var_default(), true)).collect();
+        assert!(s.new_fixed_literals_since(0).is_empty());
+        s.add_clause_reuse(&mut vec![!x[0], x[1]]);
+        s.add_clause_reuse(&mut vec![x[0]]);
+        assert_eq!(s.solve_limited(&[]), lbool::TRUE);
+        let marker = s.new_fixed_literals_since(0).len();
+        assert_eq!(s.new_fixed_literals_since(0), &[x[0], x[1]]);
+        assert!(s.new_fixed_literals_since(marker).is_empty());
+
+        s.add_clause_reuse(&mut vec![x[2], x[3]]);
+        s.add_clause_reuse(&mut vec![x[2], !x[3]]);
+        assert_eq!(s.solve_limited(&[]), lbool::TRUE);
+        assert_eq!(s.new_fixed_literals_since(marker), &[x[2]]);
     }
 
-    while size - 1 != x {
-        size = (size - 1) >> 1;
-        seq -= 1;
-        x = x % size;
+    #[test]
+    #[should_panic(expected = "beyond")]
+    fn test_new_fixed_literals_marker() {
+        use interface::SolverInterface;
+        let mut s = Solver::default();
+        let x = Lit::new(s.new_var_default(), true);
+        s.add_clause_reuse(&mut vec![x]);
+        s.new_fixed_literals_since(2);
     }
 
-    return f64::powi(y, seq);
-}
+    /// the lucky assignments find a model without search, or leave the solver as it was
+    #[test]
+    fn test_lucky_assignment() {
+        use interface::SolverInterface;
+        use lbool;
+        let mut s = Solver::default();
+        let x: Vec<Lit> = (0..4).map(|_| Lit::new(s.new_var_default(), true)).collect();
+        s.add_clause_reuse(&mut vec![x[0], !x[1]]);
+        s.add_clause_reuse(&mut vec![!x[0], !x[2]]);
+        s.add_clause_reuse(&mut vec![x[2], x[3]]);
+        let phases = |s: &Solver| -> Vec<bool> { s.vars().map(|v| s.polarity[v]).collect() };
+        let before = phases(&s);
+        let model = s.try_lucky_assignment(true).expect("all true, except x2");
+        assert_eq!(model, vec![lbool::TRUE, lbool::TRUE, lbool::FALSE, lbool::TRUE]);
+        assert_eq!(s.get_model(), &model[..]);
+        assert!(s.verify_model());
+        assert_eq!(s.v.decision_level(), 0);
+        assert!(s.v.trail.is_empty());
+        assert_eq!(phases(&s), before);
+
+        // all false: x0 false forces x1 false, x2 false forces x3 true
+        let model = s.try_lucky_assignment(false).unwrap();
+        assert_eq!(model, vec![lbool::FALSE, lbool::FALSE, lbool::FALSE, lbool::TRUE]);
+
+        // x0 true forces both x1 and !x1
+        s.add_clause_reuse(&mut vec![!x[0], !x[1]]);
+        s.add_clause_reuse(&mut vec![!x[0], x[1]]);
+        assert_eq!(s.try_lucky_assignment(true), None);
+        assert!(s.get_model().is_empty());
+        assert!(s.v.trail.is_empty());
+        assert_eq!(s.solve_limited(&[]), lbool::TRUE);
+        assert_eq!(s.value_lit(x[0]), lbool::FALSE);
+    }
 
-/// Generate a random double:
-fn drand(seed: &mut f64) -> f64 {
-    *seed *= 1389796.0;
-    let q = (*seed / 2147483647.0) as i32;
-    *seed -= q as f64 * 2147483647.0;
-    return *seed / 2147483647.0;
-}
+    /// the printed deltas are the work done since the snapshot
+    #[test]
+    fn test_stats_delta() {
+        use interface::SolverInterface;
+        use lbool;
+        let mut s = Solver::default();
+        pigeonhole(&mut s, 6);
+        s.set_conflict_budget(50);
+        assert_eq!(s.solve_limited(&[]), lbool::UNDEF);
+        let since = s.stats();
+        s.budget_off();
+        assert_eq!(s.solve_limited(&[]), lbool::FALSE);
+        let now = s.stats();
+        let print = |s: &Solver, since| {
+            let mut out = vec![];
+            s.write_stats_delta(since, &mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        let out = print(&s, &since);
+        let field = |name: &str| -> u64 {
+            let line = out.lines().find(|l| l.starts_with(name)).unwrap();
+            line.split(':').nth(1).unwrap().split_whitespace().next().unwrap().parse().unwrap()
+        };
+        assert_eq!(field("c solves"), 1);
+        assert_eq!(field("c conflicts"), now.conflicts - since.conflicts);
+        assert_eq!(field("c decisions"), now.decisions - since.decisions);
+        assert_eq!(field("c propagations"), now.propagations - since.propagations);
+        assert_eq!(field("c conflict literals"), now.tot_literals - since.tot_literals);
+
+        let out = print(&s, &now);
+        for line in out.lines() {
+            assert!(line.split(':').nth(1).unwrap().trim().starts_with('0'), "{}", line);
+        }
+    }
 
-/// Generate a random integer:
-fn irand(seed: &mut f64, size: i32) -> i32 {
-    (drand(seed) * size as f64) as i32
+    #[test]
+    fn test_extract_mus() {
+        use interface::SolverInterface;
+        use lbool;
+        let mut s = Solver::default();
+        let sel: Vec<Lit> = (0..4).map(|_| Lit::new(s.new_var_default(), true)).collect();
+        let x = Lit::new(s.new_var_default(), true);
+        // `sel[1]` and `sel[3]` conflict, `sel[0]` and `sel[2]` are harmless
+        s.add_clause_reuse(&mut vec![!sel[1], x]);
+        s.add_clause_reuse(&mut vec![!sel[3], !x]);
+        s.add_clause_reuse(&mut vec![!sel[0], !sel[2], x]);
+        assert_eq!(s.extract_mus(&sel), Some(vec![sel[1], sel[3]]));
+        assert_eq!(s.extract_mus(&sel[..3]), None);
+        assert_eq!(s.solve_limited(&[]), lbool::TRUE);
+        s.add_clause_reuse(&mut vec![x]);
+        s.add_clause_reuse(&mut vec![!x]);
+        assert_eq!(s.extract_mus(&sel), Some(vec![]));
+    }
+
+    /// out of budget before the first core: nothing is known about `soft`
+    #[test]
+    fn test_extract_mus_budget() {
+        use interface::SolverInterface;
+        let mut s = Solver::default();
+        let sel: Vec<Lit> = (0..2).map(|_| Lit::new(s.new_var_default(), true)).collect();
+        pigeonhole_guarded(&mut s, 7, &[!sel[0]]);
+        s.set_conflict_budget(10);
+        assert_eq!(s.extract_mus(&sel), None);
+        s.budget_off();
+        assert_eq!(s.extract_mus(&sel), Some(vec![sel[0]]));
+    }
 }
@@ -0,0 +1,360 @@
+/*****************************************************************************************[lrat.rs]
+Copyright (c) 2006,      Niklas Een, Niklas Sorensson (MiniSat)
+Copyright (c) 2007-2010, Niklas Sorensson (MiniSat)
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or
+substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+**************************************************************************************************/
+
+//! LRAT proofs, in the binary format.
+//!
+//! Each clause has an ID. Original clauses are numbered from 1 in the order they
+//! are given to `add_clause`, and each derived clause is written with the IDs of
+//! the clauses (the "hints") that imply it by unit propagation, in the order
+//! in which they become unit. This lets a checker verify the proof without
+//! searching for the propagations itself, unlike with DRAT.
+//!
+//! A line is either `a <id> <lits> 0 <hints> 0` (addition) or `d <ids> 0`
+//! (deletion), where each number `x` is written as the variable-length
+//! encoding of `2|x| + (x < 0)`, 7 bits at a time, least significant first.
+
+use std::collections::HashMap;
+use std::mem;
+use {lbool, Lit, Var};
+use clause::{CRef, ClauseAllocator};
+use super::Solver;
+
+const ADD: u64 = u64::MAX;
+const DELETE: u64 = u64::MAX - 1;
+
+/// An LRAT proof being written, with the IDs of the current clauses.
+///
+/// Clauses are numbered in the order in which they are added or derived, and
+/// renumbered when the proof is written, so that the original clauses come first.
+#[derive(Debug, Clone)]
+pub(super) struct Lrat {
+    /// Lines, as `ADD id lits 0 hints 0` or `DELETE id 0` (literals are encoded).
+    lines: Vec<u64>,
+    next_id: u64,
+    /// ID of each original clause, in order.
+    inputs: Vec<u64>,
+    /// ID of each clause of the allocator.
+    ids: HashMap<CRef, u64>,
+    /// ID of the unit clause of each variable fixed at level 0 (0 if none yet).
+    unit_ids: Vec<u64>,
+    /// Length of the prefix of the trail whose unit clauses were derived.
+    units_head: usize,
+    /// Variables already explained while computing hints.
+    seen: Vec<bool>,
+}
+
+impl Lrat {
+    pub(super) fn new() -> Self {
+        Lrat {
+            lines: Vec::new(),
+            next_id: 1,
+            inputs: Vec::new(),
+            ids: HashMap::new(),
+            unit_ids: Vec::new(),
+            units_head: 0,
+            seen: Vec::new(),
+        }
+    }
+
+    fn fresh_id(&mut self) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    /// Take the ID of the next original clause.
+    pub(super) fn new_input(&mut self) -> u64 {
+        let id = self.fresh_id();
+        self.inputs.push(id);
+        id
+    }
+
+    /// Add the clause `lits`, implied by the clauses `hints`, and return its ID.
+    pub(super) fn add(&mut self, lits: &[Lit], hints: &[u64]) -> u64 {
+        let id = self.fresh_id();
+        self.lines.push(ADD);
+        self.lines.push(id);
+        for &lit in lits {
+            self.lines.push(2 * (lit.var().idx() as u64 + 1) + if lit.sign() { 0 } else { 1 });
+        }
+        self.lines.push(0);
+        self.lines.extend_from_slice(hints);
+        self.lines.push(0);
+        id
+    }
+
+    pub(super) fn delete_id(&mut self, id: u64) {
+        self.lines.push(DELETE);
+        self.lines.push(id);
+        self.lines.push(0);
+    }
+
+    /// The proof in the binary format, where the `i`-th original clause has ID `i`,
+    /// and the derived clauses come next.
+    pub(super) fn to_bytes(&self) -> Vec<u8> {
+        // new ID of each clause
+        let mut ids = vec![0; self.next_id as usize];
+        for (i, &id) in self.inputs.iter().enumerate() {
+            ids[id as usize] = i as u64 + 1;
+        }
+        let derived = ids.iter_mut().skip(1).filter(|id| **id == 0);
+        for (next, id) in (self.inputs.len() as u64 + 1..).zip(derived) {
+            *id = next;
+        }
+
+        let mut out = Vec::with_capacity(self.lines.len() * 2);
+        let mut i = 0;
+        while i < self.lines.len() {
+            let kind = self.lines[i];
+            out.push(if kind == ADD { b'a' } else { b'd' });
+            write_num(&mut out, 2 * ids[self.lines[i + 1] as usize]);
+            i += 2;
+            if kind == ADD {
+                // literals
+                while self.lines[i] != 0 {
+                    write_num(&mut out, self.lines[i]);
+                    i += 1;
+                }
+                out.push(0);
+                i += 1;
+                // hints
+                while self.lines[i] != 0 {
+                    write_num(&mut out, 2 * ids[self.lines[i] as usize]);
+                    i += 1;
+                }
+            }
+            out.push(0);
+            i += 1;
+        }
+        out
+    }
+
+    /// Delete clause `cr`, if it has an ID.
+    pub(super) fn delete(&mut self, cr: CRef) {
+        if let Some(id) = self.ids.remove(&cr) {
+            self.delete_id(id);
+        }
+    }
+
+    pub(super) fn set_id(&mut self, cr: CRef, id: u64) {
+        self.ids.insert(cr, id);
+    }
+
+    pub(super) fn id(&self, cr: CRef) -> u64 {
+        *self.ids.get(&cr).expect("LRAT: clause without an ID")
+    }
+
+    pub(super) fn set_unit(&mut self, v: Var, id: u64) {
+        let i = v.idx() as usize;
+        if self.unit_ids.len() <= i {
+            self.unit_ids.resize(i + 1, 0);
+        }
+        self.unit_ids[i] = id;
+    }
+
+    pub(super) fn unit_id(&self, v: Var) -> u64 {
+        let id = self.unit_ids.get(v.idx() as usize).cloned().unwrap_or(0);
+        debug_assert!(id != 0, "LRAT: no unit clause for {:?}", v);
+        id
+    }
+
+    /// Replace clause `cr`, now `lits`, by its version without the literals
+    /// whose negation is given by the unit clauses `units`.
+    pub(super) fn strengthen(&mut self, cr: CRef, lits: &[Lit], mut units: Vec<u64>) {
+        let old = self.id(cr);
+        units.push(old);
+        let id = self.add(lits, &units);
+        self.delete_id(old);
+        self.ids.insert(cr, id);
+    }
+
+    /// Move the IDs of the live clauses of `ca` to their copy in `to`.
+    pub(super) fn reloc(&mut self, ca: &mut ClauseAllocator, to: &mut ClauseAllocator) {
+        let ids = mem::take(&mut self.ids);
+        for (mut cr, id) in ids {
+            if ca.get_ref(cr).mark() != 1 {
+                ca.reloc(&mut cr, to);
+                self.ids.insert(cr, id);
+            }
+        }
+    }
+}
+
+impl Solver {
+    /// Produce an LRAT proof (see `lrat_proof`), where each derived clause comes
+    /// with the IDs of the clauses it is derived from.
+    ///
+    /// This must be called before any clause is added: the original clauses
+    /// get the IDs `1, 2, …` in the order in which they are added, so the proof
+    /// is checked against the sequence of clauses given to `add_clause`.
+    /// It is not supported by `preprocess`, `add_learnt_clause`, or external
+    /// propagators.
+    pub fn set_lrat_proof(&mut self, b: bool) {
+        assert!(self.clauses.is_empty() && self.v.trail.is_empty() && self.ok,
+            "set_lrat_proof must be called before adding clauses");
+        assert!(!b || self.ext_propagator.0.is_none(),
+            "LRAT proofs are not supported with an external propagator");
+        self.lrat = if b { Some(Lrat::new()) } else { None };
+    }
+
+    /// The LRAT proof produced so far, in the binary format (empty if
+    /// `set_lrat_proof` was not called). Once the solver is unsatisfiable
+    /// (without assumptions), it ends with the empty clause.
+    pub fn lrat_proof(&self) -> Vec<u8> {
+        self.lrat.as_ref().map_or(vec![], |lrat| lrat.to_bytes())
+    }
+
+    /// For `add_clause`: take the ID of the next original clause, and find the
+    /// unit clauses of its literals false at level 0.
+    pub(super) fn lrat_input(&mut self, lits: &[Lit]) -> Option<(u64, Vec<u64>)> {
+        let v = &self.v;
+        self.lrat.as_mut().map(|lrat| {
+            let units = lits.iter()
+                .filter(|&&lit| v.value_lit(lit) == lbool::FALSE)
+                .map(|&lit| lrat.unit_id(lit.var()))
+                .collect();
+            (lrat.new_input(), units)
+        })
+    }
+
+    /// The ID of `lits`, the simplified version of an original clause (see `lrat_input`).
+    pub(super) fn lrat_simplified_input(&mut self, input: Option<(u64, Vec<u64>)>, lits: &[Lit])
+        -> Option<u64>
+    {
+        let lrat = self.lrat.as_mut()?;
+        let (id, mut units) = input?;
+        if units.is_empty() {
+            return Some(id);
+        }
+        // remove the false literals; the empty clause, if any, is the end of the proof
+        units.sort_unstable();
+        units.dedup();
+        units.push(id);
+        let new_id = lrat.add(lits, &units);
+        if !lits.is_empty() {
+            lrat.delete_id(id);
+        }
+        Some(new_id)
+    }
+
+    /// Record that `id` is the ID of `lits`, stored in `cr` (or fixed at level 0 if it is unit).
+    pub(super) fn lrat_bind(&mut self, lits: &[Lit], cr: CRef, id: Option<u64>) {
+        if let (Some(lrat), Some(id)) = (self.lrat.as_mut(), id) {
+            if lits.len() == 1 {
+                lrat.set_unit(lits[0].var(), id);
+            } else if lits.len() > 1 {
+                lrat.set_id(cr, id);
+            }
+        }
+    }
+
+    /// Add the learnt clause `lits`, obtained by analyzing the conflict `confl`,
+    /// and return its ID.
+    ///
+    /// The hints are the reasons of the literals implied by the negation of
+    /// `lits`, in the order of the implication graph: the reasons are visited
+    /// depth first from the conflict, stopping at the variables of `lits` and at
+    /// level 0 (where the unit clauses are used instead).
+    pub(super) fn lrat_learnt(&mut self, confl: CRef, lits: &[Lit]) -> Option<u64> {
+        let lrat = self.lrat.as_mut()?;
+        let n_vars = self.next_var.idx() as usize;
+        if lrat.seen.len() < n_vars {
+            lrat.seen.resize(n_vars, false);
+        }
+        let mut touched: Vec<Var> = vec![];
+        for &lit in lits {
+            lrat.seen[lit.var().idx() as usize] = true;
+            touched.push(lit.var());
+        }
+        let mut hints = vec![];
+        // `(v, true)` means that the reason of `v` is emitted after the other ones
+        let mut stack: Vec<(Var, bool)> = self.ca.get_ref(confl).iter()
+            .map(|lit| (lit.var(), false)).collect();
+        while let Some((v, done)) = stack.pop() {
+            if done {
+                hints.push(lrat.id(self.v.reason(v)));
+                continue;
+            } else if lrat.seen[v.idx() as usize] {
+                continue;
+            }
+            lrat.seen[v.idx() as usize] = true;
+            touched.push(v);
+            if self.v.level(v) == 0 {
+                hints.push(lrat.unit_id(v));
+                continue;
+            }
+            let reason = self.v.reason(v);
+            debug_assert!(reason != CRef::UNDEF, "LRAT: learnt clause is not a cut");
+            stack.push((v, true));
+            for &lit in self.ca.get_ref(reason).iter() {
+                if !lrat.seen[lit.var().idx() as usize] {
+                    stack.push((lit.var(), false));
+                }
+            }
+        }
+        hints.push(lrat.id(confl));
+        for v in touched {
+            lrat.seen[v.idx() as usize] = false;
+        }
+        Some(lrat.add(lits, &hints))
+    }
+
+    /// Derive the unit clauses of the literals fixed at level 0 since the last
+    /// call, and the empty clause if `confl` is a conflict at level 0.
+    pub(super) fn lrat_sync_units(&mut self, confl: CRef) {
+        debug_assert_eq!(self.v.decision_level(), 0);
+        let lrat = match self.lrat {
+            Some(ref mut lrat) => lrat,
+            None => return,
+        };
+        while lrat.units_head < self.v.trail.len() {
+            let p = self.v.trail[lrat.units_head];
+            lrat.units_head += 1;
+            if lrat.unit_ids.get(p.var().idx() as usize).cloned().unwrap_or(0) != 0 {
+                continue;
+            }
+            let reason = self.v.reason(p.var());
+            debug_assert!(reason != CRef::UNDEF, "LRAT: unit without a reason");
+            let mut hints: Vec<u64> = self.ca.get_ref(reason).iter()
+                .filter(|lit| lit.var() != p.var())
+                .map(|lit| lrat.unit_id(lit.var()))
+                .collect();
+            hints.push(lrat.id(reason));
+            let id = lrat.add(&[p], &hints);
+            lrat.set_unit(p.var(), id);
+        }
+        if confl != CRef::UNDEF {
+            let mut hints: Vec<u64> = self.ca.get_ref(confl).iter()
+                .map(|lit| lrat.unit_id(lit.var()))
+                .collect();
+            hints.push(lrat.id(confl));
+            lrat.add(&[], &hints);
+        }
+    }
+}
+
+/// Write `x` 7 bits at a time, least significant first.
+fn write_num(out: &mut Vec<u8>, mut x: u64) {
+    while x > 0x7f {
+        out.push((x & 0x7f) as u8 | 0x80);
+        x >>= 7;
+    }
+    out.push(x as u8);
+}
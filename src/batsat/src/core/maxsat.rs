@@ -0,0 +1,128 @@
+/*****************************************************************************************[maxsat.rs]
+Copyright (c) 2006,      Niklas Een, Niklas Sorensson (MiniSat)
+Copyright (c) 2007-2010, Niklas Sorensson (MiniSat)
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or
+substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+**************************************************************************************************/
+
+//! Weighted MaxSAT by linear SAT-UNSAT search.
+//!
+//! The cost of a model is the total weight of the soft literals it falsifies.
+//! Each time a model is found, the next one is required to be strictly cheaper,
+//! until the solver proves that there is none. The total weight of falsified
+//! soft literals is encoded with a generalized totalizer, whose outputs are
+//! "the cost is at least `k`" for each reachable cost `k`.
+
+use std::cmp;
+use std::collections::BTreeMap;
+use {lbool, Lit};
+use interface::SolverInterface;
+use super::Solver;
+
+impl Solver {
+    /// Find a model of the clauses that minimizes the total weight of the
+    /// literals of `soft` it makes false, and return this weight with the model.
+    ///
+    /// The constraints added during the search are guarded by a fresh selector,
+    /// disabled at the end, so they do not restrict later calls. Returns
+    /// `(u64::MAX, vec![])` if the clauses are unsatisfiable. If the budget runs
+    /// out, the best model found so far is returned, which may not be optimal.
+    pub fn maxsat_linear(&mut self, soft: &[(Lit, u64)]) -> (u64, Vec<lbool>) {
+        let num_vars = self.num_vars() as usize;
+        if self.solve_limited(&[]) != lbool::TRUE {
+            return (u64::MAX, vec![]);
+        }
+        let mut cost = self.model_cost(soft);
+        let mut best = self.model[..num_vars].to_vec();
+        if cost == 0 {
+            return (cost, best);
+        }
+
+        let act = Lit::new(self.new_var(lbool::UNDEF, false), true);
+        let inputs: Vec<(Lit, u64)> = soft.iter()
+            .filter(|&&(_, w)| w > 0)
+            .map(|&(lit, w)| (!lit, cmp::min(w, cost)))
+            .collect();
+        let outputs = self.totalizer(&inputs, cost);
+        let mut bounded = cost + 1; // costs `>= bounded` are already forbidden
+        loop {
+            // forbid costs in `[cost, bounded)`
+            for (_, &out) in outputs.range(cost..bounded) {
+                self.add_clause_reuse(&mut vec![!act, !out]);
+            }
+            bounded = cost;
+            if self.solve_limited(&[act]) != lbool::TRUE {
+                break;
+            }
+            cost = self.model_cost(soft);
+            best.clear();
+            best.extend_from_slice(&self.model[..num_vars]);
+            if cost == 0 {
+                break;
+            }
+        }
+        self.add_clause_reuse(&mut vec![!act]);
+        (cost, best)
+    }
+
+    /// Weight of the literals of `soft` that are false in the model.
+    fn model_cost(&self, soft: &[(Lit, u64)]) -> u64 {
+        soft.iter()
+            .filter(|&&(lit, _)| self.value_lit(lit) != lbool::TRUE)
+            .fold(0, |acc, &(_, w)| acc.saturating_add(w))
+    }
+
+    /// Encode the sum of the weights of the true literals of `inputs`, capped at `cap`.
+    ///
+    /// The result maps each reachable sum `k` to a fresh literal implied by
+    /// "the sum is at least `k`" (the converse is not enforced).
+    fn totalizer(&mut self, inputs: &[(Lit, u64)], cap: u64) -> BTreeMap<u64, Lit> {
+        match inputs.len() {
+            0 => BTreeMap::new(),
+            1 => {
+                let mut res = BTreeMap::new();
+                res.insert(inputs[0].1, inputs[0].0);
+                res
+            }
+            n => {
+                let left = self.totalizer(&inputs[..n / 2], cap);
+                let right = self.totalizer(&inputs[n / 2..], cap);
+                let mut res = BTreeMap::new();
+                {
+                    let mut out = |s: &mut Solver, k: u64| {
+                        *res.entry(cmp::min(k, cap))
+                            .or_insert_with(|| Lit::new(s.new_var(lbool::UNDEF, false), true))
+                    };
+                    for (&a, &la) in left.iter() {
+                        let o = out(self, a);
+                        self.add_clause_reuse(&mut vec![!la, o]);
+                    }
+                    for (&b, &lb) in right.iter() {
+                        let o = out(self, b);
+                        self.add_clause_reuse(&mut vec![!lb, o]);
+                    }
+                    for (&a, &la) in left.iter() {
+                        for (&b, &lb) in right.iter() {
+                            let o = out(self, a.saturating_add(b));
+                            self.add_clause_reuse(&mut vec![!la, !lb, o]);
+                        }
+                    }
+                }
+                res
+            }
+        }
+    }
+}
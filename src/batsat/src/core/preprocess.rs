@@ -0,0 +1,870 @@
+/*****************************************************************************************[preprocess.rs]
+Copyright (c) 2006,      Niklas Een, Niklas Sorensson (MiniSat)
+Copyright (c) 2007-2010, Niklas Sorensson (MiniSat)
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or
+substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+**************************************************************************************************/
+
+//! Preprocessing of the clause database at level 0.
+//!
+//! All the passes work on original clauses. Subsumption, probing and vivification
+//! produce an equivalent formula; variable elimination and substitution of
+//! equivalent literals only preserve satisfiability, and the removed variables
+//! are recovered in the model using a reconstruction stack, like in SatELite.
+
+use std::cmp;
+use {lbool, Lit, Var};
+use clause::CRef;
+use interface::SolverInterface;
+use super::{Solver, ClauseSet, GLUE_MARK};
+
+/// Configuration of `Solver::preprocess`.
+///
+/// `eliminate` and `substitute_equiv` remove variables from the formula. Removed
+/// variables must not be used in clauses or assumptions afterwards, unless they are
+/// listed in `frozen`: adding a clause that contains one panics.
+#[derive(Debug, Clone)]
+pub struct Preprocessor {
+    /// Bounded variable elimination by clause distribution.
+    pub eliminate: bool,
+    /// Subsumption and self-subsuming resolution between original clauses.
+    pub subsume: bool,
+    /// Failed literal probing.
+    pub probe: bool,
+    /// Substitution of literals that are equivalent through binary clauses.
+    pub substitute_equiv: bool,
+    /// Vivification: shorten clauses by propagating the negation of their literals.
+    pub vivify: bool,
+    /// Maximum number of rounds of the whole pipeline (it stops earlier at fixpoint).
+    pub max_rounds: u32,
+    /// Number of propagations allowed for probing and vivification, in each round.
+    pub prop_budget: u64,
+    /// Variables occurring in more clauses than this are not eliminated,
+    /// and not used to look for subsumed clauses.
+    pub occ_limit: usize,
+    /// Variable elimination does not produce resolvents longer than this.
+    pub resolvent_limit: usize,
+    /// Variables that must not be eliminated or substituted, for instance because
+    /// they will appear in assumptions or in clauses added later.
+    pub frozen: Vec<Var>,
+}
+
+impl Default for Preprocessor {
+    fn default() -> Self {
+        Preprocessor {
+            eliminate: true,
+            subsume: true,
+            probe: true,
+            substitute_equiv: true,
+            vivify: true,
+            max_rounds: 3,
+            prop_budget: 10_000_000,
+            occ_limit: 1000,
+            resolvent_limit: 20,
+            frozen: vec![],
+        }
+    }
+}
+
+/// What `Solver::preprocess` did.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PreprocessReport {
+    /// Number of rounds of the pipeline that were run.
+    pub rounds: u32,
+    /// Number of unassigned, non-eliminated variables before preprocessing.
+    pub vars_before: u32,
+    /// Number of unassigned, non-eliminated variables after preprocessing.
+    pub vars_after: u32,
+    /// Number of original clauses before preprocessing.
+    pub clauses_before: u32,
+    /// Number of original clauses after preprocessing.
+    pub clauses_after: u32,
+    /// Number of variables fixed at level 0.
+    pub fixed_vars: u32,
+    /// Number of variables removed by elimination.
+    pub eliminated_vars: u32,
+    /// Number of variables replaced by an equivalent literal.
+    pub substituted_vars: u32,
+    /// `false` if the formula was found unsatisfiable.
+    pub ok: bool,
+}
+
+impl Solver {
+    /// Simplify the original clauses at level 0 using the passes enabled in `cfg`,
+    /// until fixpoint, `cfg.max_rounds` rounds, or the solver's budget is exhausted.
+    pub fn preprocess(&mut self, cfg: &Preprocessor) -> PreprocessReport {
+        assert_eq!(self.v.decision_level(), 0);
+        assert!(self.lrat.is_none(), "preprocess does not support LRAT proofs");
+        let mut report = PreprocessReport {
+            vars_before: self.num_active_vars(),
+            clauses_before: self.v.num_clauses as u32,
+            ..PreprocessReport::default()
+        };
+        let assigns_before = self.v.num_assigns();
+
+        let mut frozen = vec![false; self.num_vars() as usize];
+        for &v in &cfg.frozen {
+            frozen[v.idx() as usize] = true;
+        }
+
+        if cfg.eliminate || cfg.substitute_equiv {
+            // learnt clauses could mention removed variables
+            self.pp_drop_learnts();
+        }
+
+        while self.ok && report.rounds < cfg.max_rounds && self.within_budget() {
+            report.rounds += 1;
+            let fingerprint =
+                (self.v.num_assigns(), self.v.num_clauses, self.v.clauses_literals);
+            debug!("preprocess.round {}", report.rounds);
+
+            let _ = self.pp_clean()
+                && (!cfg.subsume || self.pp_subsume(cfg))
+                && (!cfg.probe || self.pp_probe(cfg))
+                && (!cfg.substitute_equiv || self.pp_substitute(&frozen, &mut report))
+                && (!cfg.eliminate || self.pp_eliminate(cfg, &frozen, &mut report))
+                && (!cfg.vivify || self.pp_vivify(cfg));
+            self.pp_purge();
+
+            if fingerprint == (self.v.num_assigns(), self.v.num_clauses, self.v.clauses_literals) {
+                break;
+            }
+        }
+        if self.ok && self.pp_clean() {
+            self.rebuild_order_heap();
+        }
+        // clauses were shortened or replaced by resolvents
+        self.rehash_clauses();
+        self.check_garbage();
+
+        report.vars_after = self.num_active_vars();
+        report.clauses_after = self.v.num_clauses as u32;
+        report.fixed_vars = self.v.num_assigns() - assigns_before;
+        report.ok = self.ok;
+        report
+    }
+
+    /// Has `v` been removed from the formula by `preprocess`?
+    pub fn is_eliminated(&self, v: Var) -> bool {
+        self.eliminated[v]
+    }
+
+    /// Number of variables that are neither assigned at level 0 nor eliminated
+    fn num_active_vars(&self) -> u32 {
+        self.vars()
+            .filter(|&v| self.v.value(v) == lbool::UNDEF && !self.eliminated[v])
+            .count() as u32
+    }
+
+    /// Can `v` be removed from the formula?
+    fn pp_removable(&self, v: Var, frozen: &[bool]) -> bool {
+        !frozen[v.idx() as usize] && self.decision[v] && !self.eliminated[v]
+            && self.v.value(v) == lbool::UNDEF
+    }
+
+    /// Extend `self.model` to eliminated variables, using the reconstruction stack.
+    ///
+    /// Each entry is a clause whose first literal is the pivot: if all the other
+    /// literals are false, the pivot is made true.
+    pub(super) fn extend_model(&mut self) {
+        for v in self.vars() {
+            if self.eliminated[v] {
+                self.model[v.idx() as usize] = lbool::FALSE;
+            }
+        }
+        for c in self.elim_clauses.iter().rev() {
+            let model = &self.model;
+            let value = |lit: Lit| model[lit.var().idx() as usize] ^ !lit.sign();
+            if c[1..].iter().all(|&lit| value(lit) == lbool::FALSE) {
+                self.model[c[0].var().idx() as usize] = lbool::new(c[0].sign());
+            }
+        }
+    }
+
+    /// Propagate at level 0 and remove satisfied clauses and false literals.
+    fn pp_clean(&mut self) -> bool {
+        if !self.ok || self.propagate() != CRef::UNDEF {
+            self.ok = false;
+            return false;
+        }
+        self.remove_satisfied(ClauseSet::Learnt);
+        self.remove_satisfied(ClauseSet::Original);
+        true
+    }
+
+    /// Remove all learnt clauses.
+    fn pp_drop_learnts(&mut self) {
+        for i in 0..self.learnts.len() {
+            let cr = self.learnts[i];
+            if self.ca.get_ref(cr).mark() == 1 {
+                continue;
+            }
+            if self.produce_proof { self.proof.delete_clause(&self.ca.get_ref(cr)); }
+            self.v.remove_clause(&mut self.ca, &mut self.watches_data, cr);
+        }
+        self.learnts.clear();
+    }
+
+    /// Forget about removed clauses in `clauses` and `learnts`.
+    fn pp_purge(&mut self) {
+        let ca = &self.ca;
+        self.clauses.retain(|&cr| ca.get_ref(cr).mark() != 1);
+        self.learnts.retain(|&cr| ca.get_ref(cr).mark() != 1);
+    }
+
+    /// Make `lit` true at level 0 and propagate. Returns `false` on conflict.
+    fn pp_add_unit(&mut self, lit: Lit) -> bool {
+        let value = self.v.value_lit(lit);
+        if value == lbool::FALSE {
+            self.ok = false;
+        } else if value == lbool::UNDEF {
+            if self.produce_proof { self.proof.create_clause(&vec![lit]); }
+            self.v.unchecked_enqueue(lit, CRef::UNDEF);
+            if self.propagate() != CRef::UNDEF {
+                self.ok = false;
+            }
+        }
+        self.ok
+    }
+
+    /// Replace the literals of the attached original clause `cr` with `lits`,
+    /// which must be implied by the formula. Returns `false` on conflict.
+    fn pp_replace_clause(&mut self, cr: CRef, lits: &[Lit]) -> bool {
+        self.v.detach_clause(&mut self.ca, &mut self.watches_data, cr, true);
+        self.pp_rewrite_detached(cr, lits)
+    }
+
+    /// Replace the literals of the detached original clause `cr` with `lits`,
+    /// then attach it again (or remove it, if it became unit or satisfied).
+    fn pp_rewrite_detached(&mut self, cr: CRef, lits: &[Lit]) -> bool {
+        if self.use_simp {
+            // occurrences of `cr` are added back once it is rewritten
+            for &lit in self.ca.get_ref(cr).iter() {
+                self.occurs[lit.var()].retain(|x| *x != cr);
+            }
+        }
+        let mut new_lits: Vec<Lit> = Vec::with_capacity(lits.len());
+        let mut satisfied = false;
+        for &lit in lits {
+            let value = self.v.value_lit(lit);
+            if value == lbool::TRUE {
+                satisfied = true;
+            } else if value == lbool::UNDEF && !new_lits.contains(&lit) {
+                new_lits.push(lit);
+            }
+        }
+
+        let changed = {
+            let c = self.ca.get_ref(cr);
+            c.size() as usize != new_lits.len() || c.iter().zip(new_lits.iter()).any(|(a, b)| a != b)
+        };
+        if self.produce_proof && changed && !satisfied {
+            self.proof.create_clause(&new_lits);
+        }
+        if self.produce_proof && changed {
+            self.proof.delete_clause(&self.ca.get_ref(cr));
+        }
+
+        if satisfied || new_lits.len() < 2 {
+            // free the clause, which is not watched anymore
+            {
+                let c = self.ca.get_ref(cr);
+                if self.v.locked(&self.ca, c) {
+                    self.v.vardata[c[0].var()].reason = CRef::UNDEF;
+                }
+            }
+            if self.ca.get_ref(cr).mark() == GLUE_MARK {
+                self.v.glue_clauses -= 1;
+            }
+            self.ca.get_mut(cr).set_mark(1);
+            self.ca.free(cr);
+            if satisfied {
+                true
+            } else if new_lits.len() == 1 {
+                self.pp_add_unit(new_lits[0])
+            } else {
+                self.ok = false;
+                false
+            }
+        } else {
+            let shaved = {
+                let mut c = self.ca.get_mut(cr);
+                debug_assert!(new_lits.len() <= c.size() as usize);
+                let orig_size = c.size();
+                for (i, &lit) in new_lits.iter().enumerate() {
+                    c[i as u32] = lit;
+                }
+                c.shrink(new_lits.len() as u32);
+                orig_size - new_lits.len() as u32
+            };
+            self.ca.free_amount(shaved);
+            self.attach_clause(cr);
+            self.add_occurrences(cr);
+            true
+        }
+    }
+
+    /// Remove the original clause `cr`, which is redundant.
+    fn pp_remove_clause(&mut self, cr: CRef) {
+        if self.produce_proof { self.proof.delete_clause(&self.ca.get_ref(cr)); }
+        self.smudge_occurrences(cr);
+        self.v.remove_clause(&mut self.ca, &mut self.watches_data, cr);
+    }
+
+    /// Occurrence lists of original clauses, indexed by literal.
+    fn pp_lit_occs(&self) -> Vec<Vec<CRef>> {
+        let mut occs = vec![vec![]; 2 * self.num_vars() as usize];
+        for &cr in &self.clauses {
+            let c = self.ca.get_ref(cr);
+            if c.mark() != 1 {
+                for &lit in c.iter() {
+                    occs[lit.idx() as usize].push(cr);
+                }
+            }
+        }
+        occs
+    }
+
+    /// Occurrence lists of original clauses, indexed by variable.
+    fn pp_var_occs(&mut self) -> Vec<Vec<CRef>> {
+        if self.use_simp {
+            return self.vars().map(|v| self.occurrences(v).to_vec()).collect();
+        }
+        let mut occs = vec![vec![]; self.num_vars() as usize];
+        for &cr in &self.clauses {
+            let c = self.ca.get_ref(cr);
+            if c.mark() != 1 {
+                for &lit in c.iter() {
+                    occs[lit.var().idx() as usize].push(cr);
+                }
+            }
+        }
+        occs
+    }
+
+    /// Subsumption and self-subsuming resolution.
+    fn pp_subsume(&mut self, cfg: &Preprocessor) -> bool {
+        let occs = self.pp_var_occs();
+        let mut queue = self.clauses.clone();
+        {
+            let ca = &self.ca;
+            queue.sort_by_key(|&cr| cmp::Reverse(ca.get_ref(cr).size()));
+        }
+        let mut marks = vec![false; 2 * self.num_vars() as usize];
+        let mut c_lits = vec![];
+        let mut d_lits = vec![];
+
+        while let Some(c_ref) = queue.pop() {
+            if self.ca.get_ref(c_ref).mark() == 1 {
+                continue;
+            }
+            c_lits.clear();
+            c_lits.extend(self.ca.get_ref(c_ref).iter().cloned());
+            let best = c_lits.iter().map(|l| l.var().idx() as usize)
+                .min_by_key(|&v| occs[v].len()).expect("empty clause");
+            if occs[best].len() > cfg.occ_limit {
+                continue;
+            }
+
+            for &d_ref in &occs[best] {
+                if d_ref == c_ref {
+                    continue;
+                }
+                d_lits.clear();
+                {
+                    let d = self.ca.get_ref(d_ref);
+                    if d.mark() == 1 || d.size() < c_lits.len() as u32 {
+                        continue;
+                    }
+                    d_lits.extend(d.iter().cloned());
+                }
+                match subsumes(&c_lits, &d_lits, &mut marks) {
+                    None => (),
+                    Some(l) if l == Lit::UNDEF => {
+                        debug!("preprocess.subsumed [{:?}] by [{:?}]", d_lits, c_lits);
+                        self.pp_remove_clause(d_ref);
+                    },
+                    Some(l) => {
+                        // remove `!l` from `d`
+                        d_lits.retain(|&x| x != !l);
+                        if !self.pp_replace_clause(d_ref, &d_lits) {
+                            return false;
+                        }
+                        if self.ca.get_ref(d_ref).mark() != 1 {
+                            queue.push(d_ref);
+                        }
+                    },
+                }
+            }
+        }
+        true
+    }
+
+    /// Propagate `lit` at level 1, push the implied literals into `implied`,
+    /// and backtrack. Returns `true` if there was a conflict.
+    fn pp_probe_lit(&mut self, lit: Lit, implied: &mut Vec<Lit>) -> bool {
+        implied.clear();
+        self.new_decision_level();
+        self.v.unchecked_enqueue(lit, CRef::UNDEF);
+        let confl = self.propagate();
+        let start = self.v.trail_lim[0] as usize + 1;
+        implied.extend_from_slice(&self.v.trail[start..]);
+        self.cancel_until(0);
+        confl != CRef::UNDEF
+    }
+
+    /// Failed literal probing: if `l` propagates to a conflict, `!l` is true;
+    /// literals implied by both `l` and `!l` are true.
+    fn pp_probe(&mut self, cfg: &Preprocessor) -> bool {
+        let start_props = self.propagations;
+        let mut implied_pos = vec![];
+        let mut implied_neg = vec![];
+        let mut marks = vec![false; 2 * self.num_vars() as usize];
+
+        for v in self.vars() {
+            if self.propagations - start_props > cfg.prop_budget || !self.within_budget() {
+                break;
+            }
+            if self.v.value(v) != lbool::UNDEF || !self.decision[v] {
+                continue;
+            }
+            let pos = Lit::new(v, true);
+            if self.pp_probe_lit(pos, &mut implied_pos) {
+                debug!("preprocess.failed-lit {:?}", pos);
+                if !self.pp_add_unit(!pos) { return false; }
+                continue;
+            }
+            if self.pp_probe_lit(!pos, &mut implied_neg) {
+                debug!("preprocess.failed-lit {:?}", !pos);
+                if !self.pp_add_unit(pos) { return false; }
+                continue;
+            }
+
+            for &lit in &implied_pos {
+                marks[lit.idx() as usize] = true;
+            }
+            for &lit in &implied_neg {
+                if marks[lit.idx() as usize] && self.v.value_lit(lit) == lbool::UNDEF {
+                    debug!("preprocess.lifted {:?} (from {:?})", lit, v);
+                    if self.produce_proof {
+                        // `lit` is not RUP by itself, justify it by case split
+                        let c1 = vec![!pos, lit];
+                        let c2 = vec![pos, lit];
+                        self.proof.create_clause(&c1);
+                        self.proof.create_clause(&c2);
+                        if !self.pp_add_unit(lit) { return false; }
+                        self.proof.delete_clause(&c1);
+                        self.proof.delete_clause(&c2);
+                    } else if !self.pp_add_unit(lit) {
+                        return false;
+                    }
+                }
+            }
+            for &lit in &implied_pos {
+                marks[lit.idx() as usize] = false;
+            }
+        }
+        true
+    }
+
+    /// Find strongly connected components of the binary implication graph,
+    /// and replace each literal by the representative of its component.
+    fn pp_substitute(&mut self, frozen: &[bool], report: &mut PreprocessReport) -> bool {
+        let n_lits = 2 * self.num_vars() as usize;
+
+        // binary implication graph: `(a \/ b)` gives `!a -> b` and `!b -> a`
+        let mut succ: Vec<Vec<Lit>> = vec![vec![]; n_lits];
+        for &cr in &self.clauses {
+            let c = self.ca.get_ref(cr);
+            if c.mark() != 1 && c.size() == 2
+                && self.v.value_lit(c[0]) == lbool::UNDEF
+                && self.v.value_lit(c[1]) == lbool::UNDEF
+            {
+                succ[(!c[0]).idx() as usize].push(c[1]);
+                succ[(!c[1]).idx() as usize].push(c[0]);
+            }
+        }
+
+        let comps = tarjan_scc(&succ);
+
+        // choose representatives, consistently for `l` and `!l`
+        let mut repr: Vec<Lit> = (0..n_lits as u32).map(Lit::from_idx).collect();
+        let mut has_repr = vec![false; n_lits];
+        for comp in &comps {
+            if comp.len() < 2 || has_repr[comp[0].idx() as usize] {
+                continue;
+            }
+            let mut r = comp[0];
+            for &lit in comp {
+                if lit == !comp[0] {
+                    // `l <=> !l`
+                    self.ok = false;
+                    return false;
+                }
+                let better = if frozen[lit.var().idx() as usize] != frozen[r.var().idx() as usize] {
+                    frozen[lit.var().idx() as usize]
+                } else {
+                    lit < r
+                };
+                if better {
+                    r = lit;
+                }
+            }
+            for &lit in comp {
+                // only removable variables are substituted
+                if lit != r && self.pp_removable(lit.var(), frozen) {
+                    repr[lit.idx() as usize] = r;
+                    repr[(!lit).idx() as usize] = !r;
+                }
+                has_repr[lit.idx() as usize] = true;
+                has_repr[(!lit).idx() as usize] = true;
+            }
+        }
+
+        // rewrite clauses: first add the new clauses, then remove the tautologies,
+        // among which are the binary clauses that justify the substitution
+        let mut tautologies = vec![];
+        let mut lits = vec![];
+        for i in 0..self.clauses.len() {
+            let cr = self.clauses[i];
+            if self.ca.get_ref(cr).mark() == 1 {
+                continue;
+            }
+            lits.clear();
+            let mut changed = false;
+            for &lit in self.ca.get_ref(cr).iter() {
+                let r = repr[lit.idx() as usize];
+                changed |= r != lit;
+                lits.push(r);
+            }
+            if !changed {
+                continue;
+            }
+            lits.sort();
+            lits.dedup();
+            if lits.windows(2).any(|w| w[0] == !w[1]) {
+                tautologies.push(cr);
+            } else if !self.pp_replace_clause(cr, &lits) {
+                return false;
+            }
+        }
+        for cr in tautologies {
+            self.pp_remove_clause(cr);
+        }
+
+        for v in self.vars() {
+            let x = Lit::new(v, true);
+            let r = repr[x.idx() as usize];
+            if r != x {
+                debug!("preprocess.substitute {:?} := {:?}", x, r);
+                self.elim_clauses.push(vec![x, !r]);
+                self.elim_clauses.push(vec![!x, r]);
+                self.set_eliminated(v);
+                report.substituted_vars += 1;
+            }
+        }
+        true
+    }
+
+    /// Bounded variable elimination: replace the clauses containing `v` by
+    /// their resolvents on `v`, if there aren't more of them.
+    fn pp_eliminate(&mut self, cfg: &Preprocessor, frozen: &[bool], report: &mut PreprocessReport) -> bool {
+        let mut occs = self.pp_lit_occs();
+        let mut candidates: Vec<Var> = self.vars()
+            .filter(|&v| self.pp_removable(v, frozen))
+            .collect();
+        candidates.sort_by_key(|&v| {
+            let l = Lit::new(v, true);
+            occs[l.idx() as usize].len() + occs[(!l).idx() as usize].len()
+        });
+
+        let mut marks = vec![false; 2 * self.num_vars() as usize];
+        let mut resolvents: Vec<Vec<Lit>> = vec![];
+
+        for v in candidates {
+            if !self.within_budget() {
+                break;
+            }
+            if !self.pp_removable(v, frozen) {
+                continue;
+            }
+            let pos_lit = Lit::new(v, true);
+            let (pos, neg) = {
+                let ca = &self.ca;
+                let alive = |crs: &Vec<CRef>, lit: Lit| -> Vec<CRef> {
+                    crs.iter().cloned()
+                        .filter(|&cr| {
+                            let c = ca.get_ref(cr);
+                            c.mark() != 1 && c.iter().any(|&x| x == lit)
+                        })
+                        .collect()
+                };
+                (alive(&occs[pos_lit.idx() as usize], pos_lit),
+                 alive(&occs[(!pos_lit).idx() as usize], !pos_lit))
+            };
+            if pos.len() + neg.len() > cfg.occ_limit {
+                continue;
+            }
+
+            // compute the resolvents, giving up if there are too many or too long
+            resolvents.clear();
+            let mut too_costly = false;
+            'outer: for &p in &pos {
+                for &q in &neg {
+                    let r = resolve(&self.ca.get_ref(p), &self.ca.get_ref(q), v, &mut marks);
+                    if let Some(r) = r {
+                        if r.len() > cfg.resolvent_limit || resolvents.len() >= pos.len() + neg.len() {
+                            too_costly = true;
+                            break 'outer;
+                        }
+                        resolvents.push(r);
+                    }
+                }
+            }
+            if too_costly {
+                continue;
+            }
+
+            debug!("preprocess.eliminate {:?} ({} clauses -> {} resolvents)",
+                   v, pos.len() + neg.len(), resolvents.len());
+
+            // save the clauses of the smallest side for model reconstruction,
+            // with the pivot first, then the default value of `v`
+            let (saved, pivot) = if pos.len() > neg.len() { (&neg, !pos_lit) } else { (&pos, pos_lit) };
+            for &cr in saved.iter() {
+                let mut c = vec![pivot];
+                c.extend(self.ca.get_ref(cr).iter().cloned().filter(|&x| x != pivot));
+                self.elim_clauses.push(c);
+            }
+            self.elim_clauses.push(vec![!pivot]);
+
+            // add resolvents before removing the clauses they are derived from
+            let mut units = vec![];
+            for r in resolvents.drain(..) {
+                let mut lits: Vec<Lit> = vec![];
+                let mut satisfied = false;
+                for &lit in &r {
+                    let value = self.v.value_lit(lit);
+                    if value == lbool::TRUE { satisfied = true; }
+                    else if value == lbool::UNDEF { lits.push(lit); }
+                }
+                if satisfied {
+                    continue;
+                }
+                match lits.len() {
+                    0 => {
+                        self.ok = false;
+                        return false;
+                    },
+                    1 => units.push(lits[0]),
+                    _ => {
+                        if self.produce_proof { self.proof.create_clause(&lits); }
+                        let cr = self.ca.alloc_with_learnt(&lits, false);
+                        self.clauses.push(cr);
+                        self.attach_clause(cr);
+                        self.add_occurrences(cr);
+                        for &lit in &lits {
+                            occs[lit.idx() as usize].push(cr);
+                        }
+                    },
+                }
+            }
+            for &cr in pos.iter().chain(neg.iter()) {
+                self.pp_remove_clause(cr);
+            }
+            self.set_eliminated(v);
+            report.eliminated_vars += 1;
+
+            for lit in units {
+                if !self.pp_add_unit(lit) {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// Vivification: for each clause `l1 \/ ... \/ ln`, propagate `!l1, ..., !li`
+    /// in turn; a conflict, or `li+1` becoming true, allows shortening the clause.
+    fn pp_vivify(&mut self, cfg: &Preprocessor) -> bool {
+        let start_props = self.propagations;
+        let crs = self.clauses.clone();
+        let mut lits = vec![];
+        let mut new_lits = vec![];
+
+        for cr in crs {
+            if self.propagations - start_props > cfg.prop_budget || !self.within_budget() {
+                break;
+            }
+            lits.clear();
+            {
+                let c = self.ca.get_ref(cr);
+                if c.mark() == 1 {
+                    continue;
+                }
+                lits.extend(c.iter().cloned());
+            }
+            if lits.iter().any(|&lit| self.v.value_lit(lit) == lbool::TRUE) {
+                continue;
+            }
+
+            // the clause must not propagate itself
+            self.v.detach_clause(&mut self.ca, &mut self.watches_data, cr, true);
+            new_lits.clear();
+            for &lit in &lits {
+                let value = self.v.value_lit(lit);
+                if value == lbool::TRUE {
+                    // implied by the negation of the previous literals
+                    new_lits.push(lit);
+                    break;
+                } else if value == lbool::UNDEF {
+                    new_lits.push(lit);
+                    self.new_decision_level();
+                    self.v.unchecked_enqueue(!lit, CRef::UNDEF);
+                    if self.propagate() != CRef::UNDEF {
+                        break;
+                    }
+                }
+            }
+            self.cancel_until(0);
+
+            if new_lits.len() < lits.len() {
+                debug!("preprocess.vivify [{:?}] into [{:?}]", lits, new_lits);
+            }
+            if !self.pp_rewrite_detached(cr, &new_lits) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Remove `v` from the set of decision variables, for good.
+    fn set_eliminated(&mut self, v: Var) {
+        self.eliminated[v] = true;
+        self.set_decision_var(v, false);
+    }
+}
+
+/// Does `c` subsume `d`, or can it strengthen it?
+///
+/// Returns `Some(Lit::UNDEF)` if `c` subsumes `d`, `Some(l)` if `l` is the only
+/// literal of `c` occurring negated in `d` (so `!l` can be removed from `d`),
+/// and `None` otherwise. `marks` must be all `false`, and is left that way.
+fn subsumes(c: &[Lit], d: &[Lit], marks: &mut [bool]) -> Option<Lit> {
+    for &lit in d {
+        marks[lit.idx() as usize] = true;
+    }
+    let mut res = Some(Lit::UNDEF);
+    for &lit in c {
+        if marks[lit.idx() as usize] {
+            continue;
+        } else if res == Some(Lit::UNDEF) && marks[(!lit).idx() as usize] {
+            res = Some(lit);
+        } else {
+            res = None;
+            break;
+        }
+    }
+    for &lit in d {
+        marks[lit.idx() as usize] = false;
+    }
+    res
+}
+
+/// Resolvent of `p` and `q` on `v`, or `None` if it is a tautology.
+/// `marks` must be all `false`, and is left that way.
+fn resolve(p: &::clause::ClauseRef, q: &::clause::ClauseRef, v: Var, marks: &mut [bool]) -> Option<Vec<Lit>> {
+    let mut res = vec![];
+    for &lit in p.iter() {
+        if lit.var() != v {
+            marks[lit.idx() as usize] = true;
+            res.push(lit);
+        }
+    }
+    let mut tautology = false;
+    for &lit in q.iter() {
+        if lit.var() == v || marks[lit.idx() as usize] {
+            continue;
+        } else if marks[(!lit).idx() as usize] {
+            tautology = true;
+            break;
+        }
+        res.push(lit);
+    }
+    for &lit in p.iter() {
+        marks[lit.idx() as usize] = false;
+    }
+    if tautology { None } else { Some(res) }
+}
+
+/// Strongly connected components of the graph on literals given by `succ`,
+/// in reverse topological order (Tarjan's algorithm, without recursion).
+pub(super) fn tarjan_scc(succ: &[Vec<Lit>]) -> Vec<Vec<Lit>> {
+    const UNVISITED: u32 = !0;
+    let n = succ.len();
+    let mut index = vec![UNVISITED; n];
+    let mut lowlink = vec![0; n];
+    let mut on_stack = vec![false; n];
+    let mut stack: Vec<usize> = vec![];
+    let mut call_stack: Vec<(usize, usize)> = vec![]; // (node, next successor)
+    let mut next_index = 0;
+    let mut comps = vec![];
+
+    for root in 0..n {
+        if index[root] != UNVISITED || succ[root].is_empty() {
+            continue;
+        }
+        call_stack.push((root, 0));
+        index[root] = next_index;
+        lowlink[root] = next_index;
+        next_index += 1;
+        stack.push(root);
+        on_stack[root] = true;
+
+        while let Some(&mut (node, ref mut i)) = call_stack.last_mut() {
+            if *i < succ[node].len() {
+                let w = succ[node][*i].idx() as usize;
+                *i += 1;
+                if index[w] == UNVISITED {
+                    index[w] = next_index;
+                    lowlink[w] = next_index;
+                    next_index += 1;
+                    stack.push(w);
+                    on_stack[w] = true;
+                    call_stack.push((w, 0));
+                } else if on_stack[w] {
+                    lowlink[node] = cmp::min(lowlink[node], index[w]);
+                }
+            } else {
+                call_stack.pop();
+                if let Some(&(parent, _)) = call_stack.last() {
+                    lowlink[parent] = cmp::min(lowlink[parent], lowlink[node]);
+                }
+                if lowlink[node] == index[node] {
+                    let mut comp = vec![];
+                    loop {
+                        let w = stack.pop().expect("empty SCC stack");
+                        on_stack[w] = false;
+                        comp.push(Lit::from_idx(w as u32));
+                        if w == node { break; }
+                    }
+                    comps.push(comp);
+                }
+            }
+        }
+    }
+    comps
+}
@@ -0,0 +1,189 @@
+/*****************************************************************************************[structure.rs]
+Copyright (c) 2006,      Niklas Een, Niklas Sorensson (MiniSat)
+Copyright (c) 2007-2010, Niklas Sorensson (MiniSat)
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or
+substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+**************************************************************************************************/
+
+//! Recognition of tractable classes of formulas.
+//!
+//! Horn and dual-Horn formulas are decided by unit propagation alone, and
+//! 2-SAT formulas by computing the strongly connected components of their
+//! implication graph. Both take linear time.
+
+use {lbool, Lit, Var};
+use clause::CRef;
+use interface::SolverInterface;
+use super::Solver;
+use super::preprocess::tarjan_scc;
+
+/// Syntactic class of the clauses of a solver, see `Solver::detect_structure`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormulaClass {
+    /// Each clause has at most one positive literal
+    Horn,
+    /// Each clause has at most one negative literal
+    DualHorn,
+    /// Each clause has at most two literals
+    TwoSat,
+    /// None of the above
+    General,
+}
+
+impl Solver {
+    /// Classify the original clauses, ignoring literals false at level 0 and
+    /// clauses satisfied at level 0.
+    ///
+    /// When several classes apply, `Horn` is preferred to `DualHorn`, and
+    /// `DualHorn` to `TwoSat`.
+    pub fn detect_structure(&self) -> FormulaClass {
+        assert_eq!(self.v.decision_level(), 0);
+        let (mut horn, mut dual_horn, mut two_sat) = (true, true, true);
+        for &cr in &self.clauses {
+            let c = self.ca.get_ref(cr);
+            if c.mark() == 1 || c.iter().any(|&p| self.v.value_lit(p) == lbool::TRUE) {
+                continue;
+            }
+            let (mut pos, mut neg) = (0, 0);
+            for &p in c.iter().filter(|&&p| self.v.value_lit(p) == lbool::UNDEF) {
+                if p.sign() { pos += 1 } else { neg += 1 }
+            }
+            horn &= pos <= 1;
+            dual_horn &= neg <= 1;
+            two_sat &= pos + neg <= 2;
+            if !(horn || dual_horn || two_sat) {
+                return FormulaClass::General;
+            }
+        }
+        if horn {
+            FormulaClass::Horn
+        } else if dual_horn {
+            FormulaClass::DualHorn
+        } else if two_sat {
+            FormulaClass::TwoSat
+        } else {
+            FormulaClass::General
+        }
+    }
+
+    /// Solve without assumptions, using a linear-time algorithm if the formula is
+    /// Horn, dual-Horn or 2-SAT (see `detect_structure`), and `solve_limited` otherwise.
+    ///
+    /// The linear-time algorithms do not learn clauses, and do not produce a proof,
+    /// so `solve_limited` is always used if proof production is enabled.
+    pub fn solve_structured(&mut self) -> lbool {
+        assert_eq!(self.v.decision_level(), 0);
+        if self.produce_proof || self.lrat.is_some() || !self.ok {
+            return self.solve_limited(&[]);
+        }
+        let status = self.solve_structured_main();
+        self.last_result = status;
+        status
+    }
+
+    fn solve_structured_main(&mut self) -> lbool {
+        self.assumptions.clear();
+        self.conflict.clear();
+        self.model.clear();
+        if self.propagate() != CRef::UNDEF {
+            self.solves += 1;
+            self.ok = false;
+            return lbool::FALSE;
+        }
+        // `solve_limited` counts the solve itself
+        let class = self.detect_structure();
+        if class != FormulaClass::General {
+            self.solves += 1;
+        }
+        match class {
+            FormulaClass::Horn => {
+                // after propagation, each clause that is not satisfied has at least
+                // two unassigned literals, among which a negative one
+                self.structured_model(|_| false);
+                lbool::TRUE
+            }
+            FormulaClass::DualHorn => {
+                self.structured_model(|_| true);
+                lbool::TRUE
+            }
+            FormulaClass::TwoSat => {
+                match self.solve_two_sat() {
+                    Some(vals) => {
+                        self.structured_model(|v| vals[v.idx() as usize]);
+                        lbool::TRUE
+                    }
+                    None => {
+                        self.ok = false;
+                        lbool::FALSE
+                    }
+                }
+            }
+            FormulaClass::General => self.solve_limited(&[]),
+        }
+    }
+
+    /// Build the model from the level 0 assignment, completed by `default`.
+    fn structured_model<F: Fn(Var) -> bool>(&mut self, default: F) {
+        self.model.clear();
+        for v in self.vars() {
+            let val = self.v.value(v);
+            self.model.push(if val == lbool::UNDEF { lbool::new(default(v)) } else { val });
+        }
+        if !self.elim_clauses.is_empty() {
+            self.extend_model();
+        }
+    }
+
+    /// Solve the binary clauses that are not satisfied at level 0, in which both
+    /// literals are unassigned. Returns the value of each variable, or `None` if
+    /// they are unsatisfiable.
+    fn solve_two_sat(&self) -> Option<Vec<bool>> {
+        let mut succ: Vec<Vec<Lit>> = vec![vec![]; 2 * self.num_vars() as usize];
+        for &cr in &self.clauses {
+            let c = self.ca.get_ref(cr);
+            if c.mark() == 1 || c.iter().any(|&p| self.v.value_lit(p) == lbool::TRUE) {
+                continue;
+            }
+            let mut lits = c.iter().filter(|&&p| self.v.value_lit(p) == lbool::UNDEF);
+            let (a, b) = match (lits.next(), lits.next()) {
+                (Some(&a), Some(&b)) => (a, b),
+                _ => unreachable!("clause is not binary after propagation"),
+            };
+            succ[(!a).idx() as usize].push(b);
+            succ[(!b).idx() as usize].push(a);
+        }
+
+        // components are in reverse topological order: a literal is made true
+        // if its component comes before the one of its negation
+        const NONE: usize = !0;
+        let mut comp = vec![NONE; succ.len()];
+        for (i, scc) in tarjan_scc(&succ).iter().enumerate() {
+            for &p in scc {
+                comp[p.idx() as usize] = i;
+            }
+        }
+        let mut vals = vec![false; self.num_vars() as usize];
+        for v in self.vars() {
+            let (pos, neg) = (Lit::new(v, true), Lit::new(v, false));
+            let (c_pos, c_neg) = (comp[pos.idx() as usize], comp[neg.idx() as usize]);
+            if c_pos != NONE && c_pos == c_neg {
+                return None;
+            }
+            vals[v.idx() as usize] = c_pos < c_neg;
+        }
+        Some(vals)
+    }
+}
@@ -19,9 +19,12 @@ DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE,
 OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 **************************************************************************************************/
 
+use std::cmp;
 use std::io::{self, BufRead};
 use interface::SolverInterface;
 use {Lit, Var, lbool};
+#[cfg(feature="parallel")]
+use rayon::{self, prelude::*};
 
 /// `parse(input, solver)` adds the content of `input` to the solver
 ///
@@ -33,6 +36,23 @@ pub fn parse<S: SolverInterface, R: BufRead>(
     is_strict: bool,
     incremental: bool,
 ) -> io::Result<()> {
+    parse_with_header(input, solver, is_strict, incremental, |_, _, _| ())
+}
+
+/// Same as `parse`, but calls `on_header(solver, num_vars, num_clauses)` when
+/// the `p cnf` header is read.
+pub(crate) fn parse_with_header<S, R, F>(
+    input: &mut R,
+    solver: &mut S,
+    is_strict: bool,
+    incremental: bool,
+    mut on_header: F,
+) -> io::Result<()>
+where
+    S: SolverInterface,
+    R: BufRead,
+    F: FnMut(&mut S, u32, u32),
+{
     let mut lits = vec![];
     // let mut num_vars = 0;
     let mut num_clauses = 0;
@@ -50,9 +70,9 @@ pub fn parse<S: SolverInterface, R: BufRead>(
             if &header != b"p cnf" {
                 return parse_error(format!("PARSE ERROR! Unexpected char: p"));
             }
-            // num_vars = parse_int(input)?;
-            parse_int(input)?;
+            let num_vars = parse_int(input)?;
             num_clauses = parse_int(input)?;
+            on_header(solver, cmp::max(num_vars, 0) as u32, cmp::max(num_clauses, 0) as u32);
         // eprintln!("num_vars = {}", num_vars);
         // eprintln!("num_clauses = {}", num_clauses);
         } else if ch == Some(b'c') {
@@ -84,6 +104,146 @@ pub fn parse<S: SolverInterface, R: BufRead>(
     Ok(())
 }
 
+/// Parse the DIMACS problem `input`, held in memory, using several threads.
+///
+/// The input is cut into chunks of whole lines, which are parsed in parallel;
+/// the resulting clauses are then added to `solver` on the calling thread.
+/// The variables declared in the `p cnf` header are created upfront.
+/// The incremental format is not supported, and the header is never checked.
+#[cfg(feature="parallel")]
+pub fn parse_parallel<S: SolverInterface>(input: &[u8], solver: &mut S) -> io::Result<()> {
+    parse_parallel_with_header(input, solver, |_, _, _| ())
+}
+
+/// Same as `parse_parallel`, but calls `on_header(solver, num_vars, num_clauses)`
+/// before adding any clause, if the input has a `p cnf` header.
+#[cfg(feature="parallel")]
+pub(crate) fn parse_parallel_with_header<S, F>(
+    input: &[u8],
+    solver: &mut S,
+    on_header: F,
+) -> io::Result<()>
+where
+    S: SolverInterface,
+    F: FnOnce(&mut S, u32, u32),
+{
+    // cut after newlines, so that no token, comment or header is split
+    let n_chunks = 4 * rayon::current_num_threads();
+    let chunk_len = input.len() / cmp::max(n_chunks, 1) + 1;
+    let mut chunks = vec![];
+    let mut start = 0;
+    while start < input.len() {
+        let mut end = cmp::min(start + chunk_len, input.len());
+        while end < input.len() && input[end - 1] != b'\n' {
+            end += 1;
+        }
+        chunks.push(&input[start..end]);
+        start = end;
+    }
+    let parsed: Vec<ParsedChunk> =
+        chunks.par_iter().map(|chunk| parse_chunk(chunk)).collect::<io::Result<_>>()?;
+
+    if let Some((num_vars, num_clauses)) = parsed.iter().filter_map(|c| c.header).next() {
+        on_header(solver, num_vars, num_clauses);
+        while solver.num_vars() < num_vars {
+            solver.new_var_default();
+        }
+    }
+    // a clause may span several chunks
+    let mut lits = vec![];
+    for chunk in &parsed {
+        for &parsed_lit in &chunk.lits {
+            if parsed_lit == 0 {
+                solver.add_clause_reuse(&mut lits);
+                lits.clear();
+                continue;
+            }
+            let var = (parsed_lit.abs() - 1) as u32;
+            while var >= solver.num_vars() {
+                solver.new_var_default();
+            }
+            lits.push(Lit::new(Var::from_idx(var), parsed_lit > 0));
+        }
+    }
+    if !lits.is_empty() {
+        return parse_error("PARSE ERROR! Unexpected EOF".to_string());
+    }
+    Ok(())
+}
+
+/// Result of parsing one chunk of lines: the header, if any, and the
+/// literals, each clause being terminated by `0`.
+#[cfg(feature="parallel")]
+struct ParsedChunk {
+    header: Option<(u32, u32)>,
+    lits: Vec<i32>,
+}
+
+#[cfg(feature="parallel")]
+fn parse_chunk(chunk: &[u8]) -> io::Result<ParsedChunk> {
+    let mut res = ParsedChunk { header: None, lits: vec![] };
+    for line in chunk.split(|&ch| ch == b'\n') {
+        let mut tokens = line.split(|&ch| is_whitespace(Some(ch))).filter(|tok| !tok.is_empty());
+        while let Some(tok) = tokens.next() {
+            match tok[0] {
+                b'c' => break, // comment until the end of the line
+                b'p' => {
+                    if tok != b"p" || tokens.next() != Some(&b"cnf"[..]) {
+                        return parse_error("PARSE ERROR! Unexpected char: p".to_string());
+                    }
+                    let num_vars = parse_int_token(tokens.next())?;
+                    let num_clauses = parse_int_token(tokens.next())?;
+                    res.header = Some((cmp::max(num_vars, 0) as u32, cmp::max(num_clauses, 0) as u32));
+                }
+                _ => res.lits.push(parse_int_token(Some(tok))?),
+            }
+        }
+    }
+    Ok(res)
+}
+
+#[cfg(feature="parallel")]
+fn parse_int_token(tok: Option<&[u8]>) -> io::Result<i32> {
+    let tok = match tok {
+        Some(tok) => tok,
+        None => return parse_error("PARSE ERROR! Unexpected EOF".to_string()),
+    };
+    let (neg, digits) = match tok[0] {
+        b'-' => (true, &tok[1..]),
+        b'+' => (false, &tok[1..]),
+        _ => (false, tok),
+    };
+    if digits.is_empty() {
+        return parse_error("PARSE ERROR! Unexpected EOF".to_string());
+    }
+    let mut val: i32 = 0;
+    for &ch in digits {
+        if !ch.is_ascii_digit() {
+            return parse_error(format!("PARSE ERROR! Unexpected char: {}", ch as char));
+        }
+        val = match val.checked_mul(10).and_then(|val| val.checked_add((ch - b'0') as i32)) {
+            Some(val) => val,
+            None => return parse_error(format!(
+                "PARSE ERROR! Integer too large: {}", String::from_utf8_lossy(tok))),
+        };
+    }
+    Ok(if neg { -val } else { val })
+}
+
+/// Read whitespace-separated integers until the end of `input`, skipping
+/// comment lines (starting with `c`).
+pub(crate) fn read_ints<R: BufRead>(input: &mut R) -> io::Result<Vec<i32>> {
+    let mut ints = vec![];
+    loop {
+        skip_whitespace(input)?;
+        match next_byte(input)? {
+            None => return Ok(ints),
+            Some(b'c') => skip_line(input)?,
+            Some(_) => ints.push(parse_int(input)?),
+        }
+    }
+}
+
 fn read_clause<S: SolverInterface, R: BufRead>(
     input: &mut R,
     solver: &mut S,
@@ -167,3 +327,32 @@ fn next_byte<R: BufRead>(input: &mut R) -> io::Result<Option<u8>> {
 fn parse_error<T>(message: String) -> io::Result<T> {
     Err(io::Error::new(io::ErrorKind::InvalidInput, message))
 }
+
+#[cfg(test)]
+mod test {
+
+    #[cfg(feature="parallel")]
+    #[test]
+    fn test_parse_int_token() {
+        use super::parse_int_token;
+        assert_eq!(parse_int_token(Some(b"42")).unwrap(), 42);
+        assert_eq!(parse_int_token(Some(b"-17")).unwrap(), -17);
+        assert_eq!(parse_int_token(Some(b"+3")).unwrap(), 3);
+        assert_eq!(parse_int_token(Some(b"2147483647")).unwrap(), i32::MAX);
+        assert!(parse_int_token(Some(b"2147483648")).is_err());
+        assert!(parse_int_token(Some(b"-99999999999999999999")).is_err());
+        assert!(parse_int_token(Some(b"-")).is_err());
+        assert!(parse_int_token(Some(b"1x")).is_err());
+        assert!(parse_int_token(None).is_err());
+    }
+
+    #[test]
+    fn test_read_ints() {
+        use super::read_ints;
+        let mut input = &b"c comment\n1 -2  +3\n\tc another comment\n0\n"[..];
+        assert_eq!(read_ints(&mut input).unwrap(), vec![1, -2, 3, 0]);
+        assert_eq!(read_ints(&mut &b""[..]).unwrap(), vec![]);
+        assert!(read_ints(&mut &b"1 - 2"[..]).is_err());
+        assert!(read_ints(&mut &b"1 two"[..]).is_err());
+    }
+}
@@ -13,6 +13,12 @@ pub trait SolverInterface {
     fn num_clauses(&self) -> u32;
     fn num_conflicts(&self) -> u32;
 
+    /// Returns `false` once the clauses are known to be unsatisfiable without
+    /// any assumption, i.e. after a conflict at level 0. This is final: no clause
+    /// can be added anymore, and every `solve_limited` returns `lbool::FALSE`.
+    ///
+    /// UNSAT under assumptions does not change it: the assumptions responsible
+    /// for the conflict are available in the unsat core instead.
     fn is_ok(&self) -> bool;
 
     /// Print some current statistics to standard output.
@@ -49,6 +55,11 @@ pub trait SolverInterface {
     /// Query model for var
     ///
     /// Precondition: last result was `Sat` (ie `lbool::TRUE`)
+    ///
+    /// The model only covers decision variables and the variables they imply:
+    /// it is `UNDEF` for a non-decision variable left unassigned, which may then take
+    /// any value that satisfies the clauses it occurs in (if it occurs in none,
+    /// any value at all), and for variables created after the last `solve`.
     fn value_var(&self, Var) -> lbool;
 
     /// Query model for lit
@@ -73,3 +84,17 @@ pub trait SolverInterface {
     fn unsat_core_contains_var(&self, v: Var) -> bool;
 }
 
+
+/// External propagator, called during search each time unit propagation
+/// reaches a fixpoint without conflict (lazy clause generation).
+pub trait Propagator {
+    /// Examine the current assignment, given as the trail in assignment order.
+    ///
+    /// Returns `Some(c)` to add the clause `c` to the solver. The clause must be
+    /// implied by the problem (including the theory the propagator implements);
+    /// if it is unit or false under `trail`, the solver propagates it or handles
+    /// the conflict, backjumping if needed. The propagator is then called again,
+    /// until it returns `None`. When the trail is a complete assignment, returning
+    /// `None` accepts it as a model.
+    fn propagate(&mut self, trail: &[Lit]) -> Option<Vec<Lit>>;
+}
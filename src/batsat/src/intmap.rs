@@ -107,12 +107,37 @@ impl<K: AsIndex, V> IntMap<K, V> {
         self.map.clear();
         self.map.shrink_to_fit();
     }
+    /// Number of keys in the map, that is, one more than the largest key.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+    /// Iterate over the keys and their values, in increasing order of keys.
+    ///
+    /// ```
+    /// use batsat::{Var, VMap};
+    /// let mut m: VMap<f64> = VMap::new();
+    /// m.insert(Var::from_idx(1), 2.5, 0.0);
+    /// assert_eq!(m.len(), 2);
+    /// let entries: Vec<(Var, f64)> = m.iter().map(|(v, &x)| (v, x)).collect();
+    /// assert_eq!(entries, vec![(Var::from_idx(0), 0.0), (Var::from_idx(1), 2.5)]);
+    /// ```
     pub fn iter(& self) -> impl iter::Iterator<Item=(K,&V)> {
         self.map.iter().enumerate().map(|(k, v)| (K::from_index(k), v))
     }
     pub fn iter_mut(&mut self) -> impl iter::Iterator<Item=(K,&mut V)> {
         self.map.iter_mut().enumerate().map(|(k, v)| (K::from_index(k), v))
     }
+    /// Iterate over the keys, in increasing order.
+    pub fn keys(&self) -> impl iter::Iterator<Item=K> {
+        (0..self.map.len()).map(K::from_index)
+    }
+    /// Iterate over the values, in increasing order of keys.
+    pub fn values(&self) -> impl iter::Iterator<Item=&V> {
+        self.map.iter()
+    }
 }
 
 impl<K: AsIndex, V> ops::Index<K> for IntMap<K, V> {
@@ -40,6 +40,9 @@ pub extern crate log;
 
 extern crate smallvec;
 
+#[cfg(feature="parallel")]
+extern crate rayon;
+
 //======== PUBLIC INTERFACE ============
 
 pub mod alloc;
@@ -48,7 +51,11 @@ pub mod clause;
 pub mod dimacs;
 pub mod core;
 pub mod interface;
+pub mod session;
 
-pub use interface::SolverInterface;
-pub use core::{Solver, SolverOpts};
+pub use interface::{SolverInterface, Propagator};
+pub use core::{Solver, SolverOpts, SolverBuilder, AddResult, ClauseHandle, Preprocessor,
+    PreprocessReport, Statistics, FormulaClass, SimplifyReport, WatchSelect, SolveStatus, UipMode,
+    LearntSort, SatResult, SolverError};
+pub use session::IncrementalSession;
 pub use clause::{lbool, Lit, Var, LMap, LSet, VMap, display::Print};
@@ -0,0 +1,196 @@
+/*****************************************************************************************[session.rs]
+Copyright (c) 2018-2018, Masaki Hara
+
+Permission is hereby granted, free of charge, to any person obtaining a copy of this software and
+associated documentation files (the "Software"), to deal in the Software without restriction,
+including without limitation the rights to use, copy, modify, merge, publish, distribute,
+sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all copies or
+substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT
+OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+**************************************************************************************************/
+
+//! Incremental solving with `push`/`pop` frames, in the style of SMT-LIB's
+//! `check-sat`.
+
+use interface::SolverInterface;
+use core::Solver;
+use {lbool, Lit, Var};
+
+/// A solver used through a sequence of `check` queries.
+///
+/// Clauses are added to the current frame; `pop` retracts all the clauses of the
+/// innermost frame. This is implemented with one activation literal per frame:
+/// each clause `c` of a frame is added as `c \/ !a`, and `a` is assumed while the
+/// frame is active. Popping a frame adds the unit clause `!a`, so the clauses of
+/// the frame, and the learnt clauses derived from them, become satisfied and are
+/// eventually removed. Other learnt clauses are kept across queries.
+#[derive(Debug)]
+pub struct IncrementalSession {
+    solver: Solver,
+    /// Activation literal of each frame, innermost last.
+    frames: Vec<Lit>,
+    /// Assumptions for the next `check`.
+    assumptions: Vec<Lit>,
+    /// Unsat core of the last `check`, without activation literals.
+    core: Vec<Lit>,
+}
+
+impl Default for IncrementalSession {
+    fn default() -> Self {
+        Self::new(Solver::default())
+    }
+}
+
+impl IncrementalSession {
+    /// Create a session around `solver`, which may already contain clauses
+    /// (they belong to the outermost frame and cannot be popped).
+    pub fn new(solver: Solver) -> Self {
+        IncrementalSession {
+            solver,
+            frames: vec![],
+            assumptions: vec![],
+            core: vec![],
+        }
+    }
+
+    /// The underlying solver.
+    pub fn solver(&self) -> &Solver {
+        &self.solver
+    }
+
+    /// The underlying solver. Clauses added directly to it are not retracted by `pop`.
+    pub fn solver_mut(&mut self) -> &mut Solver {
+        &mut self.solver
+    }
+
+    /// Retrieve the underlying solver.
+    pub fn into_inner(self) -> Solver {
+        self.solver
+    }
+
+    /// Create a new variable.
+    pub fn new_var(&mut self) -> Var {
+        self.solver.new_var_default()
+    }
+
+    /// Number of frames pushed and not yet popped.
+    pub fn level(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Open a new frame.
+    pub fn push(&mut self) {
+        let act = Lit::new(self.solver.new_var(lbool::UNDEF, false), true);
+        self.frames.push(act);
+    }
+
+    /// Retract all the clauses added since the matching `push`.
+    ///
+    /// Panics if there is no frame to pop.
+    pub fn pop(&mut self) {
+        let act = self.frames.pop().expect("pop without matching push");
+        self.solver.add_clause_reuse(&mut vec![!act]);
+        self.solver.simplify();
+    }
+
+    /// Add a clause to the current frame. Returns `false` if the solver
+    /// is unsatisfiable independently of the frames.
+    pub fn add_clause(&mut self, lits: &[Lit]) -> bool {
+        let mut c = lits.to_vec();
+        if let Some(&act) = self.frames.last() {
+            c.push(!act);
+        }
+        self.solver.add_clause_reuse(&mut c)
+    }
+
+    /// Assume `lit` for the next `check` only.
+    pub fn assume(&mut self, lit: Lit) {
+        self.assumptions.push(lit);
+    }
+
+    /// Check satisfiability of the clauses of all active frames, under the
+    /// assumptions given since the last `check`, which are then cleared.
+    ///
+    /// After `lbool::TRUE`, the model is available from `solver()`; after
+    /// `lbool::FALSE`, see `unsat_core`.
+    pub fn check(&mut self) -> lbool {
+        let mut assumps = self.frames.clone();
+        assumps.extend_from_slice(&self.assumptions);
+        self.assumptions.clear();
+        let res = self.solver.solve_limited(&assumps);
+
+        self.core.clear();
+        if res == lbool::FALSE {
+            let frames = &self.frames;
+            // the core contains negated assumptions
+            self.core.extend(self.solver.unsat_core().iter().cloned()
+                .filter(|&lit| !frames.contains(&!lit)));
+        }
+        res
+    }
+
+    /// After `check` returned `lbool::FALSE`, the subset of the assumptions
+    /// responsible for unsatisfiability, negated (empty if the active frames
+    /// are unsatisfiable by themselves).
+    pub fn unsat_core(&self) -> &[Lit] {
+        &self.core
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    /// clauses of a popped frame are retracted, and assumptions only hold for one check
+    #[test]
+    fn test_push_pop() {
+        use super::IncrementalSession;
+        use interface::SolverInterface;
+        use {lbool, Lit};
+        let mut s = IncrementalSession::default();
+        let x: Vec<Lit> = (0..3).map(|_| Lit::new(s.new_var(), true)).collect();
+        assert!(s.add_clause(&[x[0], x[1]]));
+        assert_eq!(s.check(), lbool::TRUE);
+
+        s.push();
+        assert_eq!(s.level(), 1);
+        assert!(s.add_clause(&[!x[0]]));
+        assert!(s.add_clause(&[!x[1], x[2]]));
+        assert_eq!(s.check(), lbool::TRUE);
+        assert_eq!(s.solver().value_lit(x[2]), lbool::TRUE);
+        s.assume(!x[2]);
+        assert_eq!(s.check(), lbool::FALSE);
+        assert_eq!(s.unsat_core(), &[x[2]]);
+        assert!(s.solver().is_ok());
+        // the assumption is gone
+        assert_eq!(s.check(), lbool::TRUE);
+
+        s.push();
+        assert!(s.add_clause(&[!x[1]]));
+        assert_eq!(s.check(), lbool::FALSE);
+        assert!(s.unsat_core().is_empty());
+        s.pop();
+        assert_eq!(s.check(), lbool::TRUE);
+
+        s.pop();
+        assert_eq!(s.level(), 0);
+        s.assume(!x[1]);
+        s.assume(!x[2]);
+        assert_eq!(s.check(), lbool::TRUE);
+        assert_eq!(s.solver().value_lit(x[0]), lbool::TRUE);
+    }
+
+    #[test]
+    #[should_panic(expected = "pop without matching push")]
+    fn test_pop_empty() {
+        use super::IncrementalSession;
+        IncrementalSession::default().pop();
+    }
+}